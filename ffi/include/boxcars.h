@@ -0,0 +1,109 @@
+#ifndef BOXCARS_H
+#define BOXCARS_H
+
+/* This file is generated by the build script from src/lib.rs. Do not edit. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// When to validate the replay's crc (see `boxcars::CrcCheck`)
+typedef enum BoxcarsCrcCheck {
+  BOXCARS_CRC_CHECK_ALWAYS,
+  BOXCARS_CRC_CHECK_NEVER,
+  BOXCARS_CRC_CHECK_ON_ERROR,
+} BoxcarsCrcCheck;
+
+// How to handle the network data (see `boxcars::NetworkParse`)
+typedef enum BoxcarsNetworkParse {
+  BOXCARS_NETWORK_PARSE_ALWAYS,
+  BOXCARS_NETWORK_PARSE_NEVER,
+  BOXCARS_NETWORK_PARSE_IGNORE_ON_ERROR,
+  BOXCARS_NETWORK_PARSE_PARTIAL,
+} BoxcarsNetworkParse;
+
+// A parsed replay
+typedef struct BoxcarsReplay BoxcarsReplay;
+
+// The `ParserBuilder` options
+typedef struct BoxcarsParseOptions {
+  enum BoxcarsCrcCheck crc_check;
+  enum BoxcarsNetworkParse network_parse;
+  // The number of threads used to decode the network data
+  size_t threads;
+} BoxcarsParseOptions;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// The default options: check the crc on error, always parse the network data, and decode the
+// network data on a single thread
+struct BoxcarsParseOptions boxcars_default_options(void);
+
+// Parses the replay in the buffer, which is only read for the duration of the call. `options` may
+// be `NULL` for the default options. Returns `NULL` on failure.
+//
+// # Safety
+//
+// `data` must point to `len` readable bytes and `options` must be `NULL` or point to valid
+// options.
+struct BoxcarsReplay *boxcars_parse(const uint8_t *data,
+                                    size_t len,
+                                    const struct BoxcarsParseOptions *options);
+
+// Releases the replay. Passing `NULL` is a no-op.
+//
+// # Safety
+//
+// `replay` must be `NULL` or a replay returned by `boxcars_parse` that hasn't been released.
+void boxcars_replay_free(struct BoxcarsReplay *replay);
+
+// The replay's header (versions, game type, and properties) as a JSON object. Returns `NULL` on
+// failure.
+//
+// # Safety
+//
+// `replay` must be a live replay returned by `boxcars_parse`.
+char *boxcars_header_json(const struct BoxcarsReplay *replay);
+
+// The number of network frames, which is 0 when the network data wasn't parsed
+//
+// # Safety
+//
+// `replay` must be `NULL` or a live replay returned by `boxcars_parse`.
+size_t boxcars_frame_count(const struct BoxcarsReplay *replay);
+
+// The network frame at the index as a JSON object. Returns `NULL` on failure, such as an index
+// that is out of range.
+//
+// # Safety
+//
+// `replay` must be a live replay returned by `boxcars_parse`.
+char *boxcars_frame_json(const struct BoxcarsReplay *replay, size_t index);
+
+// When parsed with `BOXCARS_NETWORK_PARSE_PARTIAL`, the error that stopped decoding the network data.
+// Returns `NULL` if the network data was decoded completely.
+//
+// # Safety
+//
+// `replay` must be a live replay returned by `boxcars_parse`.
+char *boxcars_network_error(const struct BoxcarsReplay *replay);
+
+// The message of the last error on the calling thread, or `NULL` if there hasn't been one
+char *boxcars_last_error(void);
+
+// Releases a string returned by the library. Passing `NULL` is a no-op.
+//
+// # Safety
+//
+// `s` must be `NULL` or a string returned by the library that hasn't been released.
+void boxcars_string_free(char *s);
+
+#ifdef __cplusplus
+} // extern "C"
+#endif // __cplusplus
+
+#endif /* BOXCARS_H */
@@ -0,0 +1,354 @@
+//! # boxcars-ffi
+//!
+//! A C interface to boxcars, built as a shared library (`libboxcars_ffi.so`, `boxcars_ffi.dll`,
+//! or `libboxcars_ffi.dylib`) with the declarations in `include/boxcars.h`, which is generated
+//! from this file when the crate is built and checked to be up to date by the tests.
+//!
+//! The interface follows a few rules:
+//!
+//! - A replay is parsed into an opaque `BoxcarsReplay` that is released with
+//!   `boxcars_replay_free`
+//! - Every string returned is allocated by the library, NUL terminated, and must be released with
+//!   `boxcars_string_free`
+//! - Functions that fail return `NULL` and record an error message for the calling thread, which
+//!   is retrieved with `boxcars_last_error`
+//! - Panics are caught and reported as errors instead of unwinding into the caller
+//!
+//! ```c
+//! BoxcarsParseOptions options = boxcars_default_options();
+//! BoxcarsReplay *replay = boxcars_parse(data, len, &options);
+//! if (replay == NULL) {
+//!     char *error = boxcars_last_error();
+//!     fprintf(stderr, "%s\n", error);
+//!     boxcars_string_free(error);
+//!     return 1;
+//! }
+//!
+//! for (size_t i = 0; i < boxcars_frame_count(replay); i++) {
+//!     char *frame = boxcars_frame_json(replay, i);
+//!     puts(frame);
+//!     boxcars_string_free(frame);
+//! }
+//!
+//! boxcars_replay_free(replay);
+//! ```
+use boxcars::{CrcCheck, HeaderProp, NetworkParse, ParserBuilder, Replay};
+use serde::{Serialize, Serializer};
+use std::cell::RefCell;
+use std::ffi::CString;
+use std::os::raw::c_char;
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+use std::slice;
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// When to validate the replay's crc (see `boxcars::CrcCheck`)
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BoxcarsCrcCheck {
+    Always,
+    Never,
+    OnError,
+}
+
+/// How to handle the network data (see `boxcars::NetworkParse`)
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BoxcarsNetworkParse {
+    Always,
+    Never,
+    IgnoreOnError,
+    Partial,
+}
+
+/// The `ParserBuilder` options
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BoxcarsParseOptions {
+    pub crc_check: BoxcarsCrcCheck,
+    pub network_parse: BoxcarsNetworkParse,
+
+    /// The number of threads used to decode the network data
+    pub threads: usize,
+}
+
+/// A parsed replay
+pub struct BoxcarsReplay {
+    replay: Replay,
+}
+
+/// The header section of the replay
+#[derive(Serialize)]
+struct Header<'a> {
+    header_size: i32,
+    header_crc: u32,
+    major_version: i32,
+    minor_version: i32,
+    net_version: Option<i32>,
+    game_type: &'a str,
+    #[serde(serialize_with = "properties")]
+    properties: &'a [(String, HeaderProp)],
+}
+
+fn properties<S: Serializer>(
+    properties: &&[(String, HeaderProp)],
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_map(properties.iter().map(|(key, value)| (key, value)))
+}
+
+impl From<BoxcarsCrcCheck> for CrcCheck {
+    fn from(check: BoxcarsCrcCheck) -> Self {
+        match check {
+            BoxcarsCrcCheck::Always => CrcCheck::Always,
+            BoxcarsCrcCheck::Never => CrcCheck::Never,
+            BoxcarsCrcCheck::OnError => CrcCheck::OnError,
+        }
+    }
+}
+
+impl From<BoxcarsNetworkParse> for NetworkParse {
+    fn from(parse: BoxcarsNetworkParse) -> Self {
+        match parse {
+            BoxcarsNetworkParse::Always => NetworkParse::Always,
+            BoxcarsNetworkParse::Never => NetworkParse::Never,
+            BoxcarsNetworkParse::IgnoreOnError => NetworkParse::IgnoreOnError,
+            BoxcarsNetworkParse::Partial => NetworkParse::Partial,
+        }
+    }
+}
+
+fn set_error(message: String) {
+    LAST_ERROR.with(|x| *x.borrow_mut() = Some(message));
+}
+
+/// Runs the function, recording its error (or panic) for the thread and returning `NULL` instead
+fn guard<T, F>(f: F) -> *mut T
+where
+    F: FnOnce() -> Result<*mut T, String>,
+{
+    match panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(result)) => result,
+        Ok(Err(message)) => {
+            set_error(message);
+            ptr::null_mut()
+        }
+        Err(_) => {
+            set_error(String::from("boxcars panicked"));
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Transfers ownership of the string to the caller. Interior NUL bytes can't be represented and
+/// are replaced.
+fn into_c_string(s: String) -> *mut c_char {
+    let s = if s.contains('\0') {
+        s.replace('\0', "?")
+    } else {
+        s
+    };
+    CString::new(s).unwrap_or_default().into_raw()
+}
+
+fn to_json<T: Serialize>(value: &T) -> Result<*mut c_char, String> {
+    serde_json::to_string(value)
+        .map(into_c_string)
+        .map_err(|e| e.to_string())
+}
+
+unsafe fn replay_ref<'a>(replay: *const BoxcarsReplay) -> Result<&'a Replay, String> {
+    replay
+        .as_ref()
+        .map(|x| &x.replay)
+        .ok_or_else(|| String::from("replay is NULL"))
+}
+
+/// The default options: check the crc on error, always parse the network data, and decode the
+/// network data on a single thread
+#[no_mangle]
+pub extern "C" fn boxcars_default_options() -> BoxcarsParseOptions {
+    BoxcarsParseOptions {
+        crc_check: BoxcarsCrcCheck::OnError,
+        network_parse: BoxcarsNetworkParse::Always,
+        threads: 1,
+    }
+}
+
+/// Parses the replay in the buffer, which is only read for the duration of the call. `options` may
+/// be `NULL` for the default options. Returns `NULL` on failure.
+///
+/// # Safety
+///
+/// `data` must point to `len` readable bytes and `options` must be `NULL` or point to valid
+/// options.
+#[no_mangle]
+pub unsafe extern "C" fn boxcars_parse(
+    data: *const u8,
+    len: usize,
+    options: *const BoxcarsParseOptions,
+) -> *mut BoxcarsReplay {
+    guard(|| {
+        if data.is_null() {
+            return Err(String::from("data is NULL"));
+        }
+
+        let data = slice::from_raw_parts(data, len);
+        let options = options
+            .as_ref()
+            .copied()
+            .unwrap_or_else(|| boxcars_default_options());
+        let replay = ParserBuilder::new(data)
+            .with_crc_check(options.crc_check.into())
+            .with_network_parse(options.network_parse.into())
+            .with_network_threads(options.threads)
+            .parse()
+            .map_err(|e| e.to_string())?;
+
+        Ok(Box::into_raw(Box::new(BoxcarsReplay { replay })))
+    })
+}
+
+/// Releases the replay. Passing `NULL` is a no-op.
+///
+/// # Safety
+///
+/// `replay` must be `NULL` or a replay returned by `boxcars_parse` that hasn't been released.
+#[no_mangle]
+pub unsafe extern "C" fn boxcars_replay_free(replay: *mut BoxcarsReplay) {
+    if !replay.is_null() {
+        drop(Box::from_raw(replay));
+    }
+}
+
+/// The replay's header (versions, game type, and properties) as a JSON object. Returns `NULL` on
+/// failure.
+///
+/// # Safety
+///
+/// `replay` must be a live replay returned by `boxcars_parse`.
+#[no_mangle]
+pub unsafe extern "C" fn boxcars_header_json(replay: *const BoxcarsReplay) -> *mut c_char {
+    guard(|| {
+        let replay = replay_ref(replay)?;
+        to_json(&Header {
+            header_size: replay.header_size,
+            header_crc: replay.header_crc,
+            major_version: replay.major_version,
+            minor_version: replay.minor_version,
+            net_version: replay.net_version,
+            game_type: &replay.game_type,
+            properties: &replay.properties,
+        })
+    })
+}
+
+/// The number of network frames, which is 0 when the network data wasn't parsed
+///
+/// # Safety
+///
+/// `replay` must be `NULL` or a live replay returned by `boxcars_parse`.
+#[no_mangle]
+pub unsafe extern "C" fn boxcars_frame_count(replay: *const BoxcarsReplay) -> usize {
+    replay_ref(replay)
+        .ok()
+        .and_then(|x| x.network_frames.as_ref())
+        .map(|x| x.frames.len())
+        .unwrap_or(0)
+}
+
+/// The network frame at the index as a JSON object. Returns `NULL` on failure, such as an index
+/// that is out of range.
+///
+/// # Safety
+///
+/// `replay` must be a live replay returned by `boxcars_parse`.
+#[no_mangle]
+pub unsafe extern "C" fn boxcars_frame_json(
+    replay: *const BoxcarsReplay,
+    index: usize,
+) -> *mut c_char {
+    guard(|| {
+        let frame = replay_ref(replay)?
+            .network_frames
+            .as_ref()
+            .and_then(|x| x.frames.get(index))
+            .ok_or_else(|| format!("frame {} is out of range", index))?;
+        to_json(frame)
+    })
+}
+
+/// When parsed with `BOXCARS_NETWORK_PARSE_PARTIAL`, the error that stopped decoding the network data.
+/// Returns `NULL` if the network data was decoded completely.
+///
+/// # Safety
+///
+/// `replay` must be a live replay returned by `boxcars_parse`.
+#[no_mangle]
+pub unsafe extern "C" fn boxcars_network_error(replay: *const BoxcarsReplay) -> *mut c_char {
+    match replay_ref(replay) {
+        Ok(replay) => replay
+            .network_error
+            .as_ref()
+            .map(|e| into_c_string(e.to_string()))
+            .unwrap_or(ptr::null_mut()),
+        Err(_) => ptr::null_mut(),
+    }
+}
+
+/// The message of the last error on the calling thread, or `NULL` if there hasn't been one
+#[no_mangle]
+pub extern "C" fn boxcars_last_error() -> *mut c_char {
+    LAST_ERROR.with(|x| {
+        x.borrow()
+            .clone()
+            .map(into_c_string)
+            .unwrap_or(ptr::null_mut())
+    })
+}
+
+/// Releases a string returned by the library. Passing `NULL` is a no-op.
+///
+/// # Safety
+///
+/// `s` must be `NULL` or a string returned by the library that hasn't been released.
+#[no_mangle]
+pub unsafe extern "C" fn boxcars_string_free(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::CStr;
+
+    unsafe fn take(s: *mut c_char) -> String {
+        assert!(!s.is_null());
+        let result = CStr::from_ptr(s).to_string_lossy().into_owned();
+        boxcars_string_free(s);
+        result
+    }
+
+    #[test]
+    fn test_into_c_string_replaces_nul() {
+        let s = into_c_string(String::from("a\0b"));
+        assert_eq!(unsafe { take(s) }, "a?b");
+    }
+
+    #[test]
+    fn test_parse_error() {
+        let data = [0u8; 4];
+        let replay = unsafe { boxcars_parse(data.as_ptr(), data.len(), ptr::null()) };
+        assert!(replay.is_null());
+        assert!(!unsafe { take(boxcars_last_error()) }.is_empty());
+
+        let replay = unsafe { boxcars_parse(ptr::null(), 0, ptr::null()) };
+        assert!(replay.is_null());
+        assert_eq!(unsafe { take(boxcars_last_error()) }, "data is NULL");
+    }
+}
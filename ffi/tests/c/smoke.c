@@ -0,0 +1,81 @@
+/* Exercises the C interface: parses the replay given on the command line, reads the header and
+ * every frame, and checks that errors are reported. Exits with a non-zero status on failure. */
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+#include "boxcars.h"
+
+#define CHECK(cond)                                                          \
+    do {                                                                     \
+        if (!(cond)) {                                                       \
+            fprintf(stderr, "%s:%d: check failed: %s\n", __FILE__, __LINE__, \
+                    #cond);                                                  \
+            exit(1);                                                         \
+        }                                                                    \
+    } while (0)
+
+static unsigned char *read_file(const char *path, size_t *len) {
+    FILE *f = fopen(path, "rb");
+    CHECK(f != NULL);
+    CHECK(fseek(f, 0, SEEK_END) == 0);
+    long size = ftell(f);
+    CHECK(size > 0);
+    rewind(f);
+
+    unsigned char *data = malloc((size_t)size);
+    CHECK(data != NULL);
+    CHECK(fread(data, 1, (size_t)size, f) == (size_t)size);
+    fclose(f);
+    *len = (size_t)size;
+    return data;
+}
+
+int main(int argc, char **argv) {
+    CHECK(argc == 2);
+
+    size_t len;
+    unsigned char *data = read_file(argv[1], &len);
+
+    /* A truncated replay fails to parse with a message */
+    CHECK(boxcars_parse(data, 10, NULL) == NULL);
+    char *error = boxcars_last_error();
+    CHECK(error != NULL && strlen(error) > 0);
+    boxcars_string_free(error);
+
+    BoxcarsParseOptions options = boxcars_default_options();
+    options.crc_check = BOXCARS_CRC_CHECK_ALWAYS;
+    options.threads = 2;
+    BoxcarsReplay *replay = boxcars_parse(data, len, &options);
+    free(data);
+    CHECK(replay != NULL);
+
+    char *header = boxcars_header_json(replay);
+    CHECK(header != NULL);
+    CHECK(strstr(header, "\"game_type\":\"TAGame.Replay_Soccar_TA\"") != NULL);
+    CHECK(strstr(header, "\"TeamSize\"") != NULL);
+    boxcars_string_free(header);
+
+    size_t frames = boxcars_frame_count(replay);
+    CHECK(frames > 0);
+    for (size_t i = 0; i < frames; i++) {
+        char *frame = boxcars_frame_json(replay, i);
+        CHECK(frame != NULL && frame[0] == '{');
+        boxcars_string_free(frame);
+    }
+
+    CHECK(boxcars_frame_json(replay, frames) == NULL);
+    error = boxcars_last_error();
+    CHECK(error != NULL && strstr(error, "out of range") != NULL);
+    boxcars_string_free(error);
+
+    CHECK(boxcars_network_error(replay) == NULL);
+    boxcars_replay_free(replay);
+
+    /* Releasing NULL is a no-op */
+    boxcars_replay_free(NULL);
+    boxcars_string_free(NULL);
+
+    printf("%zu frames\n", frames);
+    return 0;
+}
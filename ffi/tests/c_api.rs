@@ -0,0 +1,60 @@
+//! Compiles the C program in `tests/c` against the generated header and the shared library, and
+//! runs it on a replay. The C compiler can be overridden with the `CC` environment variable.
+#![cfg(unix)]
+
+use std::env;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Builds the shared library, as cargo only builds the rlib for integration tests, and returns the
+/// directory it was built in, which is the parent of the test's `deps` directory
+fn build_library(crate_dir: &Path) -> PathBuf {
+    let exe = env::current_exe().unwrap();
+    let lib_dir = exe.parent().and_then(Path::parent).unwrap().to_path_buf();
+
+    let mut cargo = Command::new(env!("CARGO"));
+    cargo
+        .arg("build")
+        .arg("--lib")
+        .arg("--manifest-path")
+        .arg(crate_dir.join("Cargo.toml"));
+    if lib_dir.ends_with("release") {
+        cargo.arg("--release");
+    }
+
+    assert!(cargo.status().unwrap().success());
+    lib_dir
+}
+
+#[test]
+fn test_c_program() {
+    let crate_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let lib_dir = build_library(crate_dir);
+    let program = lib_dir.join("boxcars_ffi_smoke");
+    let compiler = env::var("CC").unwrap_or_else(|_| String::from("cc"));
+
+    let status = Command::new(compiler)
+        .arg(crate_dir.join("tests").join("c").join("smoke.c"))
+        .arg("-I")
+        .arg(crate_dir.join("include"))
+        .arg("-L")
+        .arg(&lib_dir)
+        .arg(format!("-Wl,-rpath,{}", lib_dir.display()))
+        .arg("-lboxcars_ffi")
+        .arg("-Wall")
+        .arg("-Werror")
+        .arg("-o")
+        .arg(&program)
+        .status()
+        .expect("a C compiler is required");
+    assert!(status.success());
+
+    let replay = crate_dir.join("../assets/replays/good/rumble.replay");
+    let output = Command::new(&program).arg(replay).output().unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(String::from_utf8_lossy(&output.stdout).ends_with(" frames\n"));
+}
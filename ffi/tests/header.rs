@@ -0,0 +1,19 @@
+//! The build script generates the C header into `OUT_DIR`, so that building never modifies the
+//! source tree. The committed header must match it.
+use std::fs;
+use std::path::Path;
+
+#[test]
+fn test_header_is_up_to_date() {
+    let generated = Path::new(env!("OUT_DIR")).join("boxcars.h");
+    let committed = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("include")
+        .join("boxcars.h");
+
+    assert!(
+        fs::read_to_string(&generated).unwrap() == fs::read_to_string(&committed).unwrap(),
+        "{} is out of date, copy the generated header from {}",
+        committed.display(),
+        generated.display()
+    );
+}
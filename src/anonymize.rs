@@ -0,0 +1,232 @@
+//! # Anonymizing
+//!
+//! Replays identify their players by name and by online id (in both the header and the network
+//! data), which should be removed before replays are published. Each name and id is replaced
+//! with a pseudonym that is assigned in the order they are first seen, so the pseudonyms can't be
+//! reversed, but the same player keeps the same pseudonym everywhere it is seen by the
+//! `Anonymizer`. An anonymized replay can then be written out with `ReplayWriter`, which
+//! recomputes the crcs.
+//!
+//! The following are redacted:
+//!
+//! - Header properties: `PlayerName` (of the recording player and of goals), and the `Name` and
+//!   `OnlineID` of `PlayerStats`
+//! - Player names and remote user data (`Engine.PlayerReplicationInfo:PlayerName` and
+//!   `Engine.PlayerReplicationInfo:RemoteUserData`)
+//! - The online id (and PlayStation name) of every `UniqueId`, whether it is a player's id, a
+//!   `Reservation`, or a `PartyLeader`. A `Reservation` name is replaced too, and the unknown
+//!   bytes of PlayStation, PsyNet, and Switch ids, which may identify the account, are zeroed.
+//! - The password of `PrivateMatchSettings`
+//!
+//! Free form text chosen by players, such as the replay name or custom team names, is retained.
+
+use crate::models::{HeaderProp, Replay};
+use crate::network::attributes::{Attribute, RemoteId, UniqueId};
+use fnv::FnvHashMap;
+
+const PLAYER_NAME: &str = "Engine.PlayerReplicationInfo:PlayerName";
+const REMOTE_USER_DATA: &str = "Engine.PlayerReplicationInfo:RemoteUserData";
+
+/// Replaces names and online ids with stable pseudonyms. Reuse the same anonymizer for all
+/// replays of a dataset so that a player has the same pseudonym in each one.
+///
+/// ```
+/// use boxcars::{Anonymizer, ParserBuilder, ReplayWriter};
+///
+/// let data = include_bytes!("../assets/replays/good/rumble.replay");
+/// let mut replay = ParserBuilder::new(&data[..])
+///     .must_parse_network_data()
+///     .parse()
+///     .unwrap();
+///
+/// let mut anonymizer = Anonymizer::new();
+/// anonymizer.anonymize(&mut replay);
+///
+/// let header = replay.typed_header().unwrap();
+/// assert!(header.player_stats.iter().all(|x| x.name.starts_with("Player ")));
+///
+/// // And write it back out as a replay
+/// let written = ReplayWriter::new(&replay).write().unwrap();
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Anonymizer {
+    names: FnvHashMap<String, String>,
+    online_ids: FnvHashMap<u64, u64>,
+}
+
+impl Anonymizer {
+    pub fn new() -> Self {
+        Anonymizer::default()
+    }
+
+    /// The pseudonym of a name, such as `Player 3`. An empty name remains empty.
+    pub fn name(&mut self, name: &str) -> String {
+        if name.is_empty() {
+            return String::new();
+        }
+
+        let count = self.names.len();
+        self.names
+            .entry(name.to_string())
+            .or_insert_with(|| format!("Player {}", count + 1))
+            .clone()
+    }
+
+    /// The pseudonym of an online id, which is a small positive number. An id of 0, which denotes
+    /// a player without an online id (eg: a bot), remains 0.
+    pub fn online_id(&mut self, id: u64) -> u64 {
+        if id == 0 {
+            return 0;
+        }
+
+        let count = self.online_ids.len() as u64;
+        *self.online_ids.entry(id).or_insert(count + 1)
+    }
+
+    /// Redacts the header properties and, if parsed, the network data of the replay
+    pub fn anonymize(&mut self, replay: &mut Replay) {
+        self.header_props(&mut replay.properties, false);
+
+        let objects = &replay.objects;
+        let frames = match replay.network_frames.as_mut() {
+            Some(network) => &mut network.frames,
+            None => return,
+        };
+
+        for update in frames.iter_mut().flat_map(|x| x.updated_actors.iter_mut()) {
+            match update.attribute {
+                Attribute::String(ref mut value) => {
+                    match objects
+                        .get(usize::from(update.object_id))
+                        .map(|x| x.as_str())
+                    {
+                        Some(PLAYER_NAME) => *value = self.name(value),
+                        Some(REMOTE_USER_DATA) => *value = self.remote_user_data(value),
+                        _ => {}
+                    }
+                }
+                Attribute::UniqueId(ref mut id) => self.unique_id(id),
+                Attribute::PartyLeader(Some(ref mut id)) => self.unique_id(id),
+                Attribute::Reservation(ref mut reservation) => {
+                    self.unique_id(&mut reservation.unique_id);
+                    if let Some(name) = reservation.name.as_mut() {
+                        *name = self.name(name);
+                    }
+                }
+                Attribute::PrivateMatch(ref mut settings) => settings.password.clear(),
+                _ => {}
+            }
+        }
+    }
+
+    fn header_props(&mut self, props: &mut [(String, HeaderProp)], is_player_stats: bool) {
+        for (key, value) in props.iter_mut() {
+            match (key.as_str(), value) {
+                ("PlayerName", HeaderProp::Str(name)) | ("PlayerName", HeaderProp::Name(name)) => {
+                    *name = self.name(name)
+                }
+                ("Name", HeaderProp::Str(name)) if is_player_stats => *name = self.name(name),
+                ("OnlineID", HeaderProp::QWord(id)) => *id = self.online_id(*id),
+                (key, HeaderProp::Array(elements)) => {
+                    let is_player_stats = key == "PlayerStats";
+                    for element in elements {
+                        self.header_props(element, is_player_stats);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn unique_id(&mut self, id: &mut UniqueId) {
+        match id.remote_id {
+            RemoteId::Steam(ref mut x) | RemoteId::Xbox(ref mut x) | RemoteId::QQ(ref mut x) => {
+                *x = self.online_id(*x)
+            }
+            RemoteId::PlayStation(ref mut ps4) => {
+                ps4.online_id = self.online_id(ps4.online_id);
+                ps4.name = self.name(&ps4.name);
+                ps4.unknown1.fill(0);
+            }
+            RemoteId::PsyNet(ref mut psynet) => {
+                psynet.online_id = self.online_id(psynet.online_id);
+                psynet.unknown1.fill(0);
+            }
+            RemoteId::Switch(ref mut switch) => {
+                switch.online_id = self.online_id(switch.online_id);
+                switch.unknown1.fill(0);
+            }
+            RemoteId::SplitScreen(_) => {}
+        }
+    }
+
+    /// Remote user data is an online id in text, which is replaced with its pseudonym. Any other
+    /// data is removed.
+    fn remote_user_data(&mut self, data: &str) -> String {
+        match data.parse::<u64>() {
+            Ok(id) => self.online_id(id).to_string(),
+            Err(_) => String::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pseudonyms_are_stable() {
+        let mut anonymizer = Anonymizer::new();
+        assert_eq!(anonymizer.name("comagoosie"), "Player 1");
+        assert_eq!(anonymizer.name("Nadir"), "Player 2");
+        assert_eq!(anonymizer.name("comagoosie"), "Player 1");
+        assert_eq!(anonymizer.name(""), "");
+
+        assert_eq!(anonymizer.online_id(76561198101748375), 1);
+        assert_eq!(anonymizer.online_id(0), 0);
+        assert_eq!(anonymizer.online_id(2533274924080102), 2);
+        assert_eq!(anonymizer.online_id(76561198101748375), 1);
+    }
+
+    #[test]
+    fn test_header_props() {
+        let mut props = vec![
+            (
+                String::from("PlayerName"),
+                HeaderProp::Str(String::from("a")),
+            ),
+            (
+                String::from("Name"),
+                HeaderProp::Str(String::from("replay")),
+            ),
+            (
+                String::from("PlayerStats"),
+                HeaderProp::Array(vec![vec![
+                    (String::from("Name"), HeaderProp::Str(String::from("b"))),
+                    (String::from("OnlineID"), HeaderProp::QWord(123)),
+                ]]),
+            ),
+        ];
+
+        Anonymizer::new().header_props(&mut props, false);
+        assert_eq!(props[0].1, HeaderProp::Str(String::from("Player 1")));
+        assert_eq!(props[1].1, HeaderProp::Str(String::from("replay")));
+        assert_eq!(
+            props[2].1,
+            HeaderProp::Array(vec![vec![
+                (
+                    String::from("Name"),
+                    HeaderProp::Str(String::from("Player 2"))
+                ),
+                (String::from("OnlineID"), HeaderProp::QWord(1)),
+            ]])
+        );
+    }
+
+    #[test]
+    fn test_remote_user_data() {
+        let mut anonymizer = Anonymizer::new();
+        assert_eq!(anonymizer.remote_user_data("2533274924080102"), "1");
+        assert_eq!(anonymizer.remote_user_data("someone"), "");
+    }
+}
@@ -0,0 +1,896 @@
+//! Converts Rocket League replays into JSON.
+//!
+//! Each path given is either a replay or a directory, which is searched recursively for files with
+//! a `.replay` extension. A replay that fails to parse, or whose network data is only partially
+//! decoded, is reported on stderr and the rest of the batch is still converted. The exit code is
+//! non-zero if any replay failed.
+//!
+//! The `trim` subcommand cuts a range of frames out of a replay into a standalone replay, and the
+//! `probe` subcommand diagnoses replays with attributes that can't be decoded.
+use boxcars::{
+    AttributeProber, ClassTables, CrcCheck, NetworkError, NetworkParse, ParseError, ParserBuilder,
+    ProbeReport, ReplayWriter, TrimRange,
+};
+use std::collections::HashSet;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io::{self, BufWriter, Read, Write};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::str::FromStr;
+
+const USAGE: &str = "Usage: boxcars [OPTIONS] <PATH>...
+       boxcars trim [OPTIONS] <REPLAY>
+       boxcars probe [OPTIONS] <REPLAY>
+
+Converts Rocket League replays into JSON. Directories are searched recursively
+for files with a .replay extension and a path of - reads a replay from stdin.
+
+Options:
+  -c, --crc <MODE>        When to check for replay corruption:
+                          always, never, on-error (default)
+  -n, --network <MODE>    How to handle the network data:
+                          always, never, ignore-on-error (default), partial.
+                          With partial, a replay whose network data fails to
+                          decode is reported and the exit code is non-zero
+  -j, --threads <N>       Number of threads to decode the network data with
+                          (default 1)
+  -f, --format <FORMAT>   Output format:
+                          json (default), pretty, or frames (newline delimited
+                          JSON with one line per network frame). With frames,
+                          a frame that can't be decoded ends the output, is
+                          reported, and the exit code is non-zero
+  -o, --output-dir <DIR>  Write each replay to <DIR>/<name>.json instead of
+                          stdout. Replays with the same name are an error,
+                          and stdin can't be written to a directory
+  -h, --help              Print this message";
+
+const TRIM_USAGE: &str = "Usage: boxcars trim [OPTIONS] <REPLAY>
+
+Cuts a clip out of a replay into a standalone replay. As replays are played
+back from keyframes, the clip starts at the keyframe at or before the start of
+the range.
+
+Options:
+  --frames <START>..<END>  The frames to keep, where the end is exclusive
+  --time <START>..<END>    The frame times (in seconds) to keep, where the end
+                           is exclusive
+  -o, --output <FILE>      Where to write the clip
+  -h, --help               Print this message";
+
+const PROBE_USAGE: &str = "Usage: boxcars probe [OPTIONS] <REPLAY>
+
+Diagnoses a replay that fails to decode, which is often due to an attribute
+introduced by a Rocket League patch. Lists the attributes that can't be
+decoded, dumps the bits from where decoding failed, and decodes the failing
+attribute as each known attribute type to find those that let decoding
+continue.
+
+Options:
+  -t, --tables <FILE>   JSON class tables that extend the built-in tables
+  -k, --lookahead <N>   Number of frames that decoding must continue for
+                        (default 100)
+  -b, --bits <N>        Number of bits to dump (default 128)
+  -f, --format <FORMAT> Output format: text (default) or json
+  -h, --help            Print this message";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Json,
+    Pretty,
+    Frames,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Options {
+    crc_check: CrcCheck,
+    network_parse: NetworkParse,
+    threads: usize,
+    format: Format,
+    output_dir: Option<PathBuf>,
+    paths: Vec<PathBuf>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct TrimOptions {
+    range: TrimRange,
+    output: PathBuf,
+    path: PathBuf,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct ProbeOptions {
+    tables: Option<PathBuf>,
+    lookahead: usize,
+    bits: usize,
+    json: bool,
+    path: PathBuf,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum ArgsError {
+    Help,
+    MissingValue(String),
+    InvalidValue(String, String),
+    UnknownOption(String),
+    MissingOption(String),
+    Conflict(String, String),
+    NoPaths,
+}
+
+impl Error for ArgsError {}
+
+impl fmt::Display for ArgsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ArgsError::Help => write!(f, "{}", USAGE),
+            ArgsError::MissingValue(opt) => write!(f, "Option {} requires a value", opt),
+            ArgsError::InvalidValue(opt, val) => {
+                write!(f, "Invalid value for option {}: {}", opt, val)
+            }
+            ArgsError::UnknownOption(opt) => write!(f, "Unrecognized option: {}", opt),
+            ArgsError::MissingOption(opt) => write!(f, "Option {} is required", opt),
+            ArgsError::Conflict(opt, other) => {
+                write!(f, "Option {} can't be used with {}", opt, other)
+            }
+            ArgsError::NoPaths => write!(f, "No replays were given"),
+        }
+    }
+}
+
+/// A command line argument, which is either a path or an option with its value
+#[derive(Debug, Clone, PartialEq)]
+enum Arg {
+    Path(PathBuf),
+    Opt(String, String),
+}
+
+/// Splits the arguments of a command into paths and options with their values. A path of `-`
+/// denotes stdin.
+fn tokenize<I: IntoIterator<Item = String>>(args: I) -> Result<Vec<Arg>, ArgsError> {
+    let mut result = Vec::new();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        // Support both "--crc never" and "--crc=never"
+        let (opt, inline) = match arg.find('=') {
+            Some(ind) if arg.starts_with("--") => (arg[..ind].to_string(), Some(&arg[ind + 1..])),
+            _ => (arg.clone(), None),
+        };
+
+        if opt == "-h" || opt == "--help" {
+            return Err(ArgsError::Help);
+        }
+
+        if !opt.starts_with('-') || opt == "-" {
+            result.push(Arg::Path(PathBuf::from(arg)));
+            continue;
+        }
+
+        let value = match inline {
+            Some(val) => val.to_string(),
+            None => args
+                .next()
+                .ok_or_else(|| ArgsError::MissingValue(opt.clone()))?,
+        };
+
+        result.push(Arg::Opt(opt, value));
+    }
+
+    Ok(result)
+}
+
+fn parse_args<I: IntoIterator<Item = String>>(args: I) -> Result<Options, ArgsError> {
+    let mut options = Options {
+        crc_check: CrcCheck::OnError,
+        network_parse: NetworkParse::IgnoreOnError,
+        threads: 1,
+        format: Format::Json,
+        output_dir: None,
+        paths: Vec::new(),
+    };
+
+    for arg in tokenize(args)? {
+        let (opt, value) = match arg {
+            Arg::Path(path) => {
+                options.paths.push(path);
+                continue;
+            }
+            Arg::Opt(opt, value) => (opt, value),
+        };
+
+        let invalid = || ArgsError::InvalidValue(opt.clone(), value.clone());
+        match opt.as_str() {
+            "-c" | "--crc" => {
+                options.crc_check = match value.as_str() {
+                    "always" => CrcCheck::Always,
+                    "never" => CrcCheck::Never,
+                    "on-error" => CrcCheck::OnError,
+                    _ => return Err(invalid()),
+                }
+            }
+            "-n" | "--network" => {
+                options.network_parse = match value.as_str() {
+                    "always" => NetworkParse::Always,
+                    "never" => NetworkParse::Never,
+                    "ignore-on-error" => NetworkParse::IgnoreOnError,
+                    "partial" => NetworkParse::Partial,
+                    _ => return Err(invalid()),
+                }
+            }
+            "-j" | "--threads" => {
+                options.threads = value.parse().ok().filter(|&x| x > 0).ok_or_else(invalid)?
+            }
+            "-f" | "--format" => {
+                options.format = match value.as_str() {
+                    "json" => Format::Json,
+                    "pretty" => Format::Pretty,
+                    "frames" => Format::Frames,
+                    _ => return Err(invalid()),
+                }
+            }
+            "-o" | "--output-dir" => options.output_dir = Some(PathBuf::from(value)),
+            _ => return Err(ArgsError::UnknownOption(opt)),
+        }
+    }
+
+    if options.paths.is_empty() {
+        return Err(ArgsError::NoPaths);
+    }
+
+    // Frames are the network data, so there would be nothing to output
+    if options.format == Format::Frames && options.network_parse == NetworkParse::Never {
+        return Err(ArgsError::Conflict(
+            String::from("--network never"),
+            String::from("--format frames"),
+        ));
+    }
+
+    // A replay from stdin has no name to write it under
+    if options.output_dir.is_some() && options.paths.iter().any(|x| x == Path::new("-")) {
+        return Err(ArgsError::Conflict(
+            String::from("--output-dir"),
+            String::from("a path of - (stdin)"),
+        ));
+    }
+
+    Ok(options)
+}
+
+fn parse_trim_args<I: IntoIterator<Item = String>>(args: I) -> Result<TrimOptions, ArgsError> {
+    let mut range = None;
+    let mut output = None;
+    let mut paths = Vec::new();
+
+    for arg in tokenize(args)? {
+        let (opt, value) = match arg {
+            Arg::Path(path) => {
+                paths.push(path);
+                continue;
+            }
+            Arg::Opt(opt, value) => (opt, value),
+        };
+
+        let invalid = || ArgsError::InvalidValue(opt.clone(), value.clone());
+        match opt.as_str() {
+            "--frames" => range = Some(TrimRange::Frames(parse_range(&value).ok_or_else(invalid)?)),
+            "--time" => range = Some(TrimRange::Time(parse_range(&value).ok_or_else(invalid)?)),
+            "-o" | "--output" => output = Some(PathBuf::from(value)),
+            _ => return Err(ArgsError::UnknownOption(opt)),
+        }
+    }
+
+    let range =
+        range.ok_or_else(|| ArgsError::MissingOption(String::from("--frames or --time")))?;
+    let output = output.ok_or_else(|| ArgsError::MissingOption(String::from("--output")))?;
+    Ok(TrimOptions {
+        range,
+        output,
+        path: single_path(paths)?,
+    })
+}
+
+fn parse_probe_args<I: IntoIterator<Item = String>>(args: I) -> Result<ProbeOptions, ArgsError> {
+    let mut options = ProbeOptions {
+        tables: None,
+        lookahead: 100,
+        bits: 128,
+        json: false,
+        path: PathBuf::new(),
+    };
+    let mut paths = Vec::new();
+
+    for arg in tokenize(args)? {
+        let (opt, value) = match arg {
+            Arg::Path(path) => {
+                paths.push(path);
+                continue;
+            }
+            Arg::Opt(opt, value) => (opt, value),
+        };
+
+        let invalid = || ArgsError::InvalidValue(opt.clone(), value.clone());
+        match opt.as_str() {
+            "-t" | "--tables" => options.tables = Some(PathBuf::from(value)),
+            "-k" | "--lookahead" => {
+                options.lookahead = value.parse().ok().filter(|&x| x > 0).ok_or_else(invalid)?
+            }
+            "-b" | "--bits" => options.bits = value.parse().map_err(|_| invalid())?,
+            "-f" | "--format" => {
+                options.json = match value.as_str() {
+                    "text" => false,
+                    "json" => true,
+                    _ => return Err(invalid()),
+                }
+            }
+            _ => return Err(ArgsError::UnknownOption(opt)),
+        }
+    }
+
+    options.path = single_path(paths)?;
+    Ok(options)
+}
+
+/// The one replay that a subcommand operates on
+fn single_path(mut paths: Vec<PathBuf>) -> Result<PathBuf, ArgsError> {
+    match paths.len() {
+        0 => Err(ArgsError::NoPaths),
+        1 => Ok(paths.remove(0)),
+        _ => Err(ArgsError::InvalidValue(
+            String::from("<REPLAY>"),
+            paths[1].display().to_string(),
+        )),
+    }
+}
+
+/// Parses a range written as `<START>..<END>`
+fn parse_range<T: FromStr>(value: &str) -> Option<Range<T>> {
+    let (start, end) = value.split_once("..")?;
+    Some(start.parse().ok()?..end.parse().ok()?)
+}
+
+/// Expands the given paths into a list of replays. Directories are walked recursively and only
+/// files with a `.replay` extension are kept, while files given explicitly are always kept.
+fn replay_files(paths: &[PathBuf]) -> io::Result<Vec<PathBuf>> {
+    let mut result = Vec::new();
+    for path in paths {
+        if path.is_dir() {
+            let mut dir_files = Vec::new();
+            walk_dir(path, &mut dir_files)?;
+            dir_files.sort();
+            result.extend(dir_files);
+        } else {
+            result.push(path.clone());
+        }
+    }
+
+    Ok(result)
+}
+
+fn walk_dir(dir: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            walk_dir(&path, files)?;
+        } else if path
+            .extension()
+            .filter(|ext| ext.eq_ignore_ascii_case("replay"))
+            .is_some()
+        {
+            files.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+/// Writes the replay to the output. Returns the error of network data that was only partially
+/// decoded, as the output is then incomplete.
+fn convert<W: Write>(
+    options: &Options,
+    data: &[u8],
+    out: &mut W,
+) -> Result<Option<NetworkError>, Box<dyn Error>> {
+    let builder = ParserBuilder::new(data)
+        .with_crc_check(options.crc_check)
+        .with_network_parse(options.network_parse)
+        .with_network_threads(options.threads);
+
+    match options.format {
+        Format::Json => {
+            let mut replay = builder.parse()?;
+            serde_json::to_writer(&mut *out, &replay)?;
+            writeln!(out)?;
+            Ok(replay.network_error.take())
+        }
+        Format::Pretty => {
+            let mut replay = builder.parse()?;
+            serde_json::to_writer_pretty(&mut *out, &replay)?;
+            writeln!(out)?;
+            Ok(replay.network_error.take())
+        }
+        Format::Frames => {
+            // Frames already written can't be taken back, so unless the network data must be
+            // decoded, a frame that fails to decode ends the output like a partial parse.
+            let must_decode = options.network_parse == NetworkParse::Always;
+            let frames = match builder.parse_streaming() {
+                Ok((_, frames)) => frames,
+                Err(ParseError::NetworkError(e)) if !must_decode => return Ok(Some(*e)),
+                Err(e) => return Err(e.into()),
+            };
+
+            for frame in frames {
+                match frame {
+                    Ok(frame) => serde_json::to_writer(&mut *out, &frame)?,
+                    Err(e) if !must_decode => return Ok(Some(e)),
+                    Err(e) => return Err(e.into()),
+                }
+                writeln!(out)?;
+            }
+            Ok(None)
+        }
+    }
+}
+
+/// Describes where the network data failed to decode
+fn describe_network_error(error: &NetworkError) -> String {
+    let report = error.report(0);
+    match (report.frame, report.bit_position) {
+        (Some(frame), Some(bit)) => format!(
+            "network data decoded up to frame {} (bit {}): {}",
+            frame, bit, report.message
+        ),
+        _ => format!("network data not decoded: {}", report.message),
+    }
+}
+
+/// Where a replay is written to in the output directory
+fn output_path(dir: &Path, path: &Path) -> PathBuf {
+    let name = path.file_stem().unwrap_or(path.as_os_str());
+    let mut out_path = dir.join(name);
+    out_path.set_extension("json");
+    out_path
+}
+
+fn convert_file(
+    options: &Options,
+    path: &Path,
+    out_path: Option<&Path>,
+) -> Result<Option<NetworkError>, Box<dyn Error>> {
+    let data = if path == Path::new("-") {
+        let mut data = Vec::new();
+        io::stdin().read_to_end(&mut data)?;
+        data
+    } else {
+        fs::read(path)?
+    };
+
+    let network_error = match out_path {
+        Some(out_path) => {
+            let mut out = BufWriter::new(fs::File::create(out_path)?);
+            let network_error = convert(options, &data, &mut out)?;
+            out.flush()?;
+            network_error
+        }
+        None => {
+            let stdout = io::stdout();
+            let mut out = BufWriter::new(stdout.lock());
+            let network_error = convert(options, &data, &mut out)?;
+            out.flush()?;
+            network_error
+        }
+    };
+
+    Ok(network_error)
+}
+
+fn trim_file(options: &TrimOptions) -> Result<(), Box<dyn Error>> {
+    let data = fs::read(&options.path)?;
+    let replay = ParserBuilder::new(&data[..])
+        .must_parse_network_data()
+        .parse()?;
+
+    let clip = boxcars::trim(&replay, options.range.clone())?;
+    fs::write(&options.output, ReplayWriter::new(&clip).write()?)?;
+    Ok(())
+}
+
+fn probe_file(options: &ProbeOptions) -> Result<ProbeReport, Box<dyn Error>> {
+    let tables: ClassTables = match options.tables {
+        Some(ref path) => serde_json::from_slice(&fs::read(path)?)?,
+        None => ClassTables::new(),
+    };
+
+    let data = fs::read(&options.path)?;
+    let report = AttributeProber::new(&data[..])
+        .with_tables(&tables)
+        .with_lookahead(options.lookahead)
+        .with_dump_bits(options.bits)
+        .probe()?;
+    Ok(report)
+}
+
+fn write_probe<W: Write>(report: &ProbeReport, out: &mut W) -> io::Result<()> {
+    writeln!(
+        out,
+        "Attributes that can't be decoded ({}):",
+        report.unknown_attributes.len()
+    )?;
+    for attr in &report.unknown_attributes {
+        writeln!(out, "  {:>5} {}", attr.object_id, attr.name)?;
+    }
+
+    let failure = match report.failure {
+        Some(ref failure) => failure,
+        None => return writeln!(out, "\nThe network data decoded without errors"),
+    };
+
+    let error = &failure.report;
+    writeln!(out, "\n{}", error.message)?;
+    if let (Some(frame), Some(bit)) = (error.frame, error.bit_position) {
+        writeln!(out, "\nDecoding failed on frame {} at bit {}", frame, bit)?;
+    }
+
+    if let (Some(actor), Some(object)) = (error.actor_id, error.object_name.as_ref()) {
+        writeln!(out, "Actor: {} ({})", actor, object)?;
+    }
+
+    if let (Some(stream), Some(name)) = (error.stream_id, error.attribute_name.as_ref()) {
+        writeln!(out, "Attribute: {} (stream id {})", name, stream)?;
+    }
+
+    if !failure.bits.is_empty() {
+        writeln!(out, "\nBits (in the order they are read):")?;
+        for line in failure.bits.as_bytes().chunks(64) {
+            let bytes: Vec<_> = line
+                .chunks(8)
+                .map(|x| String::from_utf8_lossy(x).into_owned())
+                .collect();
+            writeln!(out, "  {}", bytes.join(" "))?;
+        }
+    }
+
+    if !failure.candidates.is_empty() {
+        writeln!(out, "\nCandidates (frames decoded from the failing frame):")?;
+        for candidate in failure.candidates.iter().filter(|x| x.frames > 0) {
+            let continues = if candidate.continues {
+                " (continues)"
+            } else {
+                ""
+            };
+            writeln!(
+                out,
+                "  {:<22} {:>5}{}",
+                format!("{:?}", candidate.tag),
+                candidate.frames,
+                continues
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+fn run(options: &Options) -> Result<usize, Box<dyn Error>> {
+    if let Some(ref dir) = options.output_dir {
+        fs::create_dir_all(dir)?;
+    }
+
+    let mut failures = 0;
+    let mut written = HashSet::new();
+    for path in replay_files(&options.paths)? {
+        let out_path = options
+            .output_dir
+            .as_ref()
+            .map(|dir| output_path(dir, &path));
+        if let Some(ref out_path) = out_path {
+            // Don't let replays with the same name in different directories overwrite each other
+            if !written.insert(out_path.clone()) {
+                eprintln!(
+                    "{}: {} was already written by another replay",
+                    path.display(),
+                    out_path.display()
+                );
+                failures += 1;
+                continue;
+            }
+        }
+
+        match convert_file(options, &path, out_path.as_deref()) {
+            Ok(None) => {}
+            Ok(Some(e)) => {
+                eprintln!("{}: {}", path.display(), describe_network_error(&e));
+                failures += 1;
+            }
+            Err(e) => {
+                eprintln!("{}: {}", path.display(), e);
+                failures += 1;
+            }
+        }
+    }
+
+    Ok(failures)
+}
+
+fn trim_main<I: IntoIterator<Item = String>>(args: I) {
+    let options = match parse_trim_args(args) {
+        Ok(options) => options,
+        Err(ArgsError::Help) => {
+            println!("{}", TRIM_USAGE);
+            return;
+        }
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, TRIM_USAGE);
+            process::exit(2);
+        }
+    };
+
+    if let Err(e) = trim_file(&options) {
+        eprintln!("{}: {}", options.path.display(), e);
+        process::exit(1);
+    }
+}
+
+fn probe_main<I: IntoIterator<Item = String>>(args: I) {
+    let options = match parse_probe_args(args) {
+        Ok(options) => options,
+        Err(ArgsError::Help) => {
+            println!("{}", PROBE_USAGE);
+            return;
+        }
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, PROBE_USAGE);
+            process::exit(2);
+        }
+    };
+
+    let report = match probe_file(&options) {
+        Ok(report) => report,
+        Err(e) => {
+            eprintln!("{}: {}", options.path.display(), e);
+            process::exit(1);
+        }
+    };
+
+    let stdout = io::stdout();
+    let mut out = BufWriter::new(stdout.lock());
+    let written = if options.json {
+        serde_json::to_writer_pretty(&mut out, &report)
+            .map_err(io::Error::from)
+            .and_then(|_| writeln!(out))
+    } else {
+        write_probe(&report, &mut out)
+    };
+
+    if let Err(e) = written.and_then(|_| out.flush()) {
+        eprintln!("{}", e);
+        process::exit(1);
+    }
+}
+
+fn main() {
+    let mut args = std::env::args().skip(1).peekable();
+    match args.peek().map(|x| x.as_str()) {
+        Some("trim") => return trim_main(args.skip(1)),
+        Some("probe") => return probe_main(args.skip(1)),
+        _ => {}
+    }
+
+    let options = match parse_args(args) {
+        Ok(options) => options,
+        Err(ArgsError::Help) => {
+            println!("{}", USAGE);
+            return;
+        }
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, USAGE);
+            process::exit(2);
+        }
+    };
+
+    match run(&options) {
+        Ok(0) => {}
+        Ok(_) => process::exit(1),
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Result<Options, ArgsError> {
+        parse_args(args.iter().map(|x| x.to_string()))
+    }
+
+    #[test]
+    fn test_parse_args_defaults() {
+        let options = args(&["a.replay", "dir"]).unwrap();
+        assert_eq!(options.crc_check, CrcCheck::OnError);
+        assert_eq!(options.network_parse, NetworkParse::IgnoreOnError);
+        assert_eq!(options.threads, 1);
+        assert_eq!(options.format, Format::Json);
+        assert_eq!(options.output_dir, None);
+        assert_eq!(
+            options.paths,
+            vec![PathBuf::from("a.replay"), PathBuf::from("dir")]
+        );
+    }
+
+    #[test]
+    fn test_parse_args_options() {
+        let options = args(&[
+            "--crc",
+            "always",
+            "-n",
+            "partial",
+            "-j",
+            "4",
+            "--format=frames",
+            "-o",
+            "out",
+            "a.replay",
+        ])
+        .unwrap();
+        assert_eq!(options.crc_check, CrcCheck::Always);
+        assert_eq!(options.network_parse, NetworkParse::Partial);
+        assert_eq!(options.threads, 4);
+        assert_eq!(options.format, Format::Frames);
+        assert_eq!(options.output_dir, Some(PathBuf::from("out")));
+        assert_eq!(options.paths, vec![PathBuf::from("a.replay")]);
+    }
+
+    #[test]
+    fn test_parse_args_errors() {
+        assert_eq!(args(&["-h"]), Err(ArgsError::Help));
+        assert_eq!(args(&[]), Err(ArgsError::NoPaths));
+        assert_eq!(
+            args(&["a.replay", "--crc"]),
+            Err(ArgsError::MissingValue(String::from("--crc")))
+        );
+        assert_eq!(
+            args(&["--format", "xml", "a.replay"]),
+            Err(ArgsError::InvalidValue(
+                String::from("--format"),
+                String::from("xml")
+            ))
+        );
+        assert_eq!(
+            args(&["--bogus", "a.replay"]),
+            Err(ArgsError::UnknownOption(String::from("--bogus")))
+        );
+        assert_eq!(
+            args(&["-f", "frames", "-n", "never", "a.replay"]),
+            Err(ArgsError::Conflict(
+                String::from("--network never"),
+                String::from("--format frames")
+            ))
+        );
+        assert_eq!(
+            args(&["-o", "out", "-"]),
+            Err(ArgsError::Conflict(
+                String::from("--output-dir"),
+                String::from("a path of - (stdin)")
+            ))
+        );
+    }
+
+    #[test]
+    fn test_tokenize() {
+        let tokens = tokenize(
+            ["a.replay", "-c", "never", "--format=frames", "-"]
+                .iter()
+                .map(|x| x.to_string()),
+        )
+        .unwrap();
+        assert_eq!(
+            tokens,
+            vec![
+                Arg::Path(PathBuf::from("a.replay")),
+                Arg::Opt(String::from("-c"), String::from("never")),
+                Arg::Opt(String::from("--format"), String::from("frames")),
+                Arg::Path(PathBuf::from("-")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_trim_args() {
+        let trim_args = |args: &[&str]| parse_trim_args(args.iter().map(|x| x.to_string()));
+        let options = trim_args(&["--time=60.5..70", "-o", "clip.replay", "a.replay"]).unwrap();
+        assert_eq!(options.range, TrimRange::Time(60.5..70.0));
+        assert_eq!(options.output, PathBuf::from("clip.replay"));
+        assert_eq!(options.path, PathBuf::from("a.replay"));
+
+        let options = trim_args(&["--frames", "100..400", "-o", "c", "a.replay"]).unwrap();
+        assert_eq!(options.range, TrimRange::Frames(100..400));
+
+        assert_eq!(
+            trim_args(&["--frames", "100", "-o", "c", "a.replay"]),
+            Err(ArgsError::InvalidValue(
+                String::from("--frames"),
+                String::from("100")
+            ))
+        );
+        assert_eq!(
+            trim_args(&["--frames", "1..2", "a.replay"]),
+            Err(ArgsError::MissingOption(String::from("--output")))
+        );
+    }
+
+    #[test]
+    fn test_parse_probe_args() {
+        let probe_args = |args: &[&str]| parse_probe_args(args.iter().map(|x| x.to_string()));
+        let options = probe_args(&["a.replay"]).unwrap();
+        assert_eq!(options.lookahead, 100);
+        assert!(!options.json);
+
+        let options =
+            probe_args(&["-t", "t.json", "--lookahead=30", "-f", "json", "a.replay"]).unwrap();
+        assert_eq!(options.tables, Some(PathBuf::from("t.json")));
+        assert_eq!(options.lookahead, 30);
+        assert!(options.json);
+
+        assert_eq!(
+            probe_args(&["--lookahead", "0", "a.replay"]),
+            Err(ArgsError::InvalidValue(
+                String::from("--lookahead"),
+                String::from("0")
+            ))
+        );
+        assert_eq!(probe_args(&[]), Err(ArgsError::NoPaths));
+    }
+
+    #[test]
+    fn test_write_probe() {
+        let tables = ClassTables::new().with_attribute(
+            "TAGame.Ball_TA:HitTeamNum",
+            boxcars::AttributeTag::NotImplemented,
+        );
+        let data = include_bytes!("../../assets/replays/good/rumble.replay");
+        let report = AttributeProber::new(&data[..])
+            .with_tables(&tables)
+            .probe()
+            .unwrap();
+
+        let mut out = Vec::new();
+        write_probe(&report, &mut out).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert!(text.contains("Attribute: TAGame.Ball_TA:HitTeamNum"));
+        assert!(text.contains("  Byte "));
+    }
+
+    #[test]
+    fn test_convert_frames() {
+        let options = args(&["--format", "frames", "a.replay"]).unwrap();
+        let data = include_bytes!("../../assets/replays/good/rumble.replay");
+        let mut out = Vec::new();
+        assert!(convert(&options, &data[..], &mut out).unwrap().is_none());
+
+        let (_, frames) = ParserBuilder::new(&data[..]).parse_streaming().unwrap();
+        let lines = String::from_utf8(out).unwrap();
+        assert_eq!(lines.lines().count(), frames.count());
+    }
+
+    #[test]
+    fn test_convert_partial_network_data() {
+        // Garble a frame's time midway through the network data
+        let mut data = include_bytes!("../../assets/replays/good/rumble.replay").to_vec();
+        data[283_496] ^= 0xff;
+
+        for format in &["json", "frames"] {
+            let options = args(&["-n", "partial", "-c", "never", "-f", format, "a"]).unwrap();
+            let mut out = Vec::new();
+            let error = convert(&options, &data, &mut out).unwrap().unwrap();
+            assert!(describe_network_error(&error).starts_with("network data decoded up to frame"));
+            assert!(!out.is_empty());
+        }
+
+        let options = args(&["-n", "always", "-c", "never", "-f", "frames", "a"]).unwrap();
+        assert!(convert(&options, &data, &mut Vec::new()).is_err());
+    }
+}
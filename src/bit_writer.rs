@@ -0,0 +1,171 @@
+/// The inverse of `bitter::BitGet`. Bits are written least significant bit first so that data
+/// written can be read back with the same sequence of `BitGet` calls.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct BitWriter {
+    data: Vec<u8>,
+    current: u64,
+    current_bits: usize,
+}
+
+impl BitWriter {
+    pub fn new() -> Self {
+        BitWriter::default()
+    }
+
+    /// The number of bits written thus far
+    pub fn bit_position(&self) -> usize {
+        self.data.len() * 8 + self.current_bits
+    }
+
+    /// Writes the lowest `bits` of `value` to the stream (`bits` must not exceed 32)
+    pub fn write_u32_bits(&mut self, value: u32, bits: i32) {
+        debug_assert!((0..=32).contains(&bits));
+        let bts = bits as usize;
+        if bts == 0 {
+            return;
+        }
+
+        let mask = (1u64 << bts) - 1;
+        self.current |= (u64::from(value) & mask) << self.current_bits;
+        self.current_bits += bts;
+        while self.current_bits >= 8 {
+            self.data.push(self.current as u8);
+            self.current >>= 8;
+            self.current_bits -= 8;
+        }
+    }
+
+    pub fn write_bit(&mut self, bit: bool) {
+        self.write_u32_bits(u32::from(bit), 1);
+    }
+
+    pub fn write_u8(&mut self, value: u8) {
+        self.write_u32_bits(u32::from(value), 8);
+    }
+
+    pub fn write_i8(&mut self, value: i8) {
+        self.write_u8(value as u8);
+    }
+
+    pub fn write_u16(&mut self, value: u16) {
+        self.write_u32_bits(u32::from(value), 16);
+    }
+
+    pub fn write_u32(&mut self, value: u32) {
+        self.write_u32_bits(value, 32);
+    }
+
+    pub fn write_i32(&mut self, value: i32) {
+        self.write_u32(value as u32);
+    }
+
+    pub fn write_u64(&mut self, value: u64) {
+        self.write_u32(value as u32);
+        self.write_u32((value >> 32) as u32);
+    }
+
+    pub fn write_i64(&mut self, value: i64) {
+        self.write_u64(value as u64);
+    }
+
+    pub fn write_f32(&mut self, value: f32) {
+        self.write_u32(value.to_bits());
+    }
+
+    pub fn write_bytes(&mut self, data: &[u8]) {
+        if self.current_bits == 0 {
+            self.data.extend_from_slice(data);
+        } else {
+            for &byte in data {
+                self.write_u8(byte);
+            }
+        }
+    }
+
+    /// Mirrors `BitGet::if_get`: writes an "on" bit followed by the data if the value is present
+    /// else an "off" bit.
+    pub fn if_write<T, F>(&mut self, value: Option<T>, mut f: F)
+    where
+        F: FnMut(&mut Self, T),
+    {
+        self.write_bit(value.is_some());
+        if let Some(x) = value {
+            f(self, x);
+        }
+    }
+
+    /// Mirrors `BitGet::read_bits_max_computed`. The value must be less than the given max.
+    pub fn write_bits_max_computed(&mut self, bits: i32, max: u32, value: u32) {
+        debug_assert!(value < max);
+        let data = value & ((1u64 << bits) - 1) as u32;
+        self.write_u32_bits(data, bits);
+
+        // The reader only consumes the extra bit if it can't cause the value to exceed the max
+        let up = data + (1 << bits);
+        if up < max {
+            self.write_bit(value >= (1 << bits));
+        }
+    }
+
+    /// Mirrors `BitGet::read_bits_max`. The value must be less than the given max.
+    pub fn write_bits_max(&mut self, max: u32, value: u32) {
+        let bits = bitter::bit_width(max) as i32 - 1;
+        self.write_bits_max_computed(std::cmp::max(bits, 0), max, value);
+    }
+
+    /// Finishes the stream, padding the last byte with zeros
+    pub fn into_bytes(mut self) -> Vec<u8> {
+        if self.current_bits > 0 {
+            self.data.push(self.current as u8);
+        }
+        self.data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitter::BitGet;
+
+    #[test]
+    fn test_write_bits_round_trip() {
+        let mut writer = BitWriter::new();
+        writer.write_bit(true);
+        writer.write_u32_bits(5, 3);
+        writer.write_u8(0xab);
+        writer.write_i32(-100);
+        writer.write_u64(0x0123_4567_89ab_cdef);
+        writer.write_f32(2.5);
+        writer.write_bytes(&[1, 2, 3]);
+        assert_eq!(writer.bit_position(), 1 + 3 + 8 + 32 + 64 + 32 + 24);
+
+        let data = writer.into_bytes();
+        let mut bits = BitGet::new(&data[..]);
+        assert_eq!(bits.read_bit(), Some(true));
+        assert_eq!(bits.read_u32_bits(3), Some(5));
+        assert_eq!(bits.read_u8(), Some(0xab));
+        assert_eq!(bits.read_i32(), Some(-100));
+        assert_eq!(bits.read_u64(), Some(0x0123_4567_89ab_cdef));
+        assert_eq!(bits.read_f32(), Some(2.5));
+        assert_eq!(
+            bits.read_bytes(3).map(|x| x.into_owned()),
+            Some(vec![1, 2, 3])
+        );
+    }
+
+    #[test]
+    fn test_write_bits_max() {
+        let mut writer = BitWriter::new();
+        for i in 0..20 {
+            writer.write_bits_max(20, i);
+        }
+        writer.write_bits_max_computed(4, 22, 21);
+
+        let data = writer.into_bytes();
+        let mut bits = BitGet::new(&data[..]);
+        for i in 0..20 {
+            assert_eq!(bits.read_bits_max(20), Some(i));
+        }
+        assert_eq!(bits.read_bits_max_computed(4, 22), Some(21));
+    }
+}
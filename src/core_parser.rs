@@ -112,6 +112,23 @@ impl<'a> CoreParser<'a> {
 
     /// Parses either UTF-16 or Windows-1252 encoded strings
     pub fn parse_text(&mut self) -> Result<String, ParseError> {
+        let (size, utf16) = self.text_size()?;
+        let data = self.take_data(size)?;
+        if utf16 {
+            decode_utf16(data)
+        } else {
+            decode_windows1252(data)
+        }
+    }
+
+    /// Advances past a string without decoding it
+    pub fn skip_text(&mut self) -> Result<(), ParseError> {
+        let (size, _) = self.text_size()?;
+        self.skip(size)
+    }
+
+    /// The number of bytes of the upcoming string and whether it is UTF-16
+    fn text_size(&mut self) -> Result<(usize, bool), ParseError> {
         // The number of bytes that the string is composed of. If negative, the string is UTF-16,
         // else the string is windows 1252 encoded.
         let characters = self.take(4, le_i32)?;
@@ -125,11 +142,9 @@ impl<'a> CoreParser<'a> {
             // We're dealing with UTF-16 and each character is two bytes, we
             // multiply the size by 2. The last two bytes included in the count are
             // null terminators
-            let size = characters * -2;
-            self.take_data(size as usize).and_then(decode_utf16)
+            Ok(((characters * -2) as usize, true))
         } else {
-            self.take_data(characters as usize)
-                .and_then(decode_windows1252)
+            Ok((characters as usize, false))
         }
     }
 }
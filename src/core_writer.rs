@@ -0,0 +1,115 @@
+use crate::parsing_utils::encode_text_bytes;
+
+/// The inverse of the `CoreParser`: writes little endian encoded data
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CoreWriter {
+    data: Vec<u8>,
+}
+
+impl CoreWriter {
+    pub fn new() -> Self {
+        CoreWriter::default()
+    }
+
+    pub fn bytes_written(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn write_bytes(&mut self, data: &[u8]) {
+        self.data.extend_from_slice(data);
+    }
+
+    pub fn write_i32(&mut self, value: i32) {
+        self.write_bytes(&value.to_le_bytes());
+    }
+
+    pub fn write_u32(&mut self, value: u32) {
+        self.write_bytes(&value.to_le_bytes());
+    }
+
+    pub fn write_u64(&mut self, value: u64) {
+        self.write_bytes(&value.to_le_bytes());
+    }
+
+    pub fn write_f32(&mut self, value: f32) {
+        self.write_u32(value.to_bits());
+    }
+
+    /// Overwrites a previously written 32 bit integer. Useful when a size prefix is only known
+    /// after the data has been written
+    pub fn set_i32(&mut self, offset: usize, value: i32) {
+        self.data[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+    }
+
+    /// Writes a list of elements prefixed by the number of elements
+    pub fn list_of<F, T>(&mut self, items: &[T], mut f: F)
+    where
+        F: FnMut(&mut Self, &T),
+    {
+        self.write_i32(items.len() as i32);
+        for item in items {
+            f(self, item);
+        }
+    }
+
+    pub fn text_list(&mut self, items: &[String]) {
+        self.list_of(items, |s, x| s.write_text(x))
+    }
+
+    /// Writes UTF-8 string with a null terminator
+    pub fn write_str(&mut self, input: &str) {
+        self.write_i32(input.len() as i32 + 1);
+        self.write_bytes(input.as_bytes());
+        self.write_bytes(&[0]);
+    }
+
+    /// Writes either UTF-16 or Windows-1252 encoded strings
+    pub fn write_text(&mut self, input: &str) {
+        let (characters, data) = encode_text_bytes(input);
+        self.write_i32(characters);
+        self.write_bytes(&data);
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core_parser::CoreParser;
+
+    #[test]
+    fn write_str_round_trip() {
+        let data = include_bytes!("../assets/replays/partial/text.replay");
+        let mut writer = CoreWriter::new();
+        writer.write_str("TAGame.Replay_Soccar_TA");
+        assert_eq!(&writer.into_bytes()[..], &data[..]);
+    }
+
+    #[test]
+    fn write_utf16_string() {
+        let data = include_bytes!("../assets/replays/partial/utf-16-text.replay");
+        let mut writer = CoreWriter::new();
+        writer.write_text("\u{2623}D[e]!v1zz\u{2623}");
+        assert_eq!(&writer.into_bytes()[..], &data[..]);
+    }
+
+    #[test]
+    fn write_windows1252_string() {
+        let mut writer = CoreWriter::new();
+        writer.write_text("caudillman6000\u{b3}(2)");
+        let data = writer.into_bytes();
+        let mut parser = CoreParser::new(&data[..]);
+        assert_eq!(parser.parse_text().unwrap(), "caudillman6000\u{b3}(2)");
+        assert_eq!(parser.bytes_read() as usize, data.len());
+    }
+
+    #[test]
+    fn write_empty_text() {
+        let mut writer = CoreWriter::new();
+        writer.write_text("");
+        assert_eq!(writer.into_bytes(), vec![1, 0, 0, 0, 0]);
+    }
+}
@@ -121,6 +121,18 @@ pub struct FrameContext {
     pub actors: FnvHashMap<ActorId, ObjectId>,
     pub new_actors: Vec<NewActor>,
     pub updated_actors: Vec<UpdatedAttribute>,
+
+    /// The index of the frame that failed to decode
+    pub frame: usize,
+
+    /// The bit position in the network data where decoding failed
+    pub bit_position: usize,
+
+    /// The replay's versions (see `Replay::major_version`). The net version is 0 when the replay
+    /// doesn't have one.
+    pub major_version: i32,
+    pub minor_version: i32,
+    pub net_version: i32,
 }
 
 impl FrameContext {
@@ -196,7 +208,7 @@ impl FrameContext {
 
 impl fmt::Display for FrameContext {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "on frame: {}, ", self.frames.len())?;
+        write!(f, "on frame: {}, ", self.frame)?;
         if let Some(updated) = self.updated_actors.last() {
             write!(f, "last updated actor: ")?;
             self.display_update(f, updated)
@@ -402,6 +414,7 @@ pub enum NetworkError {
     StreamTooLargeIndex(i32, i32),
     MissingParentClass(String, String),
     ParentHasNoAttributes(ObjectId, ObjectId),
+    ParentClassCycle(String),
     FrameError(FrameError, Box<FrameContext>),
     TooManyFrames(i32),
 }
@@ -437,6 +450,9 @@ impl Display for NetworkError {
                 "Parent id of {} for object id of {} was not recognized to have attributes",
                 parent_id, object_id
             ),
+            NetworkError::ParentClassCycle(class) => {
+                write!(f, "Parent classes of {} form a cycle", class)
+            }
             NetworkError::TooManyFrames(size) => write!(f, "Too many frames to decode: {}", size),
             NetworkError::FrameError(err, context) => {
                 write!(f, "Error decoding frame: {}. ", err)?;
@@ -446,3 +462,195 @@ impl Display for NetworkError {
         }
     }
 }
+
+#[derive(PartialEq, Debug, Clone)]
+pub enum WriteError {
+    MissingNetworkFrames,
+    NetworkError(Box<NetworkError>),
+    ActorIdOutOfRange {
+        frame: usize,
+        actor: ActorId,
+    },
+    UnexpectedAttribute {
+        frame: usize,
+        actor: ActorId,
+        attribute_stream: StreamId,
+    },
+    DeletedActorUpdated {
+        frame: usize,
+        actor: ActorId,
+    },
+    FrameError(usize, FrameError),
+}
+
+impl Error for WriteError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            WriteError::NetworkError(error) => Some(error),
+            WriteError::FrameError(_, error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl Display for WriteError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            WriteError::MissingNetworkFrames => {
+                write!(f, "Network frames are required to write the network data")
+            }
+            WriteError::NetworkError(error) => write!(f, "{}", error),
+            WriteError::ActorIdOutOfRange { frame, actor } => write!(
+                f,
+                "Actor id of {} in frame {} exceeds the max channels",
+                actor, frame
+            ),
+            WriteError::UnexpectedAttribute {
+                frame,
+                actor,
+                attribute_stream,
+            } => write!(
+                f,
+                "Attribute for actor id / attribute id: {} / {} in frame {} is not the attribute expected for the attribute id",
+                actor, attribute_stream, frame
+            ),
+            WriteError::DeletedActorUpdated { frame, actor } => write!(
+                f,
+                "Actor id {} in frame {} is updated and deleted without being spawned again, but deletions are written before updates",
+                actor, frame
+            ),
+            WriteError::FrameError(frame, error) => {
+                write!(f, "Error encoding frame {}: {}", frame, error)
+            }
+        }
+    }
+}
+
+/// Errors when interpreting the header properties as a `ReplayHeader`. The property is
+/// identified by its path, eg: `PlayerStats[1].Name`
+#[derive(PartialEq, Debug, Clone)]
+pub enum HeaderError {
+    MissingProperty(String),
+    UnexpectedType {
+        property: String,
+        expected: &'static str,
+    },
+}
+
+impl Error for HeaderError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        None
+    }
+}
+
+impl Display for HeaderError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            HeaderError::MissingProperty(property) => {
+                write!(f, "Header property {} is missing", property)
+            }
+            HeaderError::UnexpectedType { property, expected } => write!(
+                f,
+                "Header property {} was expected to be of type {}",
+                property, expected
+            ),
+        }
+    }
+}
+
+/// Errors when analyzing the network frames of a replay
+#[derive(PartialEq, Debug, Clone)]
+pub enum AnalysisError {
+    MissingNetworkFrames,
+    HeaderError(HeaderError),
+    FrameError(usize, FrameError),
+}
+
+impl Error for AnalysisError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            AnalysisError::HeaderError(error) => Some(error),
+            AnalysisError::FrameError(_, error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl Display for AnalysisError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            AnalysisError::MissingNetworkFrames => {
+                write!(f, "Network frames are required for the analysis")
+            }
+            AnalysisError::HeaderError(error) => write!(f, "{}", error),
+            AnalysisError::FrameError(frame, error) => {
+                write!(f, "Error analyzing frame {}: {}", frame, error)
+            }
+        }
+    }
+}
+
+/// Errors when trimming a replay to a range of its network frames
+#[derive(PartialEq, Debug, Clone)]
+pub enum TrimError {
+    MissingNetworkFrames,
+    EmptyRange,
+}
+
+impl Error for TrimError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        None
+    }
+}
+
+impl Display for TrimError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            TrimError::MissingNetworkFrames => {
+                write!(f, "Network frames are required to trim a replay")
+            }
+            TrimError::EmptyRange => write!(f, "The range does not contain any frames"),
+        }
+    }
+}
+
+#[cfg(feature = "csv")]
+#[derive(Debug)]
+pub enum ExportError {
+    AnalysisError(AnalysisError),
+    CsvError(csv::Error),
+}
+
+#[cfg(feature = "csv")]
+impl From<AnalysisError> for ExportError {
+    fn from(error: AnalysisError) -> Self {
+        ExportError::AnalysisError(error)
+    }
+}
+
+#[cfg(feature = "csv")]
+impl From<csv::Error> for ExportError {
+    fn from(error: csv::Error) -> Self {
+        ExportError::CsvError(error)
+    }
+}
+
+#[cfg(feature = "csv")]
+impl Error for ExportError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ExportError::AnalysisError(error) => Some(error),
+            ExportError::CsvError(error) => Some(error),
+        }
+    }
+}
+
+#[cfg(feature = "csv")]
+impl Display for ExportError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            ExportError::AnalysisError(error) => write!(f, "{}", error),
+            ExportError::CsvError(error) => write!(f, "Unable to write csv: {}", error),
+        }
+    }
+}
@@ -1,6 +1,8 @@
 use crate::core_parser::CoreParser;
+use crate::core_writer::CoreWriter;
 use crate::errors::ParseError;
 use crate::models::HeaderProp;
+use crate::parser::{check_crc, CrcCheck};
 use crate::parsing_utils::{le_f32, le_u64};
 
 /// Intermediate parsing structure for the header
@@ -36,7 +38,62 @@ impl Header {
     }
 }
 
-pub fn parse_header(rlp: &mut CoreParser) -> Result<Header, ParseError> {
+/// The header of a replay with the properties left undecoded, see `ParserBuilder::parse_header`.
+///
+/// Looking up properties walks the encoded properties and only decodes the values of the
+/// requested keys, so it is cheaper than decoding all of them when only a few are needed. The
+/// values that are skipped aren't validated, so a lookup may succeed where decoding all the
+/// properties would fail.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HeaderScan<'a> {
+    pub header_size: i32,
+    pub header_crc: u32,
+    pub major_version: i32,
+    pub minor_version: i32,
+    pub net_version: Option<i32>,
+    pub game_type: String,
+
+    /// Positioned at the start of the properties
+    properties: CoreParser<'a>,
+
+    /// The header data for checking the crc when decoding the properties fails
+    data: &'a [u8],
+    crc_check: CrcCheck,
+}
+
+impl<'a> HeaderScan<'a> {
+    /// Decodes the value of the first property with the given key
+    pub fn property(&self, key: &str) -> Result<Option<HeaderProp>, ParseError> {
+        let mut found = self.properties_for(&[key])?;
+        Ok(found.pop().flatten())
+    }
+
+    /// Decodes the value of the first property with each of the given keys in a single pass,
+    /// stopping once all of them are found. The result is parallel to the keys.
+    pub fn properties_for(&self, keys: &[&str]) -> Result<Vec<Option<HeaderProp>>, ParseError> {
+        let mut found = vec![None; keys.len()];
+        self.decode(|rlp| find_rdict(rlp, keys, &mut found))?;
+        Ok(found)
+    }
+
+    /// Decodes all the properties, as found in `Replay::properties`
+    pub fn properties(&self) -> Result<Vec<(String, HeaderProp)>, ParseError> {
+        self.decode(parse_rdict)
+    }
+
+    fn decode<T, F>(&self, f: F) -> Result<T, ParseError>
+    where
+        F: FnOnce(&mut CoreParser<'a>) -> Result<T, ParseError>,
+    {
+        let mut rlp = self.properties.clone();
+        let result = f(&mut rlp).map_err(|e| {
+            ParseError::ParseError("header properties", rlp.bytes_read(), Box::new(e))
+        });
+        check_crc(self.crc_check, self.data, self.header_crc, "header", result)
+    }
+}
+
+fn parse_versions(rlp: &mut CoreParser) -> Result<(i32, i32, Option<i32>), ParseError> {
     let major_version = rlp.take_i32("major version")?;
     let minor_version = rlp.take_i32("minor version")?;
     let net_version = if major_version > 865 && minor_version > 17 {
@@ -45,9 +102,17 @@ pub fn parse_header(rlp: &mut CoreParser) -> Result<Header, ParseError> {
         None
     };
 
-    let game_type = rlp
-        .parse_text()
-        .map_err(|e| ParseError::ParseError("game type", rlp.bytes_read(), Box::new(e)))?;
+    Ok((major_version, minor_version, net_version))
+}
+
+fn parse_game_type(rlp: &mut CoreParser) -> Result<String, ParseError> {
+    rlp.parse_text()
+        .map_err(|e| ParseError::ParseError("game type", rlp.bytes_read(), Box::new(e)))
+}
+
+pub fn parse_header(rlp: &mut CoreParser) -> Result<Header, ParseError> {
+    let (major_version, minor_version, net_version) = parse_versions(rlp)?;
+    let game_type = parse_game_type(rlp)?;
 
     let properties = parse_rdict(rlp)
         .map_err(|e| ParseError::ParseError("header properties", rlp.bytes_read(), Box::new(e)))?;
@@ -61,6 +126,37 @@ pub fn parse_header(rlp: &mut CoreParser) -> Result<Header, ParseError> {
     })
 }
 
+/// Parses the header up to the properties. The header data is the whole section, which the crc
+/// is checked against as configured should decoding the properties fail.
+pub fn scan_header<'a>(
+    rlp: &mut CoreParser<'a>,
+    data: &'a [u8],
+    header_crc: u32,
+    crc_check: CrcCheck,
+) -> Result<HeaderScan<'a>, ParseError> {
+    let (major_version, minor_version, net_version) = parse_versions(rlp)?;
+    let game_type = parse_game_type(rlp)?;
+
+    Ok(HeaderScan {
+        header_size: data.len() as i32,
+        header_crc,
+        major_version,
+        minor_version,
+        net_version,
+        game_type,
+        properties: rlp.clone(),
+        data,
+        crc_check,
+    })
+}
+
+/// Mirrors the limit on lists when decoding arrays
+const MAX_ARRAY_SIZE: usize = 25_000;
+
+fn is_end(key: &str) -> bool {
+    key == "None" || key == "\0\0\0None"
+}
+
 fn parse_rdict(rlp: &mut CoreParser) -> Result<Vec<(String, HeaderProp)>, ParseError> {
     // Other the actual network data, the header property associative array is the hardest to parse.
     // The format is to:
@@ -74,28 +170,108 @@ fn parse_rdict(rlp: &mut CoreParser) -> Result<Vec<(String, HeaderProp)>, ParseE
     let mut res: Vec<_> = Vec::new();
     loop {
         let key = rlp.parse_str()?;
-        if key == "None" || key == "\0\0\0None" {
+        if is_end(key) {
             break;
         }
 
-        let val = match rlp.parse_str()? {
-            "ArrayProperty" => decode_prop(rlp, array_property),
-            "BoolProperty" => decode_prop(rlp, bool_property),
-            "ByteProperty" => decode_prop(rlp, byte_property),
-            "FloatProperty" => decode_prop(rlp, float_property),
-            "IntProperty" => decode_prop(rlp, int_property),
-            "NameProperty" => decode_prop(rlp, name_property),
-            "QWordProperty" => decode_prop(rlp, qword_property),
-            "StrProperty" => decode_prop(rlp, str_property),
-            x => Err(ParseError::UnexpectedProperty(String::from(x))),
-        }?;
-
+        let kind = rlp.parse_str()?;
+        let val = parse_prop(rlp, kind)?;
         res.push((String::from(key), val));
     }
 
     Ok(res)
 }
 
+/// Walks the properties and decodes only the values of the given keys that haven't been found
+fn find_rdict(
+    rlp: &mut CoreParser,
+    keys: &[&str],
+    found: &mut [Option<HeaderProp>],
+) -> Result<(), ParseError> {
+    while found.iter().any(Option::is_none) {
+        let key = rlp.parse_str()?;
+        if is_end(key) {
+            break;
+        }
+
+        let kind = rlp.parse_str()?;
+        let wanted = keys
+            .iter()
+            .zip(found.iter())
+            .any(|(&x, prop)| x == key && prop.is_none());
+
+        if !wanted {
+            skip_prop(rlp, kind)?;
+            continue;
+        }
+
+        let val = parse_prop(rlp, kind)?;
+        for (_, prop) in keys
+            .iter()
+            .zip(found.iter_mut())
+            .filter(|(&x, prop)| x == key && prop.is_none())
+        {
+            *prop = Some(val.clone());
+        }
+    }
+
+    Ok(())
+}
+
+fn parse_prop(rlp: &mut CoreParser, kind: &str) -> Result<HeaderProp, ParseError> {
+    match kind {
+        "ArrayProperty" => decode_prop(rlp, array_property),
+        "BoolProperty" => decode_prop(rlp, bool_property),
+        "ByteProperty" => decode_prop(rlp, byte_property),
+        "FloatProperty" => decode_prop(rlp, float_property),
+        "IntProperty" => decode_prop(rlp, int_property),
+        "NameProperty" => decode_prop(rlp, name_property),
+        "QWordProperty" => decode_prop(rlp, qword_property),
+        "StrProperty" => decode_prop(rlp, str_property),
+        x => Err(ParseError::UnexpectedProperty(String::from(x))),
+    }
+}
+
+/// Advances past a property's value without decoding it
+fn skip_prop(rlp: &mut CoreParser, kind: &str) -> Result<(), ParseError> {
+    match kind {
+        "ArrayProperty" => decode_prop(rlp, |rlp| {
+            let size = rlp.take_i32("array property size")? as usize;
+            if size > MAX_ARRAY_SIZE {
+                return Err(ParseError::ListTooLarge(size));
+            }
+
+            for _ in 0..size {
+                skip_rdict(rlp)?;
+            }
+            Ok(())
+        }),
+        "BoolProperty" => decode_prop(rlp, |rlp| rlp.skip(1)),
+        "ByteProperty" => decode_prop(rlp, |rlp| {
+            if !is_platform(rlp.parse_str()?) {
+                rlp.parse_str()?;
+            }
+            Ok(())
+        }),
+        "FloatProperty" | "IntProperty" => decode_prop(rlp, |rlp| rlp.skip(4)),
+        "NameProperty" | "StrProperty" => decode_prop(rlp, |rlp| rlp.skip_text()),
+        "QWordProperty" => decode_prop(rlp, |rlp| rlp.skip(8)),
+        x => Err(ParseError::UnexpectedProperty(String::from(x))),
+    }
+}
+
+fn skip_rdict(rlp: &mut CoreParser) -> Result<(), ParseError> {
+    loop {
+        let key = rlp.parse_str()?;
+        if is_end(key) {
+            return Ok(());
+        }
+
+        let kind = rlp.parse_str()?;
+        skip_prop(rlp, kind)?;
+    }
+}
+
 // Header properties are encoded in a pretty simple format, with some oddities. The first 64bits
 // is data that can be discarded, some people think that the 64bits is the length of the data
 // while others think that the first 32bits is the header length in bytes with the subsequent
@@ -111,12 +287,23 @@ where
 }
 
 fn byte_property(rlp: &mut CoreParser) -> Result<HeaderProp, ParseError> {
-    // It's unknown (to me at least) why the byte property has two strings in it.
-    match rlp.parse_str()? {
-        "OnlinePlatform_Steam" | "OnlinePlatform_PS4" => Ok(()),
-        _ => rlp.parse_str().map(|_| ()),
-    }?;
-    Ok(HeaderProp::Byte)
+    // A byte property is an enum: the name of the enum followed by the variant, except for older
+    // replays which only contain the variant.
+    let kind = rlp.parse_str()?;
+    let value = if is_platform(kind) {
+        None
+    } else {
+        Some(String::from(rlp.parse_str()?))
+    };
+
+    Ok(HeaderProp::Byte {
+        kind: String::from(kind),
+        value,
+    })
+}
+
+fn is_platform(kind: &str) -> bool {
+    kind == "OnlinePlatform_Steam" || kind == "OnlinePlatform_PS4"
 }
 
 fn str_property(rlp: &mut CoreParser) -> Result<HeaderProp, ParseError> {
@@ -149,6 +336,71 @@ fn array_property(rlp: &mut CoreParser) -> Result<HeaderProp, ParseError> {
     Ok(HeaderProp::Array(arr))
 }
 
+/// The inverse of `parse_header`
+pub fn write_header(w: &mut CoreWriter, header: &Header) {
+    w.write_i32(header.major_version);
+    w.write_i32(header.minor_version);
+    if header.major_version > 865 && header.minor_version > 17 {
+        w.write_i32(header.net_version.unwrap_or(0));
+    }
+
+    w.write_text(&header.game_type);
+    write_rdict(w, &header.properties);
+}
+
+fn write_rdict(w: &mut CoreWriter, properties: &[(String, HeaderProp)]) {
+    for (key, prop) in properties {
+        w.write_str(key);
+        w.write_str(match prop {
+            HeaderProp::Array(_) => "ArrayProperty",
+            HeaderProp::Bool(_) => "BoolProperty",
+            HeaderProp::Byte { .. } => "ByteProperty",
+            HeaderProp::Float(_) => "FloatProperty",
+            HeaderProp::Int(_) => "IntProperty",
+            HeaderProp::Name(_) => "NameProperty",
+            HeaderProp::QWord(_) => "QWordProperty",
+            HeaderProp::Str(_) => "StrProperty",
+        });
+
+        // The first 32 bits that we skip when parsing is the size of the property's value (with
+        // booleans having a size of zero) followed by 32 bits of zeros.
+        let size_offset = w.bytes_written();
+        w.write_i32(0);
+        w.write_i32(0);
+        let mut start = w.bytes_written();
+
+        match prop {
+            HeaderProp::Array(arr) => {
+                w.write_i32(arr.len() as i32);
+                for el in arr {
+                    write_rdict(w, el);
+                }
+            }
+            HeaderProp::Bool(x) => w.write_bytes(&[u8::from(*x)]),
+
+            // The size of a byte property only accounts for the variant
+            HeaderProp::Byte { kind, value } => {
+                w.write_str(kind);
+                if let Some(value) = value {
+                    start = w.bytes_written();
+                    w.write_str(value);
+                }
+            }
+            HeaderProp::Float(x) => w.write_f32(*x),
+            HeaderProp::Int(x) => w.write_i32(*x),
+            HeaderProp::Name(x) | HeaderProp::Str(x) => w.write_text(x),
+            HeaderProp::QWord(x) => w.write_u64(*x),
+        }
+
+        if prop.as_bool().is_none() {
+            let size = w.bytes_written() - start;
+            w.set_i32(size_offset, size as i32);
+        }
+    }
+
+    w.write_str("None");
+}
+
 #[cfg(test)]
 mod tests {
     use crate::core_parser::CoreParser;
@@ -332,7 +584,16 @@ mod tests {
         ));
         let mut parser = CoreParser::new(&data[..]);
         let res = parse_rdict(&mut parser).unwrap();
-        assert_eq!(res, vec![(String::from("Platform"), HeaderProp::Byte)]);
+        assert_eq!(
+            res,
+            vec![(
+                String::from("Platform"),
+                HeaderProp::Byte {
+                    kind: String::from("OnlinePlatform"),
+                    value: Some(String::from("OnlinePlatform_Steam")),
+                }
+            )]
+        );
     }
 
     #[test]
@@ -359,4 +620,115 @@ mod tests {
         assert_eq!(res.len(), 14);
         assert_eq!(res[0], (String::from("TeamSize"), HeaderProp::Int(3)));
     }
+
+    fn assert_rdict_round_trip(data: &[u8]) {
+        let mut parser = CoreParser::new(data);
+        let res = parse_rdict(&mut parser).unwrap();
+        let mut writer = CoreWriter::new();
+        write_rdict(&mut writer, &res);
+        assert_eq!(&writer.into_bytes()[..], data);
+    }
+
+    #[test]
+    fn rdict_write_round_trip() {
+        assert_rdict_round_trip(include_bytes!("../assets/replays/partial/rdict_one.replay"));
+        assert_rdict_round_trip(include_bytes!("../assets/replays/partial/rdict_int.replay"));
+        assert_rdict_round_trip(include_bytes!(
+            "../assets/replays/partial/rdict_bool.replay"
+        ));
+        assert_rdict_round_trip(&append_none(include_bytes!(
+            "../assets/replays/partial/rdict_array.replay"
+        )));
+        assert_rdict_round_trip(&append_none(include_bytes!(
+            "../assets/replays/partial/rdict_qword.replay"
+        )));
+        assert_rdict_round_trip(&append_none(include_bytes!(
+            "../assets/replays/partial/rdict_float.replay"
+        )));
+    }
+
+    #[test]
+    fn rdict_write_byte_property() {
+        assert_rdict_round_trip(&append_none(include_bytes!(
+            "../assets/replays/partial/rdict_byte.replay"
+        )));
+
+        let props = vec![(
+            String::from("Platform"),
+            HeaderProp::Byte {
+                kind: String::from("OnlinePlatform_PS4"),
+                value: None,
+            },
+        )];
+        let mut writer = CoreWriter::new();
+        write_rdict(&mut writer, &props);
+        let data = writer.into_bytes();
+        let mut parser = CoreParser::new(&data[..]);
+        assert_eq!(parse_rdict(&mut parser).unwrap(), props);
+    }
+
+    #[test]
+    fn rdict_find_skips_other_properties() {
+        // Each kind of property is skipped before the one that is looked up
+        let props = vec![
+            (String::from("bBot"), HeaderProp::Bool(false)),
+            (
+                String::from("Platform"),
+                HeaderProp::Byte {
+                    kind: String::from("OnlinePlatform"),
+                    value: Some(String::from("OnlinePlatform_Steam")),
+                },
+            ),
+            (String::from("RecordFPS"), HeaderProp::Float(30.0)),
+            (String::from("PlayerTeam"), HeaderProp::Int(0)),
+            (
+                String::from("MatchType"),
+                HeaderProp::Name(String::from("Online")),
+            ),
+            (
+                String::from("OnlineID"),
+                HeaderProp::QWord(76561198101748375),
+            ),
+            (
+                String::from("Goals"),
+                HeaderProp::Array(vec![vec![(String::from("frame"), HeaderProp::Int(441))]]),
+            ),
+            (String::from("Id"), HeaderProp::Str(String::from("5BCA5D"))),
+            (
+                String::from("Id"),
+                HeaderProp::Str(String::from("duplicate")),
+            ),
+        ];
+
+        let mut writer = CoreWriter::new();
+        write_rdict(&mut writer, &props);
+        let data = writer.into_bytes();
+
+        let keys = ["Id", "Missing", "bBot", "Id"];
+        let mut found = vec![None; keys.len()];
+        let mut parser = CoreParser::new(&data[..]);
+        find_rdict(&mut parser, &keys, &mut found).unwrap();
+        assert_eq!(
+            found,
+            vec![
+                Some(HeaderProp::Str(String::from("5BCA5D"))),
+                None,
+                Some(HeaderProp::Bool(false)),
+                Some(HeaderProp::Str(String::from("5BCA5D"))),
+            ]
+        );
+    }
+
+    #[test]
+    fn rdict_find_stops_once_found() {
+        // Properties after the ones looked up aren't read
+        let data = include_bytes!("../assets/replays/partial/rdict_one.replay");
+        let mut parser = CoreParser::new(&data[..data.len() - 9]);
+        let mut found = vec![None];
+        find_rdict(&mut parser, &["PlayerName"], &mut found).unwrap();
+        assert_eq!(
+            found,
+            vec![Some(HeaderProp::Str(String::from("comagoosie")))]
+        );
+    }
 }
@@ -58,12 +58,26 @@ extern crate if_chain;
 #[macro_use]
 extern crate serde;
 
-pub use self::errors::{AttributeError, FrameContext, FrameError, NetworkError, ParseError};
+pub use self::anonymize::Anonymizer;
+#[cfg(feature = "csv")]
+pub use self::errors::ExportError;
+pub use self::errors::{
+    AnalysisError, AttributeError, FrameContext, FrameError, HeaderError, NetworkError, ParseError,
+    TrimError, WriteError,
+};
+pub use self::header::HeaderScan;
 pub use self::models::*;
-pub use self::network::attributes::Attribute;
+pub use self::network::attributes::{Attribute, AttributeTag};
 pub use self::network::*;
 pub use self::parser::{CrcCheck, NetworkParse, ParserBuilder};
+pub use self::replay_header::{Goal, Highlight, PlayerStats, ReplayHeader};
+pub use self::report::{ErrorReport, ReportedUpdate};
+pub use self::trim::{trim, TrimRange};
+pub use self::writer::ReplayWriter;
+mod anonymize;
+mod bit_writer;
 mod core_parser;
+mod core_writer;
 pub mod crc;
 mod data;
 mod errors;
@@ -72,4 +86,10 @@ mod models;
 mod network;
 mod parser;
 mod parsing_utils;
+mod replay_header;
+mod report;
 mod serde_utils;
+mod trim;
+#[cfg(feature = "wasm")]
+mod wasm;
+mod writer;
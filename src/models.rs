@@ -3,18 +3,22 @@
 /// Here lies the data structures that a rocket league replay is decoded into. All of the models
 /// are contained in this one file because of serde.
 ///
-/// For serde, we only care about serialization, JSON serialization. Deserialization is not
-/// implemented from our JSON output because it is lossy (JSON isn't the best with different
-/// numeric/string types). Asking "why JSON" would be next logical step, and that's due to other
-/// rocket league replay parsers (like Octane) using JSON; however, the output of this library is
-/// not compatible with that of other rocket league replay parsers.
+/// For serde, we mainly care about JSON serialization. The JSON output can be deserialized back
+/// into the same models, so a replay can be cached as JSON and reloaded without parsing the binary
+/// again. Asking "why JSON" would be next logical step, and that's due to other rocket league
+/// replay parsers (like Octane) using JSON; however, the output of this library is not compatible
+/// with that of other rocket league replay parsers.
+use crate::errors::NetworkError;
 use crate::network::Frame;
+use serde::de::{self, Deserialize, Deserializer, MapAccess, SeqAccess, Visitor};
 use serde::ser::{SerializeMap, SerializeSeq};
 use serde::{Serialize, Serializer};
-use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fmt;
+use std::marker::PhantomData;
 
 /// The structure that a rocket league replay is parsed into.
-#[derive(Serialize, PartialEq, Debug, Clone)]
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
 pub struct Replay {
     pub header_size: i32,
     pub header_crc: u32,
@@ -25,11 +29,16 @@ pub struct Replay {
 
     /// Could use a map to represent properties but I don't want to assume that duplicate keys
     /// can't exist, so to be safe, use a traditional vector.
-    #[serde(serialize_with = "pair_vec")]
+    #[serde(serialize_with = "pair_vec", deserialize_with = "de_pair_vec")]
     pub properties: Vec<(String, HeaderProp)>,
     pub content_size: i32,
     pub content_crc: u32,
     pub network_frames: Option<NetworkFrames>,
+
+    /// When parsed with `NetworkParse::Partial`, the error that stopped decoding the network
+    /// data. The frames decoded prior to the error are kept in `network_frames`.
+    #[serde(skip)]
+    pub network_error: Option<NetworkError>,
     pub levels: Vec<String>,
     pub keyframes: Vec<KeyFrame>,
     pub debug_info: Vec<DebugInfo>,
@@ -42,7 +51,7 @@ pub struct Replay {
 }
 
 /// The frames decoded from the network data
-#[derive(Serialize, PartialEq, Debug, Clone)]
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
 pub struct NetworkFrames {
     pub frames: Vec<Frame>,
 }
@@ -51,7 +60,7 @@ pub struct NetworkFrames {
 /// the game (eg. a goal). The tick mark is placed before the event happens so there is a ramp-up
 /// time. For instance, a tickmark could be at frame 396 for a goal at frame 441. At 30 fps, this
 /// would be 1.5 seconds of ramp up time.
-#[derive(Serialize, PartialEq, Debug, Clone)]
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
 pub struct TickMark {
     pub description: String,
     pub frame: i32,
@@ -62,7 +71,7 @@ pub struct TickMark {
 /// match up with the frames decoded from the network data.
 ///
 /// [wikipedia]: https://en.wikipedia.org/wiki/Key_frame#Video_compression
-#[derive(Serialize, PartialEq, Debug, Clone, Copy)]
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Copy)]
 pub struct KeyFrame {
     pub time: f32,
     pub frame: i32,
@@ -81,7 +90,14 @@ pub struct KeyFrame {
 pub enum HeaderProp {
     Array(Vec<Vec<(String, HeaderProp)>>),
     Bool(bool),
-    Byte,
+
+    /// A byte property is an enum value where `kind` is the name of the enum and `value` is the
+    /// selected variant (eg: `OnlinePlatform` and `OnlinePlatform_Steam`). Older replays only
+    /// contain the variant, which is then stored as the `kind`.
+    Byte {
+        kind: String,
+        value: Option<String>,
+    },
     Float(f32),
     Int(i32),
     Name(String),
@@ -94,7 +110,7 @@ impl HeaderProp {
     /// ```
     /// # use boxcars::HeaderProp;
     /// let v = HeaderProp::Array(vec![
-    ///     vec![("abc".to_string(), HeaderProp::Byte)]
+    ///     vec![("abc".to_string(), HeaderProp::Int(1))]
     /// ]);
     ///
     /// assert_eq!(v.as_array().unwrap().len(), 1);
@@ -112,7 +128,7 @@ impl HeaderProp {
     /// ```
     /// # use boxcars::HeaderProp;
     /// let v = HeaderProp::Bool(true);
-    /// let b = HeaderProp::Byte;
+    /// let b = HeaderProp::Name("abc".to_string());
     ///
     /// assert_eq!(v.as_bool(), Some(true));
     /// assert_eq!(b.as_bool(), None);
@@ -129,7 +145,7 @@ impl HeaderProp {
     /// ```
     /// # use boxcars::HeaderProp;
     /// let v = HeaderProp::Float(2.50);
-    /// let b = HeaderProp::Byte;
+    /// let b = HeaderProp::Name("abc".to_string());
     ///
     /// assert_eq!(v.as_float(), Some(2.50));
     /// assert_eq!(b.as_float(), None);
@@ -146,7 +162,7 @@ impl HeaderProp {
     /// ```
     /// # use boxcars::HeaderProp;
     /// let v = HeaderProp::QWord(250);
-    /// let b = HeaderProp::Byte;
+    /// let b = HeaderProp::Name("abc".to_string());
     ///
     /// assert_eq!(v.as_u64(), Some(250));
     /// assert_eq!(b.as_u64(), None);
@@ -163,7 +179,7 @@ impl HeaderProp {
     /// ```
     /// # use boxcars::HeaderProp;
     /// let v = HeaderProp::Int(-250);
-    /// let b = HeaderProp::Byte;
+    /// let b = HeaderProp::Name("abc".to_string());
     ///
     /// assert_eq!(v.as_i32(), Some(-250));
     /// assert_eq!(b.as_i32(), None);
@@ -181,7 +197,7 @@ impl HeaderProp {
     /// # use boxcars::HeaderProp;
     /// let v = HeaderProp::Name("abc".to_string());
     /// let x = HeaderProp::Str("def".to_string());
-    /// let b = HeaderProp::Byte;
+    /// let b = HeaderProp::Bool(true);
     ///
     /// assert_eq!(v.as_string(), Some("abc"));
     /// assert_eq!(x.as_string(), Some("def"));
@@ -199,22 +215,51 @@ impl HeaderProp {
     /// ```
     /// # use boxcars::HeaderProp;
     /// let v = HeaderProp::Name("abc".to_string());
-    /// let b = HeaderProp::Byte;
+    /// let b = HeaderProp::Byte {
+    ///     kind: "OnlinePlatform".to_string(),
+    ///     value: Some("OnlinePlatform_Steam".to_string()),
+    /// };
     ///
     /// assert_eq!(v.is_byte(), false);
     /// assert_eq!(b.is_byte(), true);
     /// ```
     pub fn is_byte(&self) -> bool {
-        if let HeaderProp::Byte = self {
+        if let HeaderProp::Byte { .. } = self {
             true
         } else {
             false
         }
     }
+
+    /// If the `HeaderProp` is a byte, returns the selected variant of the enum. For older replays
+    /// that only contain the variant, the variant is returned.
+    /// ```
+    /// # use boxcars::HeaderProp;
+    /// let v = HeaderProp::Byte {
+    ///     kind: "OnlinePlatform".to_string(),
+    ///     value: Some("OnlinePlatform_Steam".to_string()),
+    /// };
+    /// let x = HeaderProp::Byte {
+    ///     kind: "OnlinePlatform_PS4".to_string(),
+    ///     value: None,
+    /// };
+    /// let b = HeaderProp::Int(1);
+    ///
+    /// assert_eq!(v.as_byte(), Some("OnlinePlatform_Steam"));
+    /// assert_eq!(x.as_byte(), Some("OnlinePlatform_PS4"));
+    /// assert_eq!(b.as_byte(), None);
+    /// ```
+    pub fn as_byte(&self) -> Option<&str> {
+        if let HeaderProp::Byte { kind, value } = self {
+            Some(value.as_ref().unwrap_or(kind).as_str())
+        } else {
+            None
+        }
+    }
 }
 
 /// Debugging info stored in the replay if debugging is enabled.
-#[derive(Serialize, PartialEq, Debug, Clone)]
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
 pub struct DebugInfo {
     pub frame: i32,
     pub user: String,
@@ -222,7 +267,7 @@ pub struct DebugInfo {
 }
 
 /// A mapping between an object's name and its index. Largely redundant
-#[derive(Serialize, PartialEq, Debug, Clone)]
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
 pub struct ClassIndex {
     /// Should be equivalent to `Replay::objects(self.index)`
     pub class: String,
@@ -233,7 +278,7 @@ pub struct ClassIndex {
 
 /// A mapping between an object (that's an attribute)'s index and what its id will be when encoded
 /// in the network data
-#[derive(Serialize, PartialEq, Debug, Clone, Copy)]
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Copy)]
 pub struct CacheProp {
     /// The index that the object appears in the `Replay::objects`
     pub object_ind: i32,
@@ -244,7 +289,7 @@ pub struct CacheProp {
 }
 
 /// Contains useful information when decoding the network stream
-#[derive(Serialize, PartialEq, Debug, Clone)]
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
 pub struct ClassNetCache {
     /// The index that the object appears in the `Replay::objects`
     pub object_ind: i32,
@@ -279,10 +324,82 @@ where
     state.end()
 }
 
+/// The inverse of `pair_vec`: a map is deserialized into a vector of key value tuples in the order
+/// that the keys appear.
+fn de_pair_vec<'de, K, V, D>(deserializer: D) -> Result<Vec<(K, V)>, D::Error>
+where
+    K: Deserialize<'de>,
+    V: Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    struct PairVecVisitor<K, V>(PhantomData<(K, V)>);
+
+    impl<'de, K, V> Visitor<'de> for PairVecVisitor<K, V>
+    where
+        K: Deserialize<'de>,
+        V: Deserialize<'de>,
+    {
+        type Value = Vec<(K, V)>;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+            formatter.write_str("a map")
+        }
+
+        fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+            let mut result = Vec::with_capacity(map.size_hint().unwrap_or(0));
+            while let Some(entry) = map.next_entry()? {
+                result.push(entry);
+            }
+            Ok(result)
+        }
+    }
+
+    deserializer.deserialize_map(PairVecVisitor(PhantomData))
+}
+
+/// Serializes the properties of a header array element as a map with the keys in order
+struct PropertyMap<'a>(&'a [(String, HeaderProp)]);
+
+impl<'a> Serialize for PropertyMap<'a> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        pair_vec(self.0, serializer)
+    }
+}
+
+/// Deserializes the properties of a header array element
+struct PropertyVec(Vec<(String, HeaderProp)>);
+
+impl<'de> Deserialize<'de> for PropertyVec {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        de_pair_vec(deserializer).map(PropertyVec)
+    }
+}
+
+#[derive(Serialize)]
+struct ByteRef<'a> {
+    kind: &'a str,
+    value: Option<&'a str>,
+}
+
+#[derive(Deserialize)]
+struct ByteOwned {
+    kind: String,
+    value: Option<String>,
+}
+
 /// By default serde will generate a serialization method that writes out the enum as well as the
-/// enum value. Since header values are self describing in JSON, we do not need to serialize the
-/// enum type. This is slightly lossy as in the serialized format it will be ambiguous if a value
-/// is a `Name` or `Str`, as well as `Byte`, `Float`, `Int`, or `QWord`.
+/// enum value. Since most header values are self describing in JSON, we do not need to serialize
+/// the enum type for them: arrays, bools, ints (`11`), floats (`10.2` or `10.0`), and strings are
+/// written as is. The remaining variants would be ambiguous with a string, so they are written as
+/// an object keyed by the variant: `{"Name": "Stadium_P"}`, `{"QWord": "76561198122624102"}`, and
+/// `{"Byte": {"kind": "OnlinePlatform", "value": "OnlinePlatform_Steam"}}`. A QWord is written as
+/// a string for the same reason as `display_it`.
 impl Serialize for HeaderProp {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -292,25 +409,124 @@ impl Serialize for HeaderProp {
             HeaderProp::Array(ref x) => {
                 let mut state = serializer.serialize_seq(Some(x.len()))?;
                 for inner in x {
-                    // Look for a better way to do this instead of allocating the intermediate map
-                    let mut els = HashMap::new();
-                    for (key, val) in inner.iter() {
-                        els.insert(key, val);
-                    }
-                    state.serialize_element(&els)?;
+                    state.serialize_element(&PropertyMap(inner))?;
                 }
                 state.end()
             }
             HeaderProp::Bool(ref x) => serializer.serialize_bool(*x),
-            HeaderProp::Byte => serializer.serialize_u8(0),
+            HeaderProp::Byte {
+                ref kind,
+                ref value,
+            } => {
+                let mut state = serializer.serialize_map(Some(1))?;
+                let byte = ByteRef {
+                    kind,
+                    value: value.as_deref(),
+                };
+                state.serialize_entry("Byte", &byte)?;
+                state.end()
+            }
             HeaderProp::Float(ref x) => serializer.serialize_f32(*x),
             HeaderProp::Int(ref x) => serializer.serialize_i32(*x),
-            HeaderProp::QWord(ref x) => serializer.collect_str(x),
-            HeaderProp::Name(ref x) | HeaderProp::Str(ref x) => serializer.serialize_str(x),
+            HeaderProp::QWord(ref x) => {
+                let mut state = serializer.serialize_map(Some(1))?;
+                state.serialize_entry("QWord", &x.to_string())?;
+                state.end()
+            }
+            HeaderProp::Name(ref x) => {
+                let mut state = serializer.serialize_map(Some(1))?;
+                state.serialize_entry("Name", x)?;
+                state.end()
+            }
+            HeaderProp::Str(ref x) => serializer.serialize_str(x),
         }
     }
 }
 
+impl<'de> Deserialize<'de> for HeaderProp {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct HeaderPropVisitor;
+
+        impl<'de> Visitor<'de> for HeaderPropVisitor {
+            type Value = HeaderProp;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+                formatter.write_str("a header property")
+            }
+
+            fn visit_bool<E: de::Error>(self, v: bool) -> Result<Self::Value, E> {
+                Ok(HeaderProp::Bool(v))
+            }
+
+            fn visit_i64<E: de::Error>(self, v: i64) -> Result<Self::Value, E> {
+                i32::try_from(v)
+                    .map(HeaderProp::Int)
+                    .map_err(|_| E::invalid_value(de::Unexpected::Signed(v), &self))
+            }
+
+            fn visit_u64<E: de::Error>(self, v: u64) -> Result<Self::Value, E> {
+                i32::try_from(v)
+                    .map(HeaderProp::Int)
+                    .map_err(|_| E::invalid_value(de::Unexpected::Unsigned(v), &self))
+            }
+
+            fn visit_f64<E: de::Error>(self, v: f64) -> Result<Self::Value, E> {
+                Ok(HeaderProp::Float(v as f32))
+            }
+
+            fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+                Ok(HeaderProp::Str(String::from(v)))
+            }
+
+            fn visit_string<E: de::Error>(self, v: String) -> Result<Self::Value, E> {
+                Ok(HeaderProp::Str(v))
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+                let mut result = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+                while let Some(PropertyVec(props)) = seq.next_element()? {
+                    result.push(props);
+                }
+                Ok(HeaderProp::Array(result))
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+                const VARIANTS: &[&str] = &["Byte", "Name", "QWord"];
+                let key: String = map
+                    .next_key()?
+                    .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+
+                let result = match key.as_str() {
+                    "Byte" => {
+                        let byte: ByteOwned = map.next_value()?;
+                        HeaderProp::Byte {
+                            kind: byte.kind,
+                            value: byte.value,
+                        }
+                    }
+                    "Name" => HeaderProp::Name(map.next_value()?),
+                    "QWord" => {
+                        let val: String = map.next_value()?;
+                        HeaderProp::QWord(val.parse().map_err(de::Error::custom)?)
+                    }
+                    _ => return Err(de::Error::unknown_variant(&key, VARIANTS)),
+                };
+
+                if map.next_key::<de::IgnoredAny>()?.is_some() {
+                    return Err(de::Error::invalid_length(2, &self));
+                }
+
+                Ok(result)
+            }
+        }
+
+        deserializer.deserialize_any(HeaderPropVisitor)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -352,9 +568,16 @@ mod tests {
 
     #[test]
     fn serialize_header_numbers() {
-        assert_eq!(to_json(&HeaderProp::Byte), "0");
-        assert_eq!(to_json(&HeaderProp::QWord(10)), "\"10\"");
+        assert_eq!(
+            to_json(&HeaderProp::Byte {
+                kind: String::from("OnlinePlatform"),
+                value: Some(String::from("OnlinePlatform_Steam")),
+            }),
+            r#"{"Byte":{"kind":"OnlinePlatform","value":"OnlinePlatform_Steam"}}"#
+        );
+        assert_eq!(to_json(&HeaderProp::QWord(10)), r#"{"QWord":"10"}"#);
         assert_eq!(to_json(&HeaderProp::Float(10.2)), "10.2");
+        assert_eq!(to_json(&HeaderProp::Float(10.0)), "10.0");
         assert_eq!(to_json(&HeaderProp::Int(11)), "11");
     }
 
@@ -367,7 +590,52 @@ mod tests {
         );
         assert_eq!(
             to_json(&HeaderProp::Name(String::from(val))),
-            "\"hello world\""
+            r#"{"Name":"hello world"}"#
         );
     }
+
+    fn round_trip(prop: HeaderProp) {
+        let json = to_json(&prop);
+        let actual: HeaderProp = serde_json::from_str(&json).unwrap();
+        assert_eq!(actual, prop, "{}", json);
+    }
+
+    #[test]
+    fn deserialize_header_props() {
+        round_trip(HeaderProp::Bool(true));
+        round_trip(HeaderProp::Int(-11));
+        round_trip(HeaderProp::Float(10.0));
+        round_trip(HeaderProp::Float(30.000_002));
+        round_trip(HeaderProp::QWord(76561198122624102));
+        round_trip(HeaderProp::Str(String::from("1")));
+        round_trip(HeaderProp::Name(String::from("Stadium_P")));
+        round_trip(HeaderProp::Byte {
+            kind: String::from("OnlinePlatform"),
+            value: Some(String::from("OnlinePlatform_Steam")),
+        });
+        round_trip(HeaderProp::Byte {
+            kind: String::from("OnlinePlatform_PS4"),
+            value: None,
+        });
+        round_trip(HeaderProp::Array(vec![
+            vec![
+                (
+                    String::from("PlayerName"),
+                    HeaderProp::Str(String::from("a")),
+                ),
+                (String::from("frame"), HeaderProp::Int(441)),
+                (String::from("frame"), HeaderProp::Int(442)),
+            ],
+            vec![],
+        ]));
+    }
+
+    #[test]
+    fn deserialize_header_errors() {
+        assert!(serde_json::from_str::<HeaderProp>("null").is_err());
+        assert!(serde_json::from_str::<HeaderProp>("4294967296").is_err());
+        assert!(serde_json::from_str::<HeaderProp>(r#"{"Int":1}"#).is_err());
+        assert!(serde_json::from_str::<HeaderProp>(r#"{"QWord":"a"}"#).is_err());
+        assert!(serde_json::from_str::<HeaderProp>(r#"{"Name":"a","QWord":"1"}"#).is_err());
+    }
 }
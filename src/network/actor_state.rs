@@ -0,0 +1,437 @@
+use crate::errors::FrameError;
+use crate::network::attributes::Attribute;
+use crate::network::models::{normalize_object, ActorId, Frame, ObjectId, Trajectory};
+use crate::network::ClassTables;
+use fnv::FnvHashMap;
+use std::collections::HashMap;
+
+/// The state of an actor: what it is, where it spawned, and the latest value of its attributes.
+/// When the tracker records the history, the values its attributes have taken throughout its
+/// lifetime are kept as well.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ActorState<'a> {
+    pub actor_id: ActorId,
+    pub object_id: ObjectId,
+
+    /// The actor's object name (eg: `Archetypes.Car.Car_Default`)
+    pub object_name: &'a str,
+
+    /// The class of the actor's object (eg: `TAGame.Car_TA`) if known
+    pub class_name: Option<&'a str>,
+    pub initial_trajectory: Trajectory,
+
+    /// The index of the frame where the actor was created
+    pub created_frame: usize,
+
+    /// The index of the frame where the actor was deleted (if it has been deleted)
+    pub deleted_frame: Option<usize>,
+
+    /// Attribute object name to its latest value
+    attributes: FnvHashMap<&'a str, Attribute>,
+
+    /// Attribute object name to the attribute values keyed by the frame index they were set in,
+    /// when the history is recorded
+    history: Option<FnvHashMap<&'a str, Vec<(usize, Attribute)>>>,
+}
+
+impl<'a> ActorState<'a> {
+    /// Returns true if the actor is alive at the given frame index
+    pub fn is_alive_at(&self, frame: usize) -> bool {
+        self.created_frame <= frame && self.deleted_frame.filter(|&x| x <= frame).is_none()
+    }
+
+    /// Returns true if the actor's object name or class is the given name
+    pub fn is_a(&self, name: &str) -> bool {
+        self.object_name == name || self.class_name == Some(name)
+    }
+
+    /// The latest value of an attribute by its object name (eg:
+    /// `Engine.PlayerReplicationInfo:PlayerName`)
+    pub fn attribute(&self, name: &str) -> Option<&Attribute> {
+        self.attributes.get(name)
+    }
+
+    /// The value of an attribute as of the given frame index. Always `None` unless the tracker
+    /// records the history (see `ActorStateTracker::with_history`).
+    pub fn attribute_at(&self, name: &str, frame: usize) -> Option<&Attribute> {
+        let values = self.history.as_ref()?.get(name)?;
+        let ind = values.partition_point(|&(x, _)| x <= frame);
+        values[..ind].last().map(|(_, attr)| attr)
+    }
+
+    /// The latest value of every attribute that has been set on the actor
+    pub fn attributes(&self) -> impl Iterator<Item = (&'a str, &Attribute)> + '_ {
+        self.attributes.iter().map(|(name, attr)| (*name, attr))
+    }
+}
+
+/// Materializes the world state from the deltas contained in network frames. Frames are fed in
+/// order and the tracker keeps every actor that has been seen (including deleted actors, as actor
+/// ids are reused). Only the latest value of each attribute is kept unless the history is recorded
+/// with `with_history`, so that attributes can be queried at any frame that has been processed.
+///
+/// ```
+/// use boxcars::{ActorStateTracker, Attribute, ClassTables, ParserBuilder};
+///
+/// let data = include_bytes!("../../assets/replays/good/rumble.replay");
+/// let replay = ParserBuilder::new(&data[..])
+///     .must_parse_network_data()
+///     .parse()
+///     .unwrap();
+///
+/// let tables = ClassTables::default();
+/// let mut tracker = ActorStateTracker::new(&replay.objects, &tables);
+/// for frame in &replay.network_frames.as_ref().unwrap().frames {
+///     tracker.process_frame(frame).unwrap();
+/// }
+///
+/// let names: Vec<_> = tracker
+///     .actors_of_class("TAGame.PRI_TA")
+///     .filter_map(|x| x.attribute("Engine.PlayerReplicationInfo:PlayerName"))
+///     .collect();
+///
+/// assert!(names.contains(&&Attribute::String(String::from("comagoosie"))));
+/// ```
+#[derive(Debug, Clone)]
+pub struct ActorStateTracker<'a> {
+    objects: &'a [String],
+    classes: HashMap<&'a str, &'a str>,
+    actors: Vec<ActorState<'a>>,
+
+    /// Actor id to the indices in `actors` that have had the id, in order of creation. The last
+    /// index is the current actor if it hasn't been deleted.
+    actor_ids: FnvHashMap<ActorId, Vec<usize>>,
+    frames_processed: usize,
+    record_history: bool,
+}
+
+impl<'a> ActorStateTracker<'a> {
+    /// Creates a tracker from the replay's objects, which are used to name actors and attributes,
+    /// and the tables the replay was parsed with, which are used to find the class of an actor
+    pub fn new(objects: &'a [String], tables: &'a ClassTables) -> Self {
+        ActorStateTracker {
+            objects,
+            classes: tables.object_classes().into_iter().collect(),
+            actors: Vec::new(),
+            actor_ids: FnvHashMap::default(),
+            frames_processed: 0,
+            record_history: false,
+        }
+    }
+
+    /// Records every value that each attribute takes, which `attribute_at` requires. Off by
+    /// default, as the history holds a copy of every update in the network data.
+    pub fn with_history(mut self) -> Self {
+        self.record_history = true;
+        self
+    }
+
+    /// The number of frames processed. This is the index that will be given to the next frame.
+    pub fn frames_processed(&self) -> usize {
+        self.frames_processed
+    }
+
+    /// Applies the frame's deleted, new, and updated actors (in that order) to the state.
+    ///
+    /// A new actor for an id that is still alive is either a re-announcement, when it is of the
+    /// same object (as happens at keyframes), in which case the actor keeps its state, or the id
+    /// has been reused without an explicit delete and the previous actor is retired.
+    pub fn process_frame(&mut self, frame: &Frame) -> Result<(), FrameError> {
+        let frame_ind = self.frames_processed;
+
+        for actor_id in &frame.deleted_actors {
+            let ind = self
+                .current_index(*actor_id)
+                .ok_or(FrameError::MissingActor { actor: *actor_id })?;
+            self.actors[ind].deleted_frame = Some(frame_ind);
+        }
+
+        for actor in &frame.new_actors {
+            if let Some(ind) = self.current_index(actor.actor_id) {
+                // Keyframes re-announce live actors, which are not new incarnations
+                if self.actors[ind].object_id == actor.object_id {
+                    continue;
+                }
+
+                // An actor id may be reused without an explicit delete, so retire the previous
+                // actor
+                self.actors[ind].deleted_frame = Some(frame_ind);
+            }
+
+            let object_name = self.object_name(actor.object_id)?;
+            let class_name = self.classes.get(normalize_object(object_name)).cloned();
+            self.actor_ids
+                .entry(actor.actor_id)
+                .or_default()
+                .push(self.actors.len());
+
+            self.actors.push(ActorState {
+                actor_id: actor.actor_id,
+                object_id: actor.object_id,
+                object_name,
+                class_name,
+                initial_trajectory: actor.initial_trajectory,
+                created_frame: frame_ind,
+                deleted_frame: None,
+                attributes: FnvHashMap::default(),
+                history: if self.record_history {
+                    Some(FnvHashMap::default())
+                } else {
+                    None
+                },
+            });
+        }
+
+        for update in &frame.updated_actors {
+            let ind = self
+                .current_index(update.actor_id)
+                .ok_or(FrameError::MissingActor {
+                    actor: update.actor_id,
+                })?;
+            let name = self.object_name(update.object_id)?;
+            let actor = &mut self.actors[ind];
+            if let Some(history) = actor.history.as_mut() {
+                history
+                    .entry(name)
+                    .or_default()
+                    .push((frame_ind, update.attribute.clone()));
+            }
+            actor.attributes.insert(name, update.attribute.clone());
+        }
+
+        self.frames_processed += 1;
+        Ok(())
+    }
+
+    /// The actor currently occupying the actor id
+    pub fn actor(&self, actor_id: ActorId) -> Option<&ActorState<'a>> {
+        self.current_index(actor_id).map(|ind| &self.actors[ind])
+    }
+
+    /// The actor that occupied the actor id at the given frame index
+    pub fn actor_at(&self, actor_id: ActorId, frame: usize) -> Option<&ActorState<'a>> {
+        self.actor_ids.get(&actor_id).and_then(|inds| {
+            inds.iter()
+                .rev()
+                .map(|&ind| &self.actors[ind])
+                .find(|x| x.is_alive_at(frame))
+        })
+    }
+
+    /// The value of an actor's attribute as of the given frame index, when the history is recorded
+    pub fn attribute_at(&self, actor_id: ActorId, name: &str, frame: usize) -> Option<&Attribute> {
+        self.actor_at(actor_id, frame)
+            .and_then(|x| x.attribute_at(name, frame))
+    }
+
+    /// All actors that are currently alive
+    pub fn actors(&self) -> impl Iterator<Item = &ActorState<'a>> {
+        self.actors.iter().filter(|x| x.deleted_frame.is_none())
+    }
+
+    /// All actors that have been seen, including those that have been deleted, in order of
+    /// creation
+    pub fn all_actors(&self) -> &[ActorState<'a>] {
+        &self.actors
+    }
+
+    /// All actors currently alive whose object name or class is the given name (eg:
+    /// `TAGame.Car_TA` or `Archetypes.Car.Car_Default`)
+    pub fn actors_of_class<'b>(
+        &'b self,
+        name: &'b str,
+    ) -> impl Iterator<Item = &'b ActorState<'a>> + 'b {
+        self.actors().filter(move |x| x.is_a(name))
+    }
+
+    fn current_index(&self, actor_id: ActorId) -> Option<usize> {
+        self.actor_ids
+            .get(&actor_id)
+            .and_then(|x| x.last())
+            .cloned()
+            .filter(|&ind| self.actors[ind].deleted_frame.is_none())
+    }
+
+    pub(crate) fn object_name(&self, object_id: ObjectId) -> Result<&'a str, FrameError> {
+        self.objects
+            .get(usize::from(object_id))
+            .map(|x| x.as_str())
+            .ok_or(FrameError::ObjectIdOutOfRange { obj: object_id })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::models::{NewActor, StreamId, UpdatedAttribute};
+
+    fn objects() -> Vec<String> {
+        vec![
+            String::from("Archetypes.Car.Car_Default"),
+            String::from("Engine.Pawn:PlayerReplicationInfo"),
+            String::from("TAGame.Vehicle_TA:ReplicatedThrottle"),
+        ]
+    }
+
+    fn frame(new_actors: Vec<NewActor>, deleted: Vec<ActorId>, updated: Vec<(i32, u8)>) -> Frame {
+        Frame {
+            time: 0.0,
+            delta: 0.0,
+            new_actors,
+            deleted_actors: deleted,
+            updated_actors: updated
+                .into_iter()
+                .map(|(actor, throttle)| UpdatedAttribute {
+                    actor_id: ActorId(actor),
+                    stream_id: StreamId(1),
+                    object_id: ObjectId(2),
+                    attribute: Attribute::Byte(throttle),
+                })
+                .collect(),
+        }
+    }
+
+    fn new_car(actor: i32) -> NewActor {
+        NewActor {
+            actor_id: ActorId(actor),
+            name_id: None,
+            object_id: ObjectId(0),
+            initial_trajectory: Trajectory {
+                location: None,
+                rotation: None,
+            },
+        }
+    }
+
+    #[test]
+    fn test_tracker_attribute_history() {
+        let objects = objects();
+        let tables = ClassTables::default();
+        let mut tracker = ActorStateTracker::new(&objects, &tables).with_history();
+        tracker
+            .process_frame(&frame(vec![new_car(1)], vec![], vec![(1, 10)]))
+            .unwrap();
+        tracker
+            .process_frame(&frame(vec![], vec![], vec![]))
+            .unwrap();
+        tracker
+            .process_frame(&frame(vec![], vec![], vec![(1, 20)]))
+            .unwrap();
+
+        let throttle = "TAGame.Vehicle_TA:ReplicatedThrottle";
+        let car = tracker.actor(ActorId(1)).unwrap();
+        assert_eq!(car.class_name, Some("TAGame.Car_TA"));
+        assert_eq!(car.attribute(throttle), Some(&Attribute::Byte(20)));
+        assert_eq!(car.attributes().count(), 1);
+        assert_eq!(
+            tracker.attribute_at(ActorId(1), throttle, 1),
+            Some(&Attribute::Byte(10))
+        );
+        assert_eq!(
+            tracker.attribute_at(ActorId(1), throttle, 2),
+            Some(&Attribute::Byte(20))
+        );
+        assert_eq!(tracker.actors_of_class("TAGame.Car_TA").count(), 1);
+    }
+
+    #[test]
+    fn test_tracker_latest_attributes() {
+        let objects = objects();
+        let tables = ClassTables::default();
+        let mut tracker = ActorStateTracker::new(&objects, &tables);
+        tracker
+            .process_frame(&frame(vec![new_car(1)], vec![], vec![(1, 10)]))
+            .unwrap();
+        tracker
+            .process_frame(&frame(vec![], vec![], vec![(1, 20)]))
+            .unwrap();
+
+        let throttle = "TAGame.Vehicle_TA:ReplicatedThrottle";
+        let car = tracker.actor(ActorId(1)).unwrap();
+        assert_eq!(car.attribute(throttle), Some(&Attribute::Byte(20)));
+        assert_eq!(car.attribute_at(throttle, 1), None);
+    }
+
+    #[test]
+    fn test_tracker_reused_actor_id() {
+        let objects = objects();
+        let tables = ClassTables::default();
+        let mut tracker = ActorStateTracker::new(&objects, &tables).with_history();
+        tracker
+            .process_frame(&frame(vec![new_car(1)], vec![], vec![(1, 10)]))
+            .unwrap();
+        tracker
+            .process_frame(&frame(vec![], vec![ActorId(1)], vec![]))
+            .unwrap();
+        assert!(tracker.actor(ActorId(1)).is_none());
+        assert_eq!(tracker.actors().count(), 0);
+
+        tracker
+            .process_frame(&frame(vec![new_car(1)], vec![], vec![(1, 30)]))
+            .unwrap();
+
+        let throttle = "TAGame.Vehicle_TA:ReplicatedThrottle";
+        assert_eq!(tracker.all_actors().len(), 2);
+        assert_eq!(tracker.attribute_at(ActorId(1), throttle, 1), None);
+        assert_eq!(
+            tracker.attribute_at(ActorId(1), throttle, 0),
+            Some(&Attribute::Byte(10))
+        );
+        assert_eq!(
+            tracker.attribute_at(ActorId(1), throttle, 2),
+            Some(&Attribute::Byte(30))
+        );
+    }
+
+    #[test]
+    fn test_tracker_reannounced_actor() {
+        let objects = objects();
+        let tables = ClassTables::default();
+        let mut tracker = ActorStateTracker::new(&objects, &tables).with_history();
+        tracker
+            .process_frame(&frame(vec![new_car(1)], vec![], vec![(1, 10)]))
+            .unwrap();
+        tracker
+            .process_frame(&frame(vec![new_car(1)], vec![], vec![]))
+            .unwrap();
+
+        let throttle = "TAGame.Vehicle_TA:ReplicatedThrottle";
+        assert_eq!(tracker.all_actors().len(), 1);
+        assert_eq!(tracker.actor(ActorId(1)).unwrap().created_frame, 0);
+        assert_eq!(
+            tracker.attribute_at(ActorId(1), throttle, 1),
+            Some(&Attribute::Byte(10))
+        );
+    }
+
+    #[test]
+    fn test_tracker_declared_object_class() {
+        let mut objects = objects();
+        objects[0] = String::from("Archetypes.Car.Car_New");
+        let tables =
+            ClassTables::new().with_object_class("Archetypes.Car.Car_New", "TAGame.Car_TA");
+        let mut tracker = ActorStateTracker::new(&objects, &tables);
+        tracker
+            .process_frame(&frame(vec![new_car(1)], vec![], vec![]))
+            .unwrap();
+        assert_eq!(tracker.actors_of_class("TAGame.Car_TA").count(), 1);
+
+        let tables = ClassTables::default();
+        let mut tracker = ActorStateTracker::new(&objects, &tables);
+        tracker
+            .process_frame(&frame(vec![new_car(1)], vec![], vec![]))
+            .unwrap();
+        assert_eq!(tracker.actor(ActorId(1)).unwrap().class_name, None);
+    }
+
+    #[test]
+    fn test_tracker_missing_actor() {
+        let objects = objects();
+        let tables = ClassTables::default();
+        let mut tracker = ActorStateTracker::new(&objects, &tables);
+        let err = tracker
+            .process_frame(&frame(vec![], vec![], vec![(1, 10)]))
+            .unwrap_err();
+        assert_eq!(err, FrameError::MissingActor { actor: ActorId(1) });
+    }
+}
@@ -1,13 +1,16 @@
+use crate::bit_writer::BitWriter;
 use crate::errors::AttributeError;
 use crate::network::{ActorId, ObjectId, Quaternion, Rotation, Vector3f, VersionTriplet};
-use crate::parsing_utils::{decode_utf16, decode_windows1252};
+use crate::parsing_utils::{decode_utf16, decode_windows1252, encode_text_bytes};
 use bitter::BitGet;
 use encoding_rs::WINDOWS_1252;
 use std::borrow::Cow;
 use std::collections::HashMap;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub(crate) enum AttributeTag {
+/// Describes how an attribute is decoded from the network data. Each attribute's object name is
+/// mapped to a tag, see [`ClassTables`](crate::ClassTables) for supplying additional mappings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AttributeTag {
     Boolean,
     Byte,
     AppliedDamage,
@@ -49,12 +52,57 @@ pub(crate) enum AttributeTag {
     RepStatTitle,
 }
 
+impl AttributeTag {
+    /// Every tag in the order they are declared
+    pub const ALL: [AttributeTag; 39] = [
+        AttributeTag::Boolean,
+        AttributeTag::Byte,
+        AttributeTag::AppliedDamage,
+        AttributeTag::DamageState,
+        AttributeTag::CamSettings,
+        AttributeTag::ClubColors,
+        AttributeTag::Demolish,
+        AttributeTag::Enum,
+        AttributeTag::Explosion,
+        AttributeTag::ExtendedExplosion,
+        AttributeTag::FlaggedByte,
+        AttributeTag::ActiveActor,
+        AttributeTag::Float,
+        AttributeTag::GameMode,
+        AttributeTag::Int,
+        AttributeTag::Int64,
+        AttributeTag::Loadout,
+        AttributeTag::TeamLoadout,
+        AttributeTag::Location,
+        AttributeTag::MusicStinger,
+        AttributeTag::Pickup,
+        AttributeTag::PickupNew,
+        AttributeTag::PlayerHistoryKey,
+        AttributeTag::QWord,
+        AttributeTag::Welded,
+        AttributeTag::RigidBody,
+        AttributeTag::Title,
+        AttributeTag::TeamPaint,
+        AttributeTag::NotImplemented,
+        AttributeTag::String,
+        AttributeTag::UniqueId,
+        AttributeTag::Reservation,
+        AttributeTag::PartyLeader,
+        AttributeTag::PrivateMatchSettings,
+        AttributeTag::LoadoutOnline,
+        AttributeTag::LoadoutsOnline,
+        AttributeTag::StatEvent,
+        AttributeTag::RotationTag,
+        AttributeTag::RepStatTitle,
+    ];
+}
+
 /// The attributes for updated actors in the network data.
 ///
 /// The vast majority of attributes in the network data are rigid bodies. As a performance
 /// improvent, any attribute variant larger than the size of a rigid body is moved to the heap (ie:
 /// `Box::new`). This change increased throughput by 40%.
-#[derive(Debug, Clone, PartialEq, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Attribute {
     Boolean(bool),
     Byte(u8),
@@ -72,7 +120,10 @@ pub enum Attribute {
     GameMode(u8, u8),
     Int(i32),
 
-    #[serde(serialize_with = "crate::serde_utils::display_it")]
+    #[serde(
+        serialize_with = "crate::serde_utils::display_it",
+        deserialize_with = "crate::serde_utils::parse_it"
+    )]
     Int64(i64),
     Loadout(Box<Loadout>),
     TeamLoadout(Box<TeamLoadout>),
@@ -82,7 +133,10 @@ pub enum Attribute {
     Pickup(Pickup),
     PickupNew(PickupNew),
 
-    #[serde(serialize_with = "crate::serde_utils::display_it")]
+    #[serde(
+        serialize_with = "crate::serde_utils::display_it",
+        deserialize_with = "crate::serde_utils::parse_it"
+    )]
     QWord(u64),
     Welded(Welded),
     Title(bool, bool, u32, u32, u32, u32, u32, bool),
@@ -100,13 +154,59 @@ pub enum Attribute {
     RepStatTitle(RepStatTitle),
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+impl Attribute {
+    /// The tag that an attribute of this variant is decoded from
+    pub(crate) fn tag(&self) -> AttributeTag {
+        match self {
+            Attribute::Boolean(_) => AttributeTag::Boolean,
+            Attribute::Byte(_) => AttributeTag::Byte,
+            Attribute::AppliedDamage(..) => AttributeTag::AppliedDamage,
+            Attribute::DamageState(_) => AttributeTag::DamageState,
+            Attribute::CamSettings(_) => AttributeTag::CamSettings,
+            Attribute::ClubColors(_) => AttributeTag::ClubColors,
+            Attribute::Demolish(_) => AttributeTag::Demolish,
+            Attribute::Enum(_) => AttributeTag::Enum,
+            Attribute::Explosion(_) => AttributeTag::Explosion,
+            Attribute::ExtendedExplosion(_) => AttributeTag::ExtendedExplosion,
+            Attribute::FlaggedByte(..) => AttributeTag::FlaggedByte,
+            Attribute::ActiveActor(_) => AttributeTag::ActiveActor,
+            Attribute::Float(_) => AttributeTag::Float,
+            Attribute::GameMode(..) => AttributeTag::GameMode,
+            Attribute::Int(_) => AttributeTag::Int,
+            Attribute::Int64(_) => AttributeTag::Int64,
+            Attribute::Loadout(_) => AttributeTag::Loadout,
+            Attribute::TeamLoadout(_) => AttributeTag::TeamLoadout,
+            Attribute::Location(_) => AttributeTag::Location,
+            Attribute::MusicStinger(_) => AttributeTag::MusicStinger,
+            Attribute::PlayerHistoryKey(_) => AttributeTag::PlayerHistoryKey,
+            Attribute::Pickup(_) => AttributeTag::Pickup,
+            Attribute::PickupNew(_) => AttributeTag::PickupNew,
+            Attribute::QWord(_) => AttributeTag::QWord,
+            Attribute::Welded(_) => AttributeTag::Welded,
+            Attribute::Title(..) => AttributeTag::Title,
+            Attribute::TeamPaint(_) => AttributeTag::TeamPaint,
+            Attribute::RigidBody(_) => AttributeTag::RigidBody,
+            Attribute::String(_) => AttributeTag::String,
+            Attribute::UniqueId(_) => AttributeTag::UniqueId,
+            Attribute::Reservation(_) => AttributeTag::Reservation,
+            Attribute::PartyLeader(_) => AttributeTag::PartyLeader,
+            Attribute::PrivateMatch(_) => AttributeTag::PrivateMatchSettings,
+            Attribute::LoadoutOnline(_) => AttributeTag::LoadoutOnline,
+            Attribute::LoadoutsOnline(_) => AttributeTag::LoadoutsOnline,
+            Attribute::StatEvent(_) => AttributeTag::StatEvent,
+            Attribute::Rotation(_) => AttributeTag::RotationTag,
+            Attribute::RepStatTitle(_) => AttributeTag::RepStatTitle,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ActiveActor {
     pub active: bool,
     pub actor: ActorId,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub struct CamSettings {
     pub fov: f32,
     pub height: f32,
@@ -117,7 +217,7 @@ pub struct CamSettings {
     pub transition: Option<f32>,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ClubColors {
     pub blue_flag: bool,
     pub blue_color: u8,
@@ -125,7 +225,7 @@ pub struct ClubColors {
     pub orange_color: u8,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub struct DamageState {
     /// State of the dropshot tile (0 - undamaged, 1 - damaged, 2 - destroyed)
     pub tile_state: u8,
@@ -144,7 +244,7 @@ pub struct DamageState {
     pub unknown1: bool,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub struct Demolish {
     pub attacker_flag: bool,
     pub attacker: ActorId,
@@ -154,21 +254,21 @@ pub struct Demolish {
     pub victim_velocity: Vector3f,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub struct Explosion {
     pub flag: bool,
     pub actor: ActorId,
     pub location: Vector3f,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub struct ExtendedExplosion {
     pub explosion: Explosion,
     pub unknown1: bool,
     pub secondary_actor: ActorId,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Loadout {
     pub version: u8,
     pub body: u32,
@@ -186,38 +286,38 @@ pub struct Loadout {
     pub unknown3: Option<u32>,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub struct TeamLoadout {
     pub blue: Loadout,
     pub orange: Loadout,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub struct StatEvent {
     pub unknown1: bool,
     pub object_id: i32,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub struct MusicStinger {
     pub flag: bool,
     pub cue: u32,
     pub trigger: u8,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Pickup {
     pub instigator: Option<ActorId>,
     pub picked_up: bool,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub struct PickupNew {
     pub instigator: Option<ActorId>,
     pub picked_up: u8,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub struct Welded {
     pub active: bool,
     pub actor: ActorId,
@@ -226,7 +326,7 @@ pub struct Welded {
     pub rotation: Rotation,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub struct TeamPaint {
     pub team: u8,
     pub primary_color: u8,
@@ -235,7 +335,7 @@ pub struct TeamPaint {
     pub accent_finish: u32,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub struct RigidBody {
     pub sleeping: bool,
     pub location: Vector3f,
@@ -244,53 +344,71 @@ pub struct RigidBody {
     pub angular_velocity: Option<Vector3f>,
 }
 
-#[derive(Debug, Clone, PartialEq, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct UniqueId {
     pub system_id: u8,
     pub remote_id: RemoteId,
     pub local_id: u8,
 }
 
-#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
 pub struct PsyNetId {
-    #[serde(serialize_with = "crate::serde_utils::display_it")]
+    #[serde(
+        serialize_with = "crate::serde_utils::display_it",
+        deserialize_with = "crate::serde_utils::parse_it"
+    )]
     pub online_id: u64,
     pub unknown1: Vec<u8>,
 }
 
-#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
 pub struct SwitchId {
-    #[serde(serialize_with = "crate::serde_utils::display_it")]
+    #[serde(
+        serialize_with = "crate::serde_utils::display_it",
+        deserialize_with = "crate::serde_utils::parse_it"
+    )]
     pub online_id: u64,
     pub unknown1: Vec<u8>,
 }
 
-#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Ps4Id {
-    #[serde(serialize_with = "crate::serde_utils::display_it")]
+    #[serde(
+        serialize_with = "crate::serde_utils::display_it",
+        deserialize_with = "crate::serde_utils::parse_it"
+    )]
     pub online_id: u64,
     pub name: String,
     pub unknown1: Vec<u8>,
 }
 
-#[derive(Debug, Clone, PartialEq, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum RemoteId {
     PlayStation(Ps4Id),
     PsyNet(PsyNetId),
     SplitScreen(u32),
 
-    #[serde(serialize_with = "crate::serde_utils::display_it")]
+    #[serde(
+        serialize_with = "crate::serde_utils::display_it",
+        deserialize_with = "crate::serde_utils::parse_it"
+    )]
     Steam(u64),
     Switch(SwitchId),
 
-    #[serde(serialize_with = "crate::serde_utils::display_it")]
+    #[serde(
+        serialize_with = "crate::serde_utils::display_it",
+        deserialize_with = "crate::serde_utils::parse_it"
+    )]
     Xbox(u64),
 
-    #[serde(serialize_with = "crate::serde_utils::display_it")]
+    #[serde(
+        serialize_with = "crate::serde_utils::display_it",
+        deserialize_with = "crate::serde_utils::parse_it"
+    )]
     QQ(u64),
 }
 
-#[derive(Debug, Clone, PartialEq, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Reservation {
     pub number: u32,
     pub unique_id: UniqueId,
@@ -300,7 +418,7 @@ pub struct Reservation {
     pub unknown3: Option<u8>,
 }
 
-#[derive(Debug, Clone, PartialEq, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct PrivateMatchSettings {
     pub mutators: String,
     pub joinable_by: u32,
@@ -310,14 +428,14 @@ pub struct PrivateMatchSettings {
     pub flag: bool,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Product {
     pub unknown: bool,
     pub object_ind: u32,
     pub value: ProductValue,
 }
 
-#[derive(Debug, Clone, PartialEq, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct LoadoutsOnline {
     pub blue: Vec<Vec<Product>>,
     pub orange: Vec<Vec<Product>>,
@@ -325,7 +443,7 @@ pub struct LoadoutsOnline {
     pub unknown2: bool,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ProductValue {
     NoColor,
     Absent,
@@ -339,7 +457,7 @@ pub enum ProductValue {
     NewTeamEdition(u32),
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct RepStatTitle {
     pub unknown: bool,
     pub name: String,
@@ -421,6 +539,29 @@ impl ProductValueDecoder {
     }
 }
 
+impl ProductValue {
+    /// The inverse of `ProductValueDecoder::decode`. The kind of product value is assumed to
+    /// agree with the product's object index
+    pub(crate) fn encode(&self, bits: &mut BitWriter) {
+        match self {
+            ProductValue::NoColor => bits.write_bit(false),
+            ProductValue::Absent => {}
+            ProductValue::OldColor(x) => {
+                bits.write_bit(true);
+                bits.write_u32_bits(*x, 31);
+            }
+            ProductValue::NewColor(x) => bits.write_i32(*x),
+            ProductValue::OldPaint(x) | ProductValue::OldTeamEdition(x) => {
+                bits.write_bits_max(14, *x)
+            }
+            ProductValue::NewPaint(x)
+            | ProductValue::SpecialEdition(x)
+            | ProductValue::NewTeamEdition(x) => bits.write_u32_bits(*x, 31),
+            ProductValue::Title(x) => encode_text(bits, x),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub(crate) struct AttributeDecoder {
     version: VersionTriplet,
@@ -1096,6 +1237,344 @@ impl AttributeDecoder {
     }
 }
 
+/// The inverse of `AttributeDecoder`: each `encode_*` method writes the bits that the
+/// corresponding `decode_*` method reads.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct AttributeEncoder {
+    version: VersionTriplet,
+}
+
+impl AttributeEncoder {
+    pub fn new(version: VersionTriplet) -> Self {
+        AttributeEncoder { version }
+    }
+
+    pub fn encode(&self, attr: &Attribute, bits: &mut BitWriter) {
+        match attr {
+            Attribute::Boolean(x) => self.encode_boolean(*x, bits),
+            Attribute::Byte(x) => self.encode_byte(*x, bits),
+            Attribute::AppliedDamage(a, vector, b, c) => {
+                self.encode_applied_damage(*a, vector, *b, *c, bits)
+            }
+            Attribute::DamageState(x) => self.encode_damage_state(x, bits),
+            Attribute::CamSettings(x) => self.encode_cam_settings(x, bits),
+            Attribute::ClubColors(x) => self.encode_club_colors(x, bits),
+            Attribute::Demolish(x) => self.encode_demolish(x, bits),
+            Attribute::Enum(x) => self.encode_enum(*x, bits),
+            Attribute::Explosion(x) => self.encode_explosion(x, bits),
+            Attribute::ExtendedExplosion(x) => self.encode_extended_explosion(x, bits),
+            Attribute::FlaggedByte(b, x) => self.encode_flagged_byte(*b, *x, bits),
+            Attribute::ActiveActor(x) => self.encode_active_actor(x, bits),
+            Attribute::Float(x) => self.encode_float(*x, bits),
+            Attribute::GameMode(_, x) => self.encode_game_mode(*x, bits),
+            Attribute::Int(x) => self.encode_int(*x, bits),
+            Attribute::Int64(x) => self.encode_int64(*x, bits),
+            Attribute::Loadout(x) => self.encode_loadout(x, bits),
+            Attribute::TeamLoadout(x) => self.encode_team_loadout(x, bits),
+            Attribute::Location(x) => self.encode_location(x, bits),
+            Attribute::MusicStinger(x) => self.encode_music_stinger(x, bits),
+            Attribute::PlayerHistoryKey(x) => self.encode_player_history_key(*x, bits),
+            Attribute::Pickup(x) => self.encode_pickup(x, bits),
+            Attribute::PickupNew(x) => self.encode_pickup_new(x, bits),
+            Attribute::QWord(x) => self.encode_qword(*x, bits),
+            Attribute::Welded(x) => self.encode_welded(x, bits),
+            Attribute::Title(a, b, c, d, e, f, g, h) => {
+                self.encode_title((*a, *b, *c, *d, *e, *f, *g, *h), bits)
+            }
+            Attribute::TeamPaint(x) => self.encode_team_paint(x, bits),
+            Attribute::RigidBody(x) => self.encode_rigid_body(x, bits),
+            Attribute::String(x) => self.encode_string(x, bits),
+            Attribute::UniqueId(x) => self.encode_unique_id(x, bits),
+            Attribute::Reservation(x) => self.encode_reservation(x, bits),
+            Attribute::PartyLeader(x) => self.encode_party_leader(x.as_deref(), bits),
+            Attribute::PrivateMatch(x) => self.encode_private_match_settings(x, bits),
+            Attribute::LoadoutOnline(x) => self.encode_loadout_online(x, bits),
+            Attribute::LoadoutsOnline(x) => self.encode_loadouts_online(x, bits),
+            Attribute::StatEvent(x) => self.encode_stat_event(x, bits),
+            Attribute::Rotation(x) => self.encode_rotation(x, bits),
+            Attribute::RepStatTitle(x) => self.encode_rep_stat_title(x, bits),
+        }
+    }
+
+    pub fn encode_byte(&self, x: u8, bits: &mut BitWriter) {
+        bits.write_u8(x);
+    }
+
+    pub fn encode_player_history_key(&self, x: u16, bits: &mut BitWriter) {
+        bits.write_u32_bits(u32::from(x), 14);
+    }
+
+    pub fn encode_flagged_byte(&self, b: bool, x: u8, bits: &mut BitWriter) {
+        bits.write_bit(b);
+        bits.write_u8(x);
+    }
+
+    pub fn encode_boolean(&self, x: bool, bits: &mut BitWriter) {
+        bits.write_bit(x);
+    }
+
+    pub fn encode_applied_damage(
+        &self,
+        a: u8,
+        vector: &Vector3f,
+        b: u32,
+        c: u32,
+        bits: &mut BitWriter,
+    ) {
+        bits.write_u8(a);
+        vector.encode(bits, self.version.net_version());
+        bits.write_u32(b);
+        bits.write_u32(c);
+    }
+
+    pub fn encode_damage_state(&self, x: &DamageState, bits: &mut BitWriter) {
+        bits.write_u8(x.tile_state);
+        bits.write_bit(x.damaged);
+        bits.write_i32(x.offender.0);
+        x.ball_position.encode(bits, self.version.net_version());
+        bits.write_bit(x.direct_hit);
+        bits.write_bit(x.unknown1);
+    }
+
+    pub fn encode_cam_settings(&self, x: &CamSettings, bits: &mut BitWriter) {
+        bits.write_f32(x.fov);
+        bits.write_f32(x.height);
+        bits.write_f32(x.angle);
+        bits.write_f32(x.distance);
+        bits.write_f32(x.stiffness);
+        bits.write_f32(x.swivel);
+        if self.version >= VersionTriplet(868, 20, 0) {
+            bits.write_f32(x.transition.unwrap_or(0.0));
+        }
+    }
+
+    pub fn encode_club_colors(&self, x: &ClubColors, bits: &mut BitWriter) {
+        bits.write_bit(x.blue_flag);
+        bits.write_u8(x.blue_color);
+        bits.write_bit(x.orange_flag);
+        bits.write_u8(x.orange_color);
+    }
+
+    pub fn encode_demolish(&self, x: &Demolish, bits: &mut BitWriter) {
+        bits.write_bit(x.attacker_flag);
+        bits.write_i32(x.attacker.0);
+        bits.write_bit(x.victim_flag);
+        bits.write_i32(x.victim.0);
+        x.attack_velocity.encode(bits, self.version.net_version());
+        x.victim_velocity.encode(bits, self.version.net_version());
+    }
+
+    pub fn encode_enum(&self, x: u16, bits: &mut BitWriter) {
+        bits.write_u32_bits(u32::from(x), 11);
+    }
+
+    pub fn encode_explosion(&self, x: &Explosion, bits: &mut BitWriter) {
+        encode_explosion(x, bits, self.version.net_version());
+    }
+
+    pub fn encode_stat_event(&self, x: &StatEvent, bits: &mut BitWriter) {
+        bits.write_bit(x.unknown1);
+        bits.write_i32(x.object_id);
+    }
+
+    pub fn encode_rep_stat_title(&self, x: &RepStatTitle, bits: &mut BitWriter) {
+        bits.write_bit(x.unknown);
+        encode_text(bits, &x.name);
+        bits.write_bit(x.unknown2);
+        bits.write_u32(x.index);
+        bits.write_u32(x.value);
+    }
+
+    pub fn encode_extended_explosion(&self, x: &ExtendedExplosion, bits: &mut BitWriter) {
+        encode_explosion(&x.explosion, bits, self.version.net_version());
+        bits.write_bit(x.unknown1);
+        bits.write_i32(x.secondary_actor.0);
+    }
+
+    pub fn encode_active_actor(&self, x: &ActiveActor, bits: &mut BitWriter) {
+        bits.write_bit(x.active);
+        bits.write_i32(x.actor.0);
+    }
+
+    pub fn encode_float(&self, x: f32, bits: &mut BitWriter) {
+        bits.write_f32(x);
+    }
+
+    pub fn encode_game_mode(&self, x: u8, bits: &mut BitWriter) {
+        let init = if self.version < VersionTriplet(868, 12, 0) {
+            2
+        } else {
+            8
+        };
+
+        bits.write_u32_bits(u32::from(x), init);
+    }
+
+    pub fn encode_int(&self, x: i32, bits: &mut BitWriter) {
+        bits.write_i32(x);
+    }
+
+    pub fn encode_int64(&self, x: i64, bits: &mut BitWriter) {
+        bits.write_i64(x);
+    }
+
+    pub fn encode_loadout(&self, x: &Loadout, bits: &mut BitWriter) {
+        encode_loadout(x, bits);
+    }
+
+    pub fn encode_team_loadout(&self, x: &TeamLoadout, bits: &mut BitWriter) {
+        encode_loadout(&x.blue, bits);
+        encode_loadout(&x.orange, bits);
+    }
+
+    pub fn encode_location(&self, x: &Vector3f, bits: &mut BitWriter) {
+        x.encode(bits, self.version.net_version());
+    }
+
+    pub fn encode_music_stinger(&self, x: &MusicStinger, bits: &mut BitWriter) {
+        bits.write_bit(x.flag);
+        bits.write_u32(x.cue);
+        bits.write_u8(x.trigger);
+    }
+
+    pub fn encode_pickup(&self, x: &Pickup, bits: &mut BitWriter) {
+        bits.if_write(x.instigator, |b, actor| b.write_i32(actor.0));
+        bits.write_bit(x.picked_up);
+    }
+
+    pub fn encode_pickup_new(&self, x: &PickupNew, bits: &mut BitWriter) {
+        bits.if_write(x.instigator, |b, actor| b.write_i32(actor.0));
+        bits.write_u8(x.picked_up);
+    }
+
+    pub fn encode_qword(&self, x: u64, bits: &mut BitWriter) {
+        bits.write_u64(x);
+    }
+
+    pub fn encode_welded(&self, x: &Welded, bits: &mut BitWriter) {
+        bits.write_bit(x.active);
+        bits.write_i32(x.actor.0);
+        x.offset.encode(bits, self.version.net_version());
+        bits.write_f32(x.mass);
+        x.rotation.encode(bits);
+    }
+
+    pub fn encode_rotation(&self, x: &Rotation, bits: &mut BitWriter) {
+        x.encode(bits);
+    }
+
+    #[allow(clippy::type_complexity)]
+    pub fn encode_title(
+        &self,
+        x: (bool, bool, u32, u32, u32, u32, u32, bool),
+        bits: &mut BitWriter,
+    ) {
+        bits.write_bit(x.0);
+        bits.write_bit(x.1);
+        bits.write_u32(x.2);
+        bits.write_u32(x.3);
+        bits.write_u32(x.4);
+        bits.write_u32(x.5);
+        bits.write_u32(x.6);
+        bits.write_bit(x.7);
+    }
+
+    pub fn encode_team_paint(&self, x: &TeamPaint, bits: &mut BitWriter) {
+        bits.write_u8(x.team);
+        bits.write_u8(x.primary_color);
+        bits.write_u8(x.accent_color);
+        bits.write_u32(x.primary_finish);
+        bits.write_u32(x.accent_finish);
+    }
+
+    pub fn encode_rigid_body(&self, x: &RigidBody, bits: &mut BitWriter) {
+        let net_version = self.version.net_version();
+        bits.write_bit(x.sleeping);
+        x.location.encode(bits, net_version);
+
+        if net_version >= 7 {
+            x.rotation.encode(bits);
+        } else {
+            x.rotation.encode_compressed(bits);
+        }
+
+        if !x.sleeping {
+            let zero = Vector3f {
+                x: 0.0,
+                y: 0.0,
+                z: 0.0,
+            };
+            x.linear_velocity.unwrap_or(zero).encode(bits, net_version);
+            x.angular_velocity.unwrap_or(zero).encode(bits, net_version);
+        }
+    }
+
+    pub fn encode_string(&self, x: &str, bits: &mut BitWriter) {
+        encode_text(bits, x);
+    }
+
+    pub fn encode_unique_id(&self, x: &UniqueId, bits: &mut BitWriter) {
+        encode_unique_id(x, bits, self.version.net_version());
+    }
+
+    pub fn encode_reservation(&self, x: &Reservation, bits: &mut BitWriter) {
+        bits.write_u32_bits(x.number, 3);
+        encode_unique_id(&x.unique_id, bits, self.version.net_version());
+        if x.unique_id.system_id != 0 {
+            encode_text(bits, x.name.as_deref().unwrap_or(""));
+        }
+
+        bits.write_bit(x.unknown1);
+        bits.write_bit(x.unknown2);
+        if self.version >= VersionTriplet(868, 12, 0) {
+            bits.write_u32_bits(u32::from(x.unknown3.unwrap_or(0)), 6);
+        }
+    }
+
+    pub fn encode_party_leader(&self, x: Option<&UniqueId>, bits: &mut BitWriter) {
+        match x {
+            Some(id) if id.system_id != 0 => encode_unique_id(id, bits, self.version.net_version()),
+            _ => bits.write_u8(0),
+        }
+    }
+
+    pub fn encode_private_match_settings(&self, x: &PrivateMatchSettings, bits: &mut BitWriter) {
+        encode_text(bits, &x.mutators);
+        bits.write_u32(x.joinable_by);
+        bits.write_u32(x.max_players);
+        encode_text(bits, &x.game_name);
+        encode_text(bits, &x.password);
+        bits.write_bit(x.flag);
+    }
+
+    pub fn encode_loadout_online(&self, x: &[Vec<Product>], bits: &mut BitWriter) {
+        self.inner_encode_online_loadout(x, bits);
+    }
+
+    pub fn encode_loadouts_online(&self, x: &LoadoutsOnline, bits: &mut BitWriter) {
+        self.inner_encode_online_loadout(&x.blue, bits);
+        self.inner_encode_online_loadout(&x.orange, bits);
+        bits.write_bit(x.unknown1);
+        bits.write_bit(x.unknown2);
+    }
+
+    fn encode_product(&self, x: &Product, bits: &mut BitWriter) {
+        bits.write_bit(x.unknown);
+        bits.write_u32(x.object_ind);
+        x.value.encode(bits);
+    }
+
+    fn inner_encode_online_loadout(&self, x: &[Vec<Product>], bits: &mut BitWriter) {
+        bits.write_u8(x.len() as u8);
+        for products in x {
+            bits.write_u8(products.len() as u8);
+            for product in products {
+                self.encode_product(product, bits);
+            }
+        }
+    }
+}
+
 fn decode_explosion(bits: &mut BitGet<'_>, net_version: i32) -> Option<Explosion> {
     if_chain! {
         if let Some(flag) = bits.read_bit();
@@ -1327,6 +1806,95 @@ fn decode_unique_id_with_system_id(
     })
 }
 
+fn encode_explosion(x: &Explosion, bits: &mut BitWriter, net_version: i32) {
+    bits.write_bit(x.flag);
+    bits.write_i32(x.actor.0);
+    x.location.encode(bits, net_version);
+}
+
+/// The inverse of `decode_text`. An empty string is written with a length of zero.
+fn encode_text(bits: &mut BitWriter, text: &str) {
+    if text.is_empty() {
+        bits.write_i32(0);
+    } else {
+        let (size, data) = encode_text_bytes(text);
+        bits.write_i32(size);
+        bits.write_bytes(&data);
+    }
+}
+
+/// The inverse of `decode_loadout`. The loadout version determines the fields written, and fields
+/// that aren't retained when decoding are written as zeros.
+fn encode_loadout(x: &Loadout, bits: &mut BitWriter) {
+    bits.write_u8(x.version);
+    bits.write_u32(x.body);
+    bits.write_u32(x.decal);
+    bits.write_u32(x.wheels);
+    bits.write_u32(x.rocket_trail);
+    bits.write_u32(x.antenna);
+    bits.write_u32(x.topper);
+    bits.write_u32(x.unknown1);
+
+    if x.version > 10 {
+        bits.write_u32(x.unknown2.unwrap_or(0));
+    }
+
+    if x.version >= 16 {
+        bits.write_u32(x.engine_audio.unwrap_or(0));
+        bits.write_u32(x.trail.unwrap_or(0));
+        bits.write_u32(x.goal_explosion.unwrap_or(0));
+    }
+
+    if x.version >= 17 {
+        bits.write_u32(x.banner.unwrap_or(0));
+    }
+
+    if x.version >= 19 {
+        bits.write_u32(x.unknown3.unwrap_or(0));
+    }
+
+    if x.version >= 22 {
+        bits.write_u32(0);
+        bits.write_u32(0);
+        bits.write_u32(0);
+    }
+}
+
+/// The inverse of `decode_unique_id`. Unknown byte sequences are truncated or zero padded to the
+/// expected length.
+fn encode_unique_id(x: &UniqueId, bits: &mut BitWriter, net_version: i32) {
+    let write_padded = |bits: &mut BitWriter, data: &[u8], len: usize| {
+        let mut data = data.to_vec();
+        data.resize(len, 0);
+        bits.write_bytes(&data);
+    };
+
+    bits.write_u8(x.system_id);
+    match &x.remote_id {
+        RemoteId::SplitScreen(id) => bits.write_u32_bits(*id, 24),
+        RemoteId::Steam(id) | RemoteId::Xbox(id) | RemoteId::QQ(id) => bits.write_u64(*id),
+        RemoteId::PlayStation(ps4) => {
+            let (name, _, _) = WINDOWS_1252.encode(&ps4.name);
+            write_padded(bits, &name, 16);
+            let to_write = if net_version >= 1 { 16 } else { 8 };
+            write_padded(bits, &ps4.unknown1, to_write);
+            bits.write_u64(ps4.online_id);
+        }
+        RemoteId::Switch(switch) => {
+            bits.write_u64(switch.online_id);
+            write_padded(bits, &switch.unknown1, 24);
+        }
+        RemoteId::PsyNet(psynet) => {
+            bits.write_u64(psynet.online_id);
+            if net_version < 10 {
+                write_padded(bits, &psynet.unknown1, 24);
+            }
+        }
+    }
+
+    bits.write_u8(x.local_id);
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1340,4 +1908,53 @@ mod tests {
     fn test_size_of_attribute() {
         assert_eq!(::std::mem::size_of::<Attribute>(), ::std::mem::size_of::<RigidBody>() + 8);
     }
+
+    #[test]
+    fn test_attribute_tag_all() {
+        for (i, tag) in AttributeTag::ALL.iter().enumerate() {
+            // The match is exhaustive, so a new tag doesn't compile until it is listed here too
+            let declared = match tag {
+                AttributeTag::Boolean => 0,
+                AttributeTag::Byte => 1,
+                AttributeTag::AppliedDamage => 2,
+                AttributeTag::DamageState => 3,
+                AttributeTag::CamSettings => 4,
+                AttributeTag::ClubColors => 5,
+                AttributeTag::Demolish => 6,
+                AttributeTag::Enum => 7,
+                AttributeTag::Explosion => 8,
+                AttributeTag::ExtendedExplosion => 9,
+                AttributeTag::FlaggedByte => 10,
+                AttributeTag::ActiveActor => 11,
+                AttributeTag::Float => 12,
+                AttributeTag::GameMode => 13,
+                AttributeTag::Int => 14,
+                AttributeTag::Int64 => 15,
+                AttributeTag::Loadout => 16,
+                AttributeTag::TeamLoadout => 17,
+                AttributeTag::Location => 18,
+                AttributeTag::MusicStinger => 19,
+                AttributeTag::Pickup => 20,
+                AttributeTag::PickupNew => 21,
+                AttributeTag::PlayerHistoryKey => 22,
+                AttributeTag::QWord => 23,
+                AttributeTag::Welded => 24,
+                AttributeTag::RigidBody => 25,
+                AttributeTag::Title => 26,
+                AttributeTag::TeamPaint => 27,
+                AttributeTag::NotImplemented => 28,
+                AttributeTag::String => 29,
+                AttributeTag::UniqueId => 30,
+                AttributeTag::Reservation => 31,
+                AttributeTag::PartyLeader => 32,
+                AttributeTag::PrivateMatchSettings => 33,
+                AttributeTag::LoadoutOnline => 34,
+                AttributeTag::LoadoutsOnline => 35,
+                AttributeTag::StatEvent => 36,
+                AttributeTag::RotationTag => 37,
+                AttributeTag::RepStatTitle => 38,
+            };
+            assert_eq!(declared, i);
+        }
+    }
 }
@@ -0,0 +1,403 @@
+use crate::errors::AnalysisError;
+use crate::models::Replay;
+use crate::network::attributes::{Attribute, Pickup, PickupNew};
+use crate::network::models::{ActorId, Vector3f, Vector3i};
+use crate::network::players::PlayerResolver;
+use crate::network::touches::{rigid_body, unreal_units};
+use crate::network::ClassTables;
+use fnv::FnvHashMap;
+use std::collections::HashMap;
+
+const PAD_CLASS: &str = "TAGame.VehiclePickup_Boost_TA";
+pub(crate) const BOOST_AMOUNT: &str = "TAGame.CarComponent_Boost_TA:ReplicatedBoostAmount";
+
+/// The replicated boost amount ranges from 0 to 255
+const MAX_BOOST: u8 = 255;
+
+/// A pad that respawns after longer than this many seconds is big. Big pads respawn after 10
+/// seconds and small pads after 4 seconds, so the threshold is the midpoint between the two.
+const BIG_PAD_RESPAWN_THRESHOLD: f32 = 7.0;
+
+/// Big pad locations in unreal units. These only match the standard field layout, so on other
+/// layouts (eg: hoops) a pad that replicates its spawn location is considered small.
+const BIG_PADS: [(i32, i32); 6] = [
+    (-3584, 0),
+    (3584, 0),
+    (-3072, -4096),
+    (3072, -4096),
+    (-3072, 4096),
+    (3072, 4096),
+];
+
+/// How close (in unreal units) a pad must be to a big pad location to be considered big
+const BIG_PAD_RADIUS: i32 = 300;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum PadSize {
+    /// A pad that fills the boost to 100
+    Big,
+
+    /// A pad that gives 12 boost
+    Small,
+}
+
+/// A boost pad collected by a player
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BoostPickup {
+    /// The index of the frame
+    pub frame: usize,
+
+    /// The frame's time in seconds
+    pub time: f32,
+
+    /// The pad's object name (eg: `Stadium_p.TheWorld:PersistentLevel.VehiclePickup_Boost_TA_24`),
+    /// which, unlike the pad's actor id, is the same for the whole match
+    pub pad: String,
+
+    /// The size of the pad. `None` if the pad never replicated its location nor was seen
+    /// respawning.
+    pub size: Option<PadSize>,
+
+    /// The pad's spawn location if replicated, otherwise the location of the car as it collected
+    /// the pad. Either way in unreal units.
+    pub location: Option<Vector3f>,
+
+    /// True if the pad is on the opponent's half of the field
+    pub stolen: bool,
+}
+
+/// A player's boost amount (from 0 to 100) when it changed
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct BoostSample {
+    /// The index of the frame
+    pub frame: usize,
+
+    /// The frame's time in seconds
+    pub time: f32,
+    pub amount: f32,
+}
+
+/// A player's boost usage and pickups. Boost amounts are on a scale from 0 to 100.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BoostStats {
+    /// The player's PRI actor (see `Player::actor_id`)
+    pub player: ActorId,
+    pub name: Option<String>,
+
+    /// The team number (0 or 1) that the player is on
+    pub team: Option<i32>,
+    pub timeline: Vec<BoostSample>,
+
+    /// The total amount of boost used
+    pub consumed: f32,
+    pub pickups: Vec<BoostPickup>,
+    pub big_pads: usize,
+    pub small_pads: usize,
+    pub stolen_big_pads: usize,
+    pub stolen_small_pads: usize,
+
+    /// Seconds spent without any boost
+    pub time_empty: f32,
+
+    /// Seconds spent with a full tank
+    pub time_full: f32,
+}
+
+/// A pickup before the size of its pad is known
+struct PendingPickup {
+    player: usize,
+    frame: usize,
+    pad: usize,
+    location: Option<Vector3f>,
+    spawn: Option<Vector3i>,
+}
+
+/// A boost pad as identified by its object name
+#[derive(Default)]
+struct Pad {
+    /// The last pickup data, as keyframes (and pads that are destroyed and created again) replicate
+    /// the pickup again
+    state: Option<(Option<ActorId>, u8)>,
+
+    /// When the pad was last collected
+    collected: Option<f32>,
+    size: Option<PadSize>,
+}
+
+/// Computes boost metrics for each player from the network frames:
+///
+/// - The boost amount over time from the boost component's `ReplicatedBoostAmount`
+/// - The boost consumed, which is the sum of decreases in the boost amount
+/// - The big and small pads collected from the pickup data of `TAGame.VehiclePickup_TA` actors. A
+///   pad's size is determined by its spawn location (on the standard field layout) when it is
+///   replicated, otherwise by how long the pad takes to respawn.
+/// - The time spent at 0 and 100 boost
+/// - The pads stolen, which are those collected on the opponent's half. Team 0 defends the
+///   negative y half.
+///
+/// The tables are those the replay was parsed with, so that pads and cars are recognized.
+///
+/// ```
+/// use boxcars::{boost_stats, ClassTables, ParserBuilder};
+///
+/// let data = include_bytes!("../../assets/replays/good/rumble.replay");
+/// let replay = ParserBuilder::new(&data[..])
+///     .must_parse_network_data()
+///     .parse()
+///     .unwrap();
+///
+/// let stats = boost_stats(&replay, &ClassTables::default()).unwrap();
+/// let player = stats
+///     .iter()
+///     .find(|x| x.name.as_deref() == Some("comagoosie"))
+///     .unwrap();
+///
+/// assert!(player.consumed > 0.0);
+/// assert!(player.big_pads > 0);
+/// assert!(player.small_pads > 0);
+/// ```
+pub fn boost_stats(
+    replay: &Replay,
+    tables: &ClassTables,
+) -> Result<Vec<BoostStats>, AnalysisError> {
+    let frames = &replay
+        .network_frames
+        .as_ref()
+        .ok_or(AnalysisError::MissingNetworkFrames)?
+        .frames;
+
+    let mut resolver = PlayerResolver::new(&replay.objects, tables);
+    let (location_scale, _) = unreal_units(replay.net_version);
+    let mut stats: Vec<BoostStats> = Vec::new();
+    let mut pads: Vec<Pad> = Vec::new();
+    let mut pad_names: HashMap<&str, usize> = HashMap::new();
+    let mut pending: Vec<PendingPickup> = Vec::new();
+
+    // Boost components to their last amount
+    let mut amounts: FnvHashMap<ActorId, u8> = FnvHashMap::default();
+
+    for (frame_ind, frame) in frames.iter().enumerate() {
+        resolver
+            .process_frame(frame)
+            .map_err(|e| AnalysisError::FrameError(frame_ind, e))?;
+
+        for player in &resolver.players()[stats.len()..] {
+            stats.push(BoostStats {
+                player: player.actor_id,
+                name: None,
+                team: None,
+                timeline: Vec::new(),
+                consumed: 0.0,
+                pickups: Vec::new(),
+                big_pads: 0,
+                small_pads: 0,
+                stolen_big_pads: 0,
+                stolen_small_pads: 0,
+                time_empty: 0.0,
+                time_full: 0.0,
+            });
+        }
+
+        let tracker = resolver.tracker();
+        for actor_id in &frame.deleted_actors {
+            amounts.remove(actor_id);
+        }
+
+        for actor in &frame.new_actors {
+            if tracker
+                .actor(actor.actor_id)
+                .filter(|x| x.created_frame == frame_ind)
+                .is_some()
+            {
+                amounts.remove(&actor.actor_id);
+            }
+        }
+
+        for update in &frame.updated_actors {
+            let actor = match tracker.actor(update.actor_id) {
+                Some(actor) => actor,
+                None => continue,
+            };
+
+            let state = match update.attribute {
+                Attribute::Byte(amount)
+                    if tracker.object_name(update.object_id).ok() == Some(BOOST_AMOUNT) =>
+                {
+                    let previous = amounts.insert(update.actor_id, amount);
+                    if previous == Some(amount) {
+                        continue;
+                    }
+
+                    let player = match resolver.owner_index(update.actor_id) {
+                        Some(player) => player,
+                        None => continue,
+                    };
+
+                    let player = &mut stats[player];
+                    if let Some(previous) = previous.filter(|&x| x > amount) {
+                        player.consumed += scale(previous - amount);
+                    }
+
+                    player.timeline.push(BoostSample {
+                        frame: frame_ind,
+                        time: frame.time,
+                        amount: scale(amount),
+                    });
+                    continue;
+                }
+                Attribute::Pickup(Pickup {
+                    instigator,
+                    picked_up,
+                }) => (instigator, u8::from(picked_up)),
+                Attribute::PickupNew(PickupNew {
+                    instigator,
+                    picked_up,
+                }) => (instigator, picked_up),
+                _ => continue,
+            };
+
+            if !actor.is_a(PAD_CLASS) {
+                continue;
+            }
+
+            let pad_ind = *pad_names.entry(actor.object_name).or_insert_with(|| {
+                pads.push(Pad::default());
+                pads.len() - 1
+            });
+
+            let pad = &mut pads[pad_ind];
+            if pad.state.replace(state) == Some(state) {
+                continue;
+            }
+
+            let car = match state.0 {
+                Some(car) => car,
+                None => {
+                    // The pad has respawned
+                    if let Some(collected) = pad.collected.take() {
+                        pad.size = Some(if frame.time - collected > BIG_PAD_RESPAWN_THRESHOLD {
+                            PadSize::Big
+                        } else {
+                            PadSize::Small
+                        });
+                    }
+                    continue;
+                }
+            };
+
+            pad.collected = Some(frame.time);
+            if let Some(player) = resolver.owner_index(car) {
+                pending.push(PendingPickup {
+                    player,
+                    frame: frame_ind,
+                    pad: pad_ind,
+                    location: tracker
+                        .actor(car)
+                        .and_then(rigid_body)
+                        .map(|x| x.location.scale(location_scale)),
+                    spawn: actor.initial_trajectory.location,
+                });
+            }
+        }
+
+        for (player, stat) in resolver.players().iter().zip(stats.iter_mut()) {
+            let amount = player
+                .boosts
+                .last()
+                .filter(|x| x.end_frame.is_none())
+                .and_then(|x| amounts.get(&x.actor_id));
+
+            match amount {
+                Some(0) => stat.time_empty += frame.delta,
+                Some(&MAX_BOOST) => stat.time_full += frame.delta,
+                _ => {}
+            }
+        }
+    }
+
+    let pad_names: HashMap<usize, &str> = pad_names.into_iter().map(|(k, v)| (v, k)).collect();
+    for pickup in pending {
+        let team = resolver.players()[pickup.player].team;
+        let size = pickup.spawn.map(|x| pad_size(&x)).or(pads[pickup.pad].size);
+        let location = pickup
+            .spawn
+            .map(|x| Vector3f {
+                x: x.x as f32,
+                y: x.y as f32,
+                z: x.z as f32,
+            })
+            .or(pickup.location);
+
+        let stolen = match (team, location) {
+            (Some(0), Some(location)) => location.y > 0.0,
+            (Some(1), Some(location)) => location.y < 0.0,
+            _ => false,
+        };
+
+        let stat = &mut stats[pickup.player];
+        match (size, stolen) {
+            (Some(PadSize::Big), true) => stat.stolen_big_pads += 1,
+            (Some(PadSize::Small), true) => stat.stolen_small_pads += 1,
+            _ => {}
+        }
+        match size {
+            Some(PadSize::Big) => stat.big_pads += 1,
+            Some(PadSize::Small) => stat.small_pads += 1,
+            None => {}
+        }
+
+        stat.pickups.push(BoostPickup {
+            frame: pickup.frame,
+            time: frames[pickup.frame].time,
+            pad: String::from(pad_names[&pickup.pad]),
+            size,
+            location,
+            stolen,
+        });
+    }
+
+    for (player, stat) in resolver.players().iter().zip(stats.iter_mut()) {
+        stat.name = player.name.clone();
+        stat.team = player.team;
+    }
+
+    Ok(stats)
+}
+
+/// Converts a replicated boost amount to a scale of 0 to 100
+pub(crate) fn scale(amount: u8) -> f32 {
+    f32::from(amount) * 100.0 / f32::from(MAX_BOOST)
+}
+
+fn pad_size(location: &Vector3i) -> PadSize {
+    let is_big = BIG_PADS.iter().any(|&(x, y)| {
+        (location.x - x).abs() <= BIG_PAD_RADIUS && (location.y - y).abs() <= BIG_PAD_RADIUS
+    });
+
+    if is_big {
+        PadSize::Big
+    } else {
+        PadSize::Small
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pad_size() {
+        let pad = |x, y| pad_size(&Vector3i { x, y, z: 70 });
+        assert_eq!(pad(3584, 0), PadSize::Big);
+        assert_eq!(pad(-3070, 4100), PadSize::Big);
+        assert_eq!(pad(0, -4240), PadSize::Small);
+        assert_eq!(pad(-1792, -2300), PadSize::Small);
+    }
+
+    #[test]
+    fn test_scale() {
+        assert_eq!(scale(0), 0.0);
+        assert_eq!(scale(MAX_BOOST), 100.0);
+        assert!((scale(85) - 33.33).abs() < 0.01);
+    }
+}
@@ -0,0 +1,204 @@
+use crate::errors::AnalysisError;
+use crate::models::Replay;
+use crate::network::attributes::{Attribute, Demolish};
+use crate::network::models::{ActorId, Vector3f};
+use crate::network::players::{Player, PlayerResolver};
+use crate::network::touches::rigid_body;
+use crate::network::ClassTables;
+use fnv::FnvHashMap;
+
+/// A car destroyed by another car (or by the environment when there is no attacker)
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Demolition {
+    /// The index of the frame
+    pub frame: usize,
+
+    /// The frame's time in seconds
+    pub time: f32,
+
+    /// The attacking car. `None` if the car wasn't destroyed by another car.
+    pub attacker_car: Option<ActorId>,
+
+    /// The PRI actor of the attacking car's player (see `Player::actor_id`)
+    pub attacker: Option<ActorId>,
+    pub attacker_name: Option<String>,
+    pub victim_car: ActorId,
+
+    /// The PRI actor of the destroyed car's player (see `Player::actor_id`)
+    pub victim: Option<ActorId>,
+    pub victim_name: Option<String>,
+
+    /// Where the destroyed car was when it was demolished
+    pub location: Option<Vector3f>,
+    pub attacker_velocity: Option<Vector3f>,
+    pub victim_velocity: Vector3f,
+}
+
+/// Extracts the demolitions from the network frames. Cars replicate a `Demolish` attribute (eg:
+/// `TAGame.Car_TA:ReplicatedDemolish`) when they are destroyed, and the attacker and victim cars
+/// are resolved to their players through the cars' `Engine.Pawn:PlayerReplicationInfo` link.
+///
+/// Any attribute decoded as a `Demolish` is considered, so newer attributes of the same shape can
+/// be picked up by mapping them to `AttributeTag::Demolish` with `ClassTables::with_attribute`. The
+/// same tables should be given here, so that new car objects are recognized as well.
+///
+/// ```
+/// use boxcars::{demolitions, ClassTables, ParserBuilder};
+///
+/// let data = include_bytes!("../../assets/replays/good/rumble.replay");
+/// let replay = ParserBuilder::new(&data[..])
+///     .must_parse_network_data()
+///     .parse()
+///     .unwrap();
+///
+/// let demos = demolitions(&replay, &ClassTables::default()).unwrap();
+/// assert!(!demos.is_empty());
+/// assert!(demos.iter().all(|x| x.victim_name.is_some()));
+/// ```
+pub fn demolitions(
+    replay: &Replay,
+    tables: &ClassTables,
+) -> Result<Vec<Demolition>, AnalysisError> {
+    let frames = &replay
+        .network_frames
+        .as_ref()
+        .ok_or(AnalysisError::MissingNetworkFrames)?
+        .frames;
+
+    let mut resolver = PlayerResolver::new(&replay.objects, tables);
+    let mut result: Vec<Demolition> = Vec::new();
+
+    // Live cars to their last demolish, as keyframes replicate it again
+    let mut last_demolish: FnvHashMap<ActorId, Demolish> = FnvHashMap::default();
+
+    for (frame_ind, frame) in frames.iter().enumerate() {
+        resolver
+            .process_frame(frame)
+            .map_err(|e| AnalysisError::FrameError(frame_ind, e))?;
+
+        let tracker = resolver.tracker();
+        for actor_id in &frame.deleted_actors {
+            last_demolish.remove(actor_id);
+        }
+
+        for actor in &frame.new_actors {
+            if tracker
+                .actor(actor.actor_id)
+                .filter(|x| x.created_frame == frame_ind)
+                .is_some()
+            {
+                last_demolish.remove(&actor.actor_id);
+            }
+        }
+
+        for update in &frame.updated_actors {
+            let demolish = match update.attribute {
+                Attribute::Demolish(ref demolish) => **demolish,
+                _ => continue,
+            };
+
+            if last_demolish.insert(update.actor_id, demolish) == Some(demolish) {
+                continue;
+            }
+
+            let victim_car = if demolish.victim_flag {
+                demolish.victim
+            } else {
+                update.actor_id
+            };
+            let attacker_car = Some(demolish.attacker).filter(|_| demolish.attacker_flag);
+
+            let victim = car_owner(&resolver, victim_car, frame_ind);
+            let attacker = attacker_car.and_then(|car| car_owner(&resolver, car, frame_ind));
+            let location = tracker
+                .actor(victim_car)
+                .and_then(rigid_body)
+                .map(|x| x.location);
+
+            result.push(Demolition {
+                frame: frame_ind,
+                time: frame.time,
+                attacker_car,
+                attacker: attacker.map(|x| x.actor_id),
+                attacker_name: attacker.and_then(|x| x.name.clone()),
+                victim_car,
+                victim: victim.map(|x| x.actor_id),
+                victim_name: victim.and_then(|x| x.name.clone()),
+                location,
+                attacker_velocity: attacker_car.map(|_| demolish.attack_velocity),
+                victim_velocity: demolish.victim_velocity,
+            });
+        }
+    }
+
+    Ok(result)
+}
+
+/// The player that owns the car, including a car that was unlinked from its player in the frame of
+/// the demolition
+fn car_owner<'a>(
+    resolver: &'a PlayerResolver<'_>,
+    car: ActorId,
+    frame: usize,
+) -> Option<&'a Player> {
+    resolver
+        .owner(car)
+        .or_else(|| last_owner(resolver.players(), car, frame))
+}
+
+/// The player whose ownership of the car ended in the frame
+fn last_owner(players: &[Player], car: ActorId, frame: usize) -> Option<&Player> {
+    players
+        .iter()
+        .filter_map(|player| {
+            player
+                .cars
+                .iter()
+                .find(|x| x.actor_id == car && x.end_frame == Some(frame))
+                .map(|x| (player, x.start_frame))
+        })
+        .max_by_key(|&(_, start)| start)
+        .map(|(player, _)| player)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::players::OwnedActor;
+
+    fn player(actor: i32, cars: Vec<OwnedActor>) -> Player {
+        Player {
+            actor_id: ActorId(actor),
+            created_frame: 0,
+            name: None,
+            remote_id: None,
+            team: None,
+            cars,
+            boosts: Vec::new(),
+            dodges: Vec::new(),
+        }
+    }
+
+    fn car(actor: i32, start_frame: usize, end_frame: Option<usize>) -> OwnedActor {
+        OwnedActor {
+            actor_id: ActorId(actor),
+            start_frame,
+            end_frame,
+        }
+    }
+
+    #[test]
+    fn test_last_owner() {
+        let players = vec![
+            player(1, vec![car(10, 0, Some(50)), car(12, 60, None)]),
+            player(2, vec![car(11, 0, Some(50)), car(10, 55, Some(80))]),
+        ];
+
+        let owner = |car, frame| last_owner(&players, ActorId(car), frame).map(|x| x.actor_id);
+        assert_eq!(owner(10, 50), Some(ActorId(1)));
+        assert_eq!(owner(10, 80), Some(ActorId(2)));
+        assert_eq!(owner(11, 50), Some(ActorId(2)));
+        assert_eq!(owner(12, 60), None);
+        assert_eq!(owner(10, 60), None);
+    }
+}
@@ -0,0 +1,231 @@
+use crate::errors::{AnalysisError, ExportError};
+use crate::models::Replay;
+use crate::network::attributes::Attribute;
+use crate::network::boost::{scale, BOOST_AMOUNT};
+use crate::network::models::ActorId;
+use crate::network::players::PlayerResolver;
+use crate::network::touches::rigid_body;
+use crate::network::ClassTables;
+use std::io;
+
+const FRAME_COLUMNS: [&str; 16] = [
+    "frame",
+    "time",
+    "delta",
+    "actor_id",
+    "object_name",
+    "location_x",
+    "location_y",
+    "location_z",
+    "rotation_x",
+    "rotation_y",
+    "rotation_z",
+    "rotation_w",
+    "velocity_x",
+    "velocity_y",
+    "velocity_z",
+    "boost",
+];
+
+const PLAYER_STATS_COLUMNS: [&str; 10] = [
+    "name",
+    "platform",
+    "online_id",
+    "team",
+    "score",
+    "goals",
+    "assists",
+    "saves",
+    "shots",
+    "is_bot",
+];
+
+/// A row of the frames export
+#[derive(Serialize)]
+struct FrameRow<'a> {
+    frame: usize,
+    time: f32,
+    delta: f32,
+    actor_id: ActorId,
+    object_name: &'a str,
+    location_x: f32,
+    location_y: f32,
+    location_z: f32,
+    rotation_x: f32,
+    rotation_y: f32,
+    rotation_z: f32,
+    rotation_w: f32,
+    velocity_x: Option<f32>,
+    velocity_y: Option<f32>,
+    velocity_z: Option<f32>,
+    boost: Option<f32>,
+}
+
+/// Writes the network frames as CSV with one row per frame per tracked actor, which are the live
+/// actors with a rigid body (eg: the ball and cars). An actor's row repeats its last replicated
+/// rigid body until it is updated again. The columns are:
+///
+/// - `frame`, `time`, `delta`: the index of the frame, its time, and the time since the previous
+///   frame in seconds
+/// - `actor_id`, `object_name`: the actor and the name of its object in `Replay::objects` (eg:
+///   `Archetypes.Car.Car_Default`)
+/// - `location_x`, `location_y`, `location_z`: the rigid body's location as replicated
+/// - `rotation_x`, `rotation_y`, `rotation_z`, `rotation_w`: the rigid body's rotation quaternion
+/// - `velocity_x`, `velocity_y`, `velocity_z`: the linear velocity, empty while the actor is asleep
+/// - `boost`: the boost amount (from 0 to 100) of a car's player, empty for other actors
+///
+/// Rows are ordered by frame and then by actor id. Cars are linked to their players with the tables
+/// the replay was parsed with.
+///
+/// ```
+/// use boxcars::{write_frames_csv, ClassTables, ParserBuilder};
+///
+/// let data = include_bytes!("../../assets/replays/good/rumble.replay");
+/// let replay = ParserBuilder::new(&data[..])
+///     .must_parse_network_data()
+///     .parse()
+///     .unwrap();
+///
+/// let mut out = Vec::new();
+/// write_frames_csv(&replay, &ClassTables::default(), &mut out).unwrap();
+///
+/// let csv = String::from_utf8(out).unwrap();
+/// assert!(csv.starts_with("frame,time,delta,actor_id,object_name,location_x"));
+/// ```
+pub fn write_frames_csv<W: io::Write>(
+    replay: &Replay,
+    tables: &ClassTables,
+    writer: W,
+) -> Result<(), ExportError> {
+    let frames = &replay
+        .network_frames
+        .as_ref()
+        .ok_or(AnalysisError::MissingNetworkFrames)?
+        .frames;
+
+    let mut csv = writer_with_headers(writer, &FRAME_COLUMNS)?;
+    let mut resolver = PlayerResolver::new(&replay.objects, tables);
+    for (frame_ind, frame) in frames.iter().enumerate() {
+        resolver
+            .process_frame(frame)
+            .map_err(|e| AnalysisError::FrameError(frame_ind, e))?;
+
+        let tracker = resolver.tracker();
+        let mut actors: Vec<_> = tracker
+            .actors()
+            .filter_map(|actor| rigid_body(actor).map(|body| (actor, body)))
+            .collect();
+        actors.sort_by_key(|(actor, _)| actor.actor_id);
+
+        for (actor, body) in actors {
+            let boost = resolver
+                .owner(actor.actor_id)
+                .and_then(|player| player.boosts.last())
+                .filter(|x| x.end_frame.is_none())
+                .and_then(|x| tracker.actor(x.actor_id))
+                .and_then(|x| match x.attribute(BOOST_AMOUNT) {
+                    Some(Attribute::Byte(amount)) => Some(scale(*amount)),
+                    _ => None,
+                });
+
+            csv.serialize(FrameRow {
+                frame: frame_ind,
+                time: frame.time,
+                delta: frame.delta,
+                actor_id: actor.actor_id,
+                object_name: actor.object_name,
+                location_x: body.location.x,
+                location_y: body.location.y,
+                location_z: body.location.z,
+                rotation_x: body.rotation.x,
+                rotation_y: body.rotation.y,
+                rotation_z: body.rotation.z,
+                rotation_w: body.rotation.w,
+                velocity_x: body.linear_velocity.map(|x| x.x),
+                velocity_y: body.linear_velocity.map(|x| x.y),
+                velocity_z: body.linear_velocity.map(|x| x.z),
+                boost,
+            })?;
+        }
+    }
+
+    csv.flush().map_err(csv::Error::from)?;
+    Ok(())
+}
+
+/// Writes the header's `PlayerStats` as CSV with one row per player. The columns are the fields of
+/// `PlayerStats`, with empty values for a missing platform or online id.
+///
+/// ```
+/// use boxcars::{write_player_stats_csv, ParserBuilder};
+///
+/// let data = include_bytes!("../../assets/replays/good/rumble.replay");
+/// let replay = ParserBuilder::new(&data[..]).parse().unwrap();
+///
+/// let mut out = Vec::new();
+/// write_player_stats_csv(&replay, &mut out).unwrap();
+///
+/// let csv = String::from_utf8(out).unwrap();
+/// let mut lines = csv.lines();
+/// assert_eq!(
+///     lines.next(),
+///     Some("name,platform,online_id,team,score,goals,assists,saves,shots,is_bot")
+/// );
+/// assert_eq!(lines.count(), 6);
+/// ```
+pub fn write_player_stats_csv<W: io::Write>(replay: &Replay, writer: W) -> Result<(), ExportError> {
+    let header = replay.typed_header().map_err(AnalysisError::HeaderError)?;
+    let mut csv = writer_with_headers(writer, &PLAYER_STATS_COLUMNS)?;
+    for stats in &header.player_stats {
+        csv.serialize(stats)?;
+    }
+
+    csv.flush().map_err(csv::Error::from)?;
+    Ok(())
+}
+
+/// Writes the column names upfront so that they are present even when there are no rows
+fn writer_with_headers<W: io::Write>(
+    writer: W,
+    columns: &[&str],
+) -> Result<csv::Writer<W>, ExportError> {
+    let mut csv = csv::WriterBuilder::new()
+        .has_headers(false)
+        .from_writer(writer);
+    csv.write_record(columns)?;
+    Ok(csv)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_player_stats_without_players() {
+        let data = include_bytes!("../../assets/replays/good/rumble.replay");
+        let mut replay = crate::ParserBuilder::new(&data[..]).parse().unwrap();
+        replay.properties.retain(|(name, _)| name != "PlayerStats");
+
+        let mut out = Vec::new();
+        write_player_stats_csv(&replay, &mut out).unwrap();
+        let expected = format!("{}\n", PLAYER_STATS_COLUMNS.join(","));
+        assert_eq!(String::from_utf8(out).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_frames_require_network_data() {
+        let data = include_bytes!("../../assets/replays/good/rumble.replay");
+        let replay = crate::ParserBuilder::new(&data[..])
+            .never_parse_network_data()
+            .parse()
+            .unwrap();
+
+        let result = write_frames_csv(&replay, &ClassTables::default(), Vec::new());
+        assert!(matches!(
+            result,
+            Err(ExportError::AnalysisError(
+                AnalysisError::MissingNetworkFrames
+            ))
+        ));
+    }
+}
@@ -1,33 +1,53 @@
 use bitter::BitGet;
 use fnv::FnvHashMap;
+use std::borrow::Cow;
 
 use crate::errors::{AttributeError, FrameContext, FrameError, NetworkError};
+use crate::models::KeyFrame;
 use crate::network::attributes::{AttributeDecoder, ProductValueDecoder};
 use crate::network::models::{
-    ActorId, Frame, NewActor, ObjectId, SpawnTrajectory, StreamId, Trajectory, UpdatedAttribute,
+    ActorId, BitSpan, Frame, FrameSpans, NewActor, ObjectId, SpawnTrajectory, StreamId, Trajectory,
+    UpdatedAttribute,
 };
-use crate::network::{CacheInfo, VersionTriplet};
-use crate::parser::ReplayBody;
+use crate::network::{parallel, CacheInfo, VersionTriplet};
 
-pub(crate) struct FrameDecoder<'a, 'b: 'a> {
+pub(crate) struct FrameDecoder<'a> {
     pub frames_len: usize,
     pub product_decoder: ProductValueDecoder,
     pub max_channels: u32,
     pub channel_bits: i32,
-    pub body: &'a ReplayBody<'b>,
-    pub spawns: &'a Vec<SpawnTrajectory>,
-    pub object_ind_attributes: FnvHashMap<ObjectId, CacheInfo<'a>>,
+    pub network_data: &'a [u8],
+    pub objects: Cow<'a, [String]>,
+    pub spawns: Vec<SpawnTrajectory>,
+    pub object_ind_attributes: FnvHashMap<ObjectId, CacheInfo>,
     pub version: VersionTriplet,
     pub is_lan: bool,
+
+    /// Record where each frame, new actor, and attribute is decoded from
+    pub record_spans: bool,
+}
+
+/// The frames decoded from the network data and, when recording spans, where each was decoded from
+#[derive(Debug)]
+pub(crate) struct DecodedFrames {
+    pub frames: Vec<Frame>,
+    pub spans: Option<Vec<FrameSpans>>,
 }
 
 #[derive(Debug)]
-enum DecodedFrame {
+pub(super) enum DecodedFrame {
     EndFrame,
-    Frame(Frame),
+
+    /// The frame and, when recording spans, where it was decoded from
+    Frame(Frame, Option<FrameSpans>),
 }
 
-impl<'a, 'b> FrameDecoder<'a, 'b> {
+impl<'a> FrameDecoder<'a> {
+    /// The bit position in the network data that will be read next
+    pub(super) fn position(&self, bits: &BitGet<'_>) -> usize {
+        self.network_data.len() * 8 - bits.bits_remaining().unwrap_or(0)
+    }
+
     fn parse_new_actor(
         &self,
         mut bits: &mut BitGet<'_>,
@@ -60,7 +80,7 @@ impl<'a, 'b> FrameDecoder<'a, 'b> {
         }
     }
 
-    fn decode_frame(
+    pub(super) fn decode_frame(
         &self,
         attr_decoder: &AttributeDecoder,
         mut bits: &mut BitGet<'_>,
@@ -69,6 +89,19 @@ impl<'a, 'b> FrameDecoder<'a, 'b> {
         deleted_actors: &mut Vec<ActorId>,
         updated_actors: &mut Vec<UpdatedAttribute>,
     ) -> Result<DecodedFrame, FrameError> {
+        let mut spans = if self.record_spans {
+            Some(FrameSpans {
+                frame: BitSpan {
+                    start: self.position(bits),
+                    end: 0,
+                },
+                new_actors: Vec::new(),
+                updated_actors: Vec::new(),
+            })
+        } else {
+            None
+        };
+
         let time = bits
             .read_f32()
             .ok_or_else(|| FrameError::NotEnoughDataFor("Time"))?;
@@ -108,7 +141,12 @@ impl<'a, 'b> FrameDecoder<'a, 'b> {
                     .read_bit()
                     .ok_or_else(|| FrameError::NotEnoughDataFor("Is new actor"))?
                 {
+                    let start = spans.as_ref().map(|_| self.position(bits));
                     let actor = self.parse_new_actor(&mut bits, actor_id)?;
+                    if let (Some(spans), Some(start)) = (spans.as_mut(), start) {
+                        let end = self.position(bits);
+                        spans.new_actors.push(BitSpan { start, end });
+                    }
 
                     // Insert the new actor so we can keep track of it for attribute
                     // updates. It's common for an actor id to already exist, so we
@@ -157,6 +195,7 @@ impl<'a, 'b> FrameDecoder<'a, 'b> {
                             }
                         })?;
 
+                        let start = spans.as_ref().map(|_| self.position(bits));
                         let attribute = attr_decoder.decode(attr.attribute, &mut bits).map_err(
                             |e| match e {
                                 AttributeError::Unimplemented => FrameError::MissingAttribute {
@@ -173,6 +212,11 @@ impl<'a, 'b> FrameDecoder<'a, 'b> {
                             },
                         )?;
 
+                        if let (Some(spans), Some(start)) = (spans.as_mut(), start) {
+                            let end = self.position(bits);
+                            spans.updated_actors.push(BitSpan { start, end });
+                        }
+
                         updated_actors.push(UpdatedAttribute {
                             actor_id,
                             stream_id,
@@ -187,72 +231,481 @@ impl<'a, 'b> FrameDecoder<'a, 'b> {
             }
         }
 
-        Ok(DecodedFrame::Frame(Frame {
+        if let Some(spans) = spans.as_mut() {
+            spans.frame.end = self.position(bits);
+        }
+
+        let frame = Frame {
             time,
             delta,
             new_actors: new_actors.drain(..).collect(),
             deleted_actors: deleted_actors.drain(..).collect(),
             updated_actors: updated_actors.drain(..).collect(),
-        }))
+        };
+
+        Ok(DecodedFrame::Frame(frame, spans))
     }
 
-    pub fn decode_frames(&self) -> Result<Vec<Frame>, NetworkError> {
-        let attr_decoder = AttributeDecoder::new(self.version, self.product_decoder);
-        let mut frames: Vec<Frame> = Vec::with_capacity(self.frames_len);
-        let mut actors = FnvHashMap::default();
-        let mut bits = BitGet::new(self.body.network_data);
-        let mut new_actors = Vec::new();
-        let mut updated_actors = Vec::new();
-        let mut deleted_actors = Vec::new();
-
-        while !bits.is_empty() && frames.len() < self.frames_len {
-            let frame = self
-                .decode_frame(
-                    &attr_decoder,
-                    &mut bits,
-                    &mut actors,
-                    &mut new_actors,
-                    &mut deleted_actors,
-                    &mut updated_actors,
-                )
-                .map_err(|e| {
-                    NetworkError::FrameError(
-                        e,
-                        Box::new(FrameContext {
-                            objects: self.body.objects.clone(),
-                            object_attributes: self
-                                .object_ind_attributes
-                                .iter()
-                                .map(|(key, value)| {
-                                    (
-                                        *key,
-                                        value
-                                            .attributes
-                                            .iter()
-                                            .map(|(key2, value)| (*key2, value.object_id))
-                                            .collect(),
-                                    )
-                                })
-                                .collect(),
-                            frames: frames.clone(),
-                            actors: actors.clone(),
-                            new_actors: new_actors.clone(),
-                            updated_actors: updated_actors.clone(),
-                        }),
+    pub(super) fn decode_trailer(&self, bits: &mut BitGet<'_>) -> Result<(), NetworkError> {
+        if self.version >= VersionTriplet(868, 24, 10) {
+            bits.read_u32()
+                .ok_or_else(|| NetworkError::NotEnoughDataFor("Trailer"))?;
+        }
+
+        Ok(())
+    }
+
+    /// Captures the state of decoding for a more helpful error message
+    pub(super) fn context(
+        &self,
+        actors: &FnvHashMap<ActorId, ObjectId>,
+        new_actors: &[NewActor],
+        updated_actors: &[UpdatedAttribute],
+        frame: usize,
+        bits: &BitGet<'_>,
+    ) -> FrameContext {
+        FrameContext {
+            objects: self.objects.to_vec(),
+            object_attributes: self
+                .object_ind_attributes
+                .iter()
+                .map(|(key, value)| {
+                    (
+                        *key,
+                        value
+                            .attributes
+                            .iter()
+                            .map(|(key2, value)| (*key2, value.object_id))
+                            .collect(),
                     )
-                })?;
+                })
+                .collect(),
+            frames: Vec::new(),
+            actors: actors.clone(),
+            new_actors: new_actors.to_vec(),
+            updated_actors: updated_actors.to_vec(),
+            frame,
+            bit_position: self.position(bits),
+            major_version: self.version.0,
+            minor_version: self.version.1,
+            net_version: self.version.2,
+        }
+    }
+}
+
+/// The keyframes that decoding can start from: those in order and within the network data. The
+/// start of the network data is always the first.
+pub(super) fn valid_keyframes(
+    keyframes: &[KeyFrame],
+    frames_len: usize,
+    data_bits: usize,
+) -> Vec<KeyFrame> {
+    let mut result = vec![KeyFrame {
+        time: 0.0,
+        frame: 0,
+        position: 0,
+    }];
+
+    for keyframe in keyframes {
+        let last = result[result.len() - 1];
+        if keyframe.frame > last.frame
+            && keyframe.position > last.position
+            && (keyframe.frame as usize) < frames_len
+            && (keyframe.position as usize) < data_bits
+        {
+            result.push(*keyframe);
+        }
+    }
+
+    result
+}
+
+/// Returns a bit reader positioned at the given bit in the data
+pub(super) fn bits_at(data: &[u8], position: usize) -> Option<BitGet<'_>> {
+    let mut bits = BitGet::new(data.get(position / 8..)?);
+    let offset = (position % 8) as i32;
+    if offset > 0 {
+        bits.read_u32_bits(offset)?;
+    }
+    Some(bits)
+}
+
+/// Decodes the network data one frame at a time. The decoded frames are exactly those that would
+/// be found in `Replay::network_frames`, except that they are never held in memory all at once,
+/// which is useful for long replays when one only needs to fold over the frames.
+///
+/// Once an error is yielded, iteration stops. As previously decoded frames are not retained,
+/// the `FrameContext` of a yielded error will not contain any frames.
+///
+/// ```
+/// use boxcars::ParserBuilder;
+///
+/// let data = include_bytes!("../../assets/replays/good/rumble.replay");
+/// let (replay, frames) = ParserBuilder::new(&data[..]).parse_streaming().unwrap();
+///
+/// let mut new_actors = 0;
+/// for frame in frames {
+///     new_actors += frame.unwrap().new_actors.len();
+/// }
+///
+/// assert!(replay.network_frames.is_none());
+/// assert!(new_actors > 0);
+/// ```
+///
+/// The iterator can also seek to a frame or time. Decoding resumes from the nearest keyframe
+/// before the target, so only the frames since that keyframe are decoded.
+///
+/// ```
+/// use boxcars::ParserBuilder;
+///
+/// let data = include_bytes!("../../assets/replays/good/rumble.replay");
+/// let (_, mut frames) = ParserBuilder::new(&data[..]).parse_streaming().unwrap();
+///
+/// frames.seek_time(180.0).unwrap();
+/// let window = frames
+///     .by_ref()
+///     .take(30)
+///     .collect::<Result<Vec<_>, _>>()
+///     .unwrap();
+/// assert!(window[0].time >= 180.0);
+///
+/// frames.seek_frame(100).unwrap();
+/// assert_eq!(frames.current_frame(), 100);
+/// ```
+pub struct FrameIter<'a> {
+    decoder: FrameDecoder<'a>,
+    attr_decoder: AttributeDecoder,
+    keyframes: Vec<KeyFrame>,
+    bits: BitGet<'a>,
+    actors: FnvHashMap<ActorId, ObjectId>,
+    new_actors: Vec<NewActor>,
+    updated_actors: Vec<UpdatedAttribute>,
+    deleted_actors: Vec<ActorId>,
+    frames_decoded: usize,
+    finished: bool,
+
+    /// False when decoding started from a keyframe, as the actor table only contains the actors
+    /// seen since the keyframe
+    exact: bool,
+
+    /// A frame decoded while seeking that has yet to be yielded
+    pending: Option<Frame>,
+
+    /// The time of the last frame yielded
+    last_time: Option<f32>,
+
+    /// Where the last frame decoded was decoded from, when recording spans
+    spans: Option<FrameSpans>,
+}
+
+impl<'a> FrameIter<'a> {
+    pub(crate) fn new(decoder: FrameDecoder<'a>, keyframes: &[KeyFrame]) -> Self {
+        let keyframes = valid_keyframes(
+            keyframes,
+            decoder.frames_len,
+            decoder.network_data.len() * 8,
+        );
+
+        FrameIter {
+            attr_decoder: AttributeDecoder::new(decoder.version, decoder.product_decoder),
+            bits: BitGet::new(decoder.network_data),
+            decoder,
+            keyframes,
+            actors: FnvHashMap::default(),
+            new_actors: Vec::new(),
+            updated_actors: Vec::new(),
+            deleted_actors: Vec::new(),
+            frames_decoded: 0,
+            finished: false,
+            exact: true,
+            pending: None,
+            last_time: None,
+            spans: None,
+        }
+    }
+
+    /// Records where each frame is decoded from in the network data, which is then available from
+    /// `spans`. Off by default, as recording the spans allocates for every frame.
+    pub fn with_bit_spans(mut self) -> Self {
+        self.decoder.record_spans = true;
+        self
+    }
 
+    /// An iterator for when the replay doesn't contain a frame count and thus no network data is
+    /// decoded
+    pub(crate) fn empty(decoder: FrameDecoder<'a>) -> Self {
+        let mut result = FrameIter::new(decoder, &[]);
+        result.finished = true;
+        result
+    }
+
+    /// Number of frames that the header claims are in the network data. Iteration will stop
+    /// before this if the network data ends early.
+    pub fn frames_len(&self) -> usize {
+        self.decoder.frames_len
+    }
+
+    /// The undecoded network data
+    pub(super) fn network_data(&self) -> &'a [u8] {
+        self.decoder.network_data
+    }
+
+    /// Where the most recently decoded frame was decoded from in the network data. Only
+    /// available after `with_bit_spans`.
+    pub fn spans(&self) -> Option<&FrameSpans> {
+        self.spans.as_ref()
+    }
+
+    /// The index of the frame that will be yielded next
+    pub fn current_frame(&self) -> usize {
+        self.frames_decoded - self.pending.iter().count()
+    }
+
+    /// Positions the iterator so that the next frame yielded is the frame at the given index.
+    /// Seeking past the end of the network data leaves the iterator at the end.
+    pub fn seek_frame(&mut self, frame: usize) -> Result<(), NetworkError> {
+        let start = self.keyframe_before(|x| x.frame as usize <= frame);
+        if self.current_frame() > frame || self.current_frame() < start.frame as usize {
+            self.reset(start);
+        }
+
+        self.skip_to(frame)
+    }
+
+    /// Positions the iterator so that the next frame yielded is the first frame with a time
+    /// greater than or equal to the given time. Time is measured the same as `Frame::time`.
+    pub fn seek_time(&mut self, time: f32) -> Result<(), NetworkError> {
+        let start = self.keyframe_before(|x| x.time <= time);
+        let behind = self.last_time.filter(|&x| x >= time).is_some();
+        if behind || self.current_frame() < start.frame as usize {
+            self.reset(start);
+        }
+
+        loop {
+            let last_time = self.last_time;
+            match self.next() {
+                Some(Ok(frame)) => {
+                    if frame.time >= time {
+                        self.last_time = last_time;
+                        self.pending = Some(frame);
+                        return Ok(());
+                    }
+                }
+                Some(Err(e)) => return Err(e),
+                None => return Ok(()),
+            }
+        }
+    }
+
+    /// Decodes and discards frames until the given frame is next
+    fn skip_to(&mut self, frame: usize) -> Result<(), NetworkError> {
+        while self.current_frame() < frame {
+            match self.next() {
+                Some(Ok(_)) => {}
+                Some(Err(e)) => return Err(e),
+                None => break,
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The last keyframe that satisfies the predicate
+    fn keyframe_before<F>(&self, f: F) -> KeyFrame
+    where
+        F: Fn(&KeyFrame) -> bool,
+    {
+        self.keyframes
+            .iter()
+            .rev()
+            .find(|x| f(x))
+            .cloned()
+            .unwrap_or(self.keyframes[0])
+    }
+
+    /// Restarts decoding at the keyframe
+    fn reset(&mut self, keyframe: KeyFrame) {
+        // Keyframes are validated to be within the network data
+        if let Some(bits) = bits_at(self.decoder.network_data, keyframe.position as usize) {
+            self.bits = bits;
+        }
+
+        self.actors.clear();
+        self.new_actors.clear();
+        self.updated_actors.clear();
+        self.deleted_actors.clear();
+        self.frames_decoded = keyframe.frame as usize;
+        self.finished = false;
+        self.exact = keyframe.frame == 0;
+        self.pending = None;
+        self.last_time = None;
+        self.spans = None;
+    }
+
+    /// Decodes all the frames. When more than one thread is given, the network data is split at
+    /// the keyframes and decoded in parallel.
+    pub(crate) fn decode_frames(self, threads: usize) -> Result<DecodedFrames, NetworkError> {
+        match self.decode_partial(threads) {
+            (network, None) => Ok(network),
+
+            // The iterator doesn't hold onto previously decoded frames, so we restore them
+            // here for a more helpful error message
+            (network, Some(NetworkError::FrameError(err, mut context))) => {
+                context.frames = network.frames;
+                Err(NetworkError::FrameError(err, context))
+            }
+            (_, Some(e)) => Err(e),
+        }
+    }
+
+    /// Decodes frames until the end of the network data or the first error, whichever comes
+    /// first. The error's context will not contain any frames.
+    pub(crate) fn decode_partial(
+        mut self,
+        threads: usize,
+    ) -> (DecodedFrames, Option<NetworkError>) {
+        if threads > 1 && !self.finished {
+            return parallel::decode_partial(&self.decoder, &self.keyframes, threads);
+        }
+
+        let mut network = DecodedFrames {
+            frames: Vec::with_capacity(self.decoder.frames_len),
+            spans: if self.decoder.record_spans {
+                Some(Vec::with_capacity(self.decoder.frames_len))
+            } else {
+                None
+            },
+        };
+
+        while let Some(frame) = self.next() {
             match frame {
-                DecodedFrame::EndFrame => break,
-                DecodedFrame::Frame(frame) => frames.push(frame),
+                Ok(frame) => network.frames.push(frame),
+                Err(e) => return (network, Some(e)),
+            }
+
+            if let (Some(spans), Some(frame_spans)) = (network.spans.as_mut(), self.spans.take()) {
+                spans.push(frame_spans);
             }
         }
 
-        if self.version >= VersionTriplet(868, 24, 10) {
-            bits.read_u32()
-                .ok_or_else(|| NetworkError::NotEnoughDataFor("Trailer"))?;
+        (network, None)
+    }
+
+    fn context(&self) -> FrameContext {
+        self.decoder.context(
+            &self.actors,
+            &self.new_actors,
+            &self.updated_actors,
+            self.frames_decoded,
+            &self.bits,
+        )
+    }
+}
+
+impl<'a> Iterator for FrameIter<'a> {
+    type Item = Result<Frame, NetworkError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(frame) = self.pending.take() {
+            self.last_time = Some(frame.time);
+            return Some(Ok(frame));
+        }
+
+        if self.finished {
+            return None;
+        }
+
+        if self.bits.is_empty() || self.frames_decoded >= self.decoder.frames_len {
+            self.finished = true;
+            return self.decoder.decode_trailer(&mut self.bits).err().map(Err);
+        }
+
+        let frame = self.decoder.decode_frame(
+            &self.attr_decoder,
+            &mut self.bits,
+            &mut self.actors,
+            &mut self.new_actors,
+            &mut self.deleted_actors,
+            &mut self.updated_actors,
+        );
+
+        match frame {
+            Ok(DecodedFrame::EndFrame) => {
+                self.finished = true;
+                self.decoder.decode_trailer(&mut self.bits).err().map(Err)
+            }
+            Ok(DecodedFrame::Frame(frame, spans)) => {
+                self.frames_decoded += 1;
+                self.last_time = Some(frame.time);
+                self.spans = spans;
+                Some(Ok(frame))
+            }
+
+            // Either an actor that wasn't re-announced at the keyframe we started from or the
+            // keyframe is bogus. Decode from the start of the network data to know for sure.
+            Err(_) if !self.exact => {
+                let frame = self.frames_decoded;
+                self.reset(self.keyframes[0]);
+                if let Err(e) = self.skip_to(frame) {
+                    return Some(Err(e));
+                }
+                self.next()
+            }
+            Err(e) => {
+                self.finished = true;
+                Some(Err(NetworkError::FrameError(e, Box::new(self.context()))))
+            }
         }
+    }
+}
+
+impl<'a> std::iter::FusedIterator for FrameIter<'a> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keyframe(frame: i32, position: i32) -> KeyFrame {
+        KeyFrame {
+            time: frame as f32,
+            frame,
+            position,
+        }
+    }
+
+    #[test]
+    fn test_valid_keyframes() {
+        let keyframes = vec![
+            keyframe(0, 0),
+            keyframe(10, 100),
+            keyframe(20, 90),
+            keyframe(30, 300),
+            keyframe(-1, 400),
+            keyframe(40, 5000),
+            keyframe(500, 600),
+        ];
+
+        let actual = valid_keyframes(&keyframes, 100, 1000);
+        assert_eq!(
+            actual,
+            vec![
+                KeyFrame {
+                    time: 0.0,
+                    frame: 0,
+                    position: 0
+                },
+                keyframe(10, 100),
+                keyframe(30, 300),
+            ]
+        );
+    }
 
-        Ok(frames)
+    #[test]
+    fn test_bits_at() {
+        let data = [0b1010_1100, 0b0000_0001];
+        let mut bits = bits_at(&data, 2).unwrap();
+        assert_eq!(bits.read_u32_bits(7), Some(0b110_1011));
+        assert!(bits_at(&data, 16).is_some());
+        assert!(bits_at(&data, 17).is_none());
     }
 }
@@ -0,0 +1,234 @@
+use fnv::FnvHashMap;
+
+use crate::bit_writer::BitWriter;
+use crate::errors::{FrameError, WriteError};
+use crate::network::attributes::AttributeEncoder;
+use crate::network::frame_decoder::FrameDecoder;
+use crate::network::models::{ActorId, Frame, NewActor, ObjectId, UpdatedAttribute};
+use crate::network::{CacheInfo, VersionTriplet};
+
+/// The network data as written by the `FrameEncoder`
+pub(crate) struct EncodedFrames {
+    pub data: Vec<u8>,
+
+    /// The bit position in the network data where each frame starts
+    pub frame_positions: Vec<usize>,
+}
+
+/// The inverse of the `FrameDecoder`. It is constructed from the same lookup tables so that the
+/// actor ids and attribute stream ids are written with the number of bits that the decoder
+/// expects.
+pub(crate) struct FrameEncoder {
+    frames_len: usize,
+    max_channels: u32,
+    channel_bits: i32,
+    object_ind_attributes: FnvHashMap<ObjectId, CacheInfo>,
+    version: VersionTriplet,
+    is_lan: bool,
+}
+
+impl FrameEncoder {
+    pub fn new(decoder: FrameDecoder<'_>) -> Self {
+        FrameEncoder {
+            frames_len: decoder.frames_len,
+            max_channels: decoder.max_channels,
+            channel_bits: decoder.channel_bits,
+            object_ind_attributes: decoder.object_ind_attributes,
+            version: decoder.version,
+            is_lan: decoder.is_lan,
+        }
+    }
+
+    pub fn encode(&self, frames: &[Frame]) -> Result<EncodedFrames, WriteError> {
+        let attr_encoder = AttributeEncoder::new(self.version);
+        let mut bits = BitWriter::new();
+        let mut actors: FnvHashMap<ActorId, ObjectId> = FnvHashMap::default();
+        let mut frame_positions = Vec::with_capacity(frames.len());
+
+        for (i, frame) in frames.iter().enumerate() {
+            frame_positions.push(bits.bit_position());
+            self.encode_frame(&attr_encoder, &mut bits, &mut actors, frame, i)?;
+        }
+
+        // The decoder stops at the header's frame count, so only when there are fewer frames
+        // is an end frame (zero time and delta) needed to stop decoding
+        if frames.len() < self.frames_len {
+            bits.write_f32(0.0);
+            bits.write_f32(0.0);
+        }
+
+        if self.version >= VersionTriplet(868, 24, 10) {
+            bits.write_u32(0);
+        }
+
+        Ok(EncodedFrames {
+            data: bits.into_bytes(),
+            frame_positions,
+        })
+    }
+
+    fn encode_actor_id(
+        &self,
+        bits: &mut BitWriter,
+        actor_id: ActorId,
+        frame: usize,
+    ) -> Result<(), WriteError> {
+        if actor_id.0 < 0 || actor_id.0 as u32 >= self.max_channels {
+            return Err(WriteError::ActorIdOutOfRange {
+                frame,
+                actor: actor_id,
+            });
+        }
+
+        bits.write_bits_max_computed(self.channel_bits, self.max_channels, actor_id.0 as u32);
+        Ok(())
+    }
+
+    fn encode_new_actor(&self, bits: &mut BitWriter, actor: &NewActor) {
+        if self.version >= VersionTriplet(868, 14, 0) && !self.is_lan {
+            bits.write_i32(actor.name_id.unwrap_or(0));
+        }
+
+        // unused bit
+        bits.write_bit(false);
+        bits.write_i32(actor.object_id.0);
+        actor
+            .initial_trajectory
+            .encode(bits, self.version.net_version());
+    }
+
+    fn encode_updates(
+        &self,
+        attr_encoder: &AttributeEncoder,
+        bits: &mut BitWriter,
+        actors: &FnvHashMap<ActorId, ObjectId>,
+        updates: &[UpdatedAttribute],
+        frame: usize,
+    ) -> Result<(), WriteError> {
+        let actor_id = updates[0].actor_id;
+        let object_id = actors.get(&actor_id).ok_or(WriteError::FrameError(
+            frame,
+            FrameError::MissingActor { actor: actor_id },
+        ))?;
+
+        let cache_info =
+            self.object_ind_attributes
+                .get(object_id)
+                .ok_or(WriteError::FrameError(
+                    frame,
+                    FrameError::MissingCache {
+                        actor: actor_id,
+                        actor_object: *object_id,
+                    },
+                ))?;
+
+        for update in updates {
+            let attr =
+                cache_info
+                    .attributes
+                    .get(&update.stream_id)
+                    .ok_or(WriteError::FrameError(
+                        frame,
+                        FrameError::MissingAttribute {
+                            actor: actor_id,
+                            actor_object: *object_id,
+                            attribute_stream: update.stream_id,
+                        },
+                    ))?;
+
+            // The decoder names the attribute after the actor's object, so an update meant for
+            // another object wouldn't be read back the same
+            if attr.attribute != update.attribute.tag() || attr.object_id != update.object_id {
+                return Err(WriteError::UnexpectedAttribute {
+                    frame,
+                    actor: actor_id,
+                    attribute_stream: update.stream_id,
+                });
+            }
+
+            // is prop present
+            bits.write_bit(true);
+            bits.write_bits_max_computed(
+                cache_info.prop_id_bits,
+                cache_info.max_prop_id,
+                update.stream_id.0 as u32,
+            );
+            attr_encoder.encode(&update.attribute, bits);
+        }
+
+        bits.write_bit(false);
+        Ok(())
+    }
+
+    /// Actors are written in the order of deleted, new, and then updated actors. Consecutive
+    /// updates to the same actor are grouped together. A frame doesn't record the order that its
+    /// actors were decoded in, so updates to an actor that the frame deletes are an error, as
+    /// they'd be written after the deletion. Only if the frame spawns the actor again are they
+    /// written, as updates to the new actor.
+    fn encode_frame(
+        &self,
+        attr_encoder: &AttributeEncoder,
+        bits: &mut BitWriter,
+        actors: &mut FnvHashMap<ActorId, ObjectId>,
+        frame: &Frame,
+        frame_ind: usize,
+    ) -> Result<(), WriteError> {
+        let deleted_update = frame.updated_actors.iter().find(|x| {
+            frame.deleted_actors.contains(&x.actor_id)
+                && !frame.new_actors.iter().any(|y| y.actor_id == x.actor_id)
+        });
+
+        if let Some(update) = deleted_update {
+            return Err(WriteError::DeletedActorUpdated {
+                frame: frame_ind,
+                actor: update.actor_id,
+            });
+        }
+
+        bits.write_f32(frame.time);
+        bits.write_f32(frame.delta);
+
+        for &actor_id in &frame.deleted_actors {
+            // has actor data
+            bits.write_bit(true);
+            self.encode_actor_id(bits, actor_id, frame_ind)?;
+
+            // is actor alive
+            bits.write_bit(false);
+            actors.remove(&actor_id);
+        }
+
+        for actor in &frame.new_actors {
+            bits.write_bit(true);
+            self.encode_actor_id(bits, actor.actor_id, frame_ind)?;
+
+            // is actor alive and new
+            bits.write_bit(true);
+            bits.write_bit(true);
+            self.encode_new_actor(bits, actor);
+            actors.insert(actor.actor_id, actor.object_id);
+        }
+
+        let mut updates = &frame.updated_actors[..];
+        while let Some(first) = updates.first() {
+            let len = updates
+                .iter()
+                .take_while(|x| x.actor_id == first.actor_id)
+                .count();
+            let (actor_updates, rest) = updates.split_at(len);
+
+            bits.write_bit(true);
+            self.encode_actor_id(bits, first.actor_id, frame_ind)?;
+
+            // is actor alive but not new
+            bits.write_bit(true);
+            bits.write_bit(false);
+            self.encode_updates(attr_encoder, bits, actors, actor_updates, frame_ind)?;
+            updates = rest;
+        }
+
+        // no more actor data
+        bits.write_bit(false);
+        Ok(())
+    }
+}
@@ -0,0 +1,216 @@
+use crate::errors::AnalysisError;
+use crate::models::Replay;
+use crate::network::attributes::{Attribute, Explosion, ExtendedExplosion, RemoteId, RigidBody};
+use crate::network::models::{ActorId, Vector3f};
+use crate::network::players::{Player, PlayerResolver};
+use crate::network::touches::{is_ball, unreal_units, Touch, TouchDetector};
+use crate::network::ClassTables;
+
+const RIGID_BODY: &str = "TAGame.RBActor_TA:ReplicatedRBState";
+const OVERTIME: &str = "TAGame.GameEvent_Soccar_TA:bOverTime";
+
+/// The ball explodes in the frame the goal is scored, which may be a few frames off from the frame
+/// recorded in the header
+const EXPLOSION_TOLERANCE: usize = 30;
+
+/// A goal from the header's `Goals` property cross-referenced with the network data
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct GoalEvent {
+    /// The index of the frame where the goal was scored
+    pub frame: usize,
+
+    /// The frame's time in seconds
+    pub time: f32,
+
+    /// The scorer's name as recorded in the header
+    pub player_name: String,
+
+    /// The team number (0 or 1) that scored
+    pub team: i32,
+
+    /// The scorer's PRI actor (see `Player::actor_id`) if the scorer could be found
+    pub player: Option<ActorId>,
+    pub remote_id: Option<RemoteId>,
+    pub overtime: bool,
+
+    /// The ball's location and velocity (in unreal units, regardless of the replay's network
+    /// version) as it crossed the goal line, which is the last update where the ball was moving
+    pub ball_location: Option<Vector3f>,
+    pub ball_velocity: Option<Vector3f>,
+    pub ball_speed: Option<f32>,
+
+    /// The touches since the previous goal (or the start of the match) with the last touch before
+    /// the goal at the end
+    pub touches: Vec<Touch>,
+}
+
+/// Cross-references the goals in the header with the network frames to know exactly when each goal
+/// was scored, who scored it (including their unique id), the ball's speed as it went in, and the
+/// touches that led up to it. Overtime goals are flagged from the game event's
+/// `TAGame.GameEvent_Soccar_TA:bOverTime` attribute. Actors are classified with the tables the replay
+/// was parsed with.
+///
+/// ```
+/// use boxcars::{goal_events, ClassTables, ParserBuilder};
+///
+/// let data = include_bytes!("../../assets/replays/good/rumble.replay");
+/// let replay = ParserBuilder::new(&data[..])
+///     .must_parse_network_data()
+///     .parse()
+///     .unwrap();
+///
+/// let goals = goal_events(&replay, &ClassTables::default()).unwrap();
+/// assert_eq!(goals.len(), 7);
+/// assert_eq!(goals[0].player_name, "Cakeboss");
+/// assert!(goals[0].remote_id.is_some());
+/// assert!(goals[0].ball_speed.unwrap() > 0.0);
+/// ```
+pub fn goal_events(replay: &Replay, tables: &ClassTables) -> Result<Vec<GoalEvent>, AnalysisError> {
+    let header = replay.typed_header().map_err(AnalysisError::HeaderError)?;
+    let frames = &replay
+        .network_frames
+        .as_ref()
+        .ok_or(AnalysisError::MissingNetworkFrames)?
+        .frames;
+
+    let mut resolver = PlayerResolver::new(&replay.objects, tables);
+    let mut detector = TouchDetector::new().with_net_version(replay.net_version);
+    let mut explosions: Vec<(usize, Vector3f)> = Vec::new();
+    let mut ball_updates: Vec<(usize, RigidBody)> = Vec::new();
+    let mut overtime_frame = None;
+
+    for (frame_ind, frame) in frames.iter().enumerate() {
+        resolver
+            .process_frame(frame)
+            .map_err(|e| AnalysisError::FrameError(frame_ind, e))?;
+        detector.process_frame(&resolver, frame);
+
+        let tracker = resolver.tracker();
+        for update in &frame.updated_actors {
+            let is_ball_update = tracker
+                .actor(update.actor_id)
+                .filter(|x| is_ball(x))
+                .is_some();
+            let name = tracker.object_name(update.object_id).unwrap_or_default();
+            match (name, &update.attribute) {
+                (RIGID_BODY, Attribute::RigidBody(body))
+                    if is_ball_update && body.linear_velocity.is_some() =>
+                {
+                    ball_updates.push((frame_ind, *body));
+                }
+                // Keyframes replicate the last explosion again
+                (_, Attribute::Explosion(Explosion { location, .. }))
+                | (
+                    _,
+                    Attribute::ExtendedExplosion(ExtendedExplosion {
+                        explosion: Explosion { location, .. },
+                        ..
+                    }),
+                ) if is_ball_update && explosions.last().filter(|x| x.1 == *location).is_none() => {
+                    explosions.push((frame_ind, *location));
+                }
+                (OVERTIME, Attribute::Boolean(true)) if overtime_frame.is_none() => {
+                    overtime_frame = Some(frame_ind);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    let (location_scale, velocity_scale) = unreal_units(replay.net_version);
+    let mut result: Vec<GoalEvent> = Vec::with_capacity(header.goals.len());
+    for goal in &header.goals {
+        let header_frame = (goal.frame.max(0) as usize).min(frames.len().saturating_sub(1));
+        let frame = explosions
+            .iter()
+            .map(|&(x, _)| x)
+            .filter(|&x| x.max(header_frame) - x.min(header_frame) <= EXPLOSION_TOLERANCE)
+            .min_by_key(|&x| x.max(header_frame) - x.min(header_frame))
+            .unwrap_or(header_frame);
+
+        let scorer = scorer(
+            resolver.players(),
+            &goal.player_name,
+            goal.player_team,
+            frame,
+        );
+        let ball = ball_updates[..ball_updates.partition_point(|&(x, _)| x <= frame)]
+            .last()
+            .map(|&(_, body)| body);
+        let ball_velocity = ball
+            .and_then(|x| x.linear_velocity)
+            .map(|x| x.scale(velocity_scale));
+
+        let since = result.last().map(|x| x.frame);
+        let touches = detector
+            .touches()
+            .iter()
+            .filter(|x| since.filter(|&since| x.frame <= since).is_none() && x.frame <= frame)
+            .cloned()
+            .collect();
+
+        result.push(GoalEvent {
+            frame,
+            time: frames.get(frame).map(|x| x.time).unwrap_or_default(),
+            player_name: goal.player_name.clone(),
+            team: goal.player_team,
+            player: scorer.map(|x| x.actor_id),
+            remote_id: scorer.and_then(|x| x.remote_id.clone()),
+            overtime: overtime_frame.filter(|&x| x <= frame).is_some(),
+            ball_location: ball.map(|x| x.location.scale(location_scale)),
+            ball_velocity,
+            ball_speed: ball_velocity.map(|x| x.magnitude()),
+            touches,
+        });
+    }
+
+    Ok(result)
+}
+
+/// Finds the scorer by name, preferring a player on the scoring team that joined before the goal
+fn scorer<'a>(players: &'a [Player], name: &str, team: i32, frame: usize) -> Option<&'a Player> {
+    players
+        .iter()
+        .filter(|x| x.name.as_deref() == Some(name))
+        .max_by_key(|x| {
+            (
+                x.team == Some(team),
+                x.created_frame <= frame,
+                x.created_frame,
+            )
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn player(actor: i32, name: &str, team: i32, created_frame: usize) -> Player {
+        Player {
+            actor_id: ActorId(actor),
+            created_frame,
+            name: Some(String::from(name)),
+            remote_id: None,
+            team: Some(team),
+            cars: Vec::new(),
+            boosts: Vec::new(),
+            dodges: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_scorer_prefers_team_and_earlier_join() {
+        let players = vec![
+            player(1, "alpha", 0, 0),
+            player(2, "alpha", 1, 0),
+            player(3, "alpha", 1, 500),
+            player(4, "beta", 1, 0),
+        ];
+
+        let actor = |name, team, frame| scorer(&players, name, team, frame).map(|x| x.actor_id);
+        assert_eq!(actor("alpha", 0, 100), Some(ActorId(1)));
+        assert_eq!(actor("alpha", 1, 100), Some(ActorId(2)));
+        assert_eq!(actor("alpha", 1, 600), Some(ActorId(3)));
+        assert_eq!(actor("gamma", 1, 600), None);
+    }
+}
@@ -1,25 +1,59 @@
+pub use self::actor_state::{ActorState, ActorStateTracker};
 pub(crate) use self::attributes::*;
+pub use self::boost::{boost_stats, BoostPickup, BoostSample, BoostStats, PadSize};
+pub use self::demolitions::{demolitions, Demolition};
+#[cfg(feature = "csv")]
+pub use self::export::{write_frames_csv, write_player_stats_csv};
+pub use self::frame_decoder::FrameIter;
+pub use self::goals::{goal_events, GoalEvent};
 pub use self::models::*;
+pub use self::physics::{physics_timeline, PhysicsActor, PhysicsSample};
+pub use self::players::{OwnedActor, Player, PlayerResolver};
+pub use self::possession::{
+    possession, PlayerPossession, Possession, PossessionFrame, TeamPossession,
+};
+pub use self::probe::{
+    AttributeProber, ProbeCandidate, ProbeFailure, ProbeReport, UnknownAttribute,
+};
+pub use self::tables::ClassTables;
+pub use self::touches::{Touch, TouchDetector};
 
+mod actor_state;
 pub mod attributes;
+mod boost;
+mod demolitions;
+#[cfg(feature = "csv")]
+mod export;
 mod frame_decoder;
+mod frame_encoder;
+mod goals;
 mod models;
+mod parallel;
+mod physics;
+mod players;
+mod possession;
+mod probe;
+mod tables;
+mod touches;
 
-use crate::data::{object_classes, ATTRIBUTES, PARENT_CLASSES, SPAWN_STATS};
-use crate::errors::NetworkError;
+use crate::errors::{NetworkError, WriteError};
 use crate::header::Header;
 use crate::models::*;
+pub(crate) use crate::network::frame_decoder::DecodedFrames;
 use crate::network::frame_decoder::FrameDecoder;
+pub(crate) use crate::network::frame_encoder::EncodedFrames;
+use crate::network::frame_encoder::FrameEncoder;
 use crate::parser::ReplayBody;
 use fnv::FnvHashMap;
+use std::borrow::Cow;
 use std::cmp;
 use std::collections::HashMap;
 use std::ops::Deref;
 
-pub(crate) struct CacheInfo<'a> {
+pub(crate) struct CacheInfo {
     max_prop_id: u32,
     prop_id_bits: i32,
-    attributes: &'a FnvHashMap<StreamId, ObjectAttribute>,
+    attributes: FnvHashMap<StreamId, ObjectAttribute>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -37,10 +71,84 @@ impl VersionTriplet {
     }
 }
 
-pub(crate) fn parse<'a>(
+pub(crate) fn parse(
+    header: &Header,
+    body: &ReplayBody<'_>,
+    tables: &ClassTables,
+    threads: usize,
+    spans: bool,
+) -> Result<DecodedFrames, NetworkError> {
+    frame_iter(header, body, Cow::Borrowed(&body.objects), tables, spans)?.decode_frames(threads)
+}
+
+/// Decodes as many frames as possible, returning the frames decoded prior to an error alongside
+/// the error
+pub(crate) fn parse_partial(
+    header: &Header,
+    body: &ReplayBody<'_>,
+    tables: &ClassTables,
+    threads: usize,
+    spans: bool,
+) -> (DecodedFrames, Option<NetworkError>) {
+    match frame_iter(header, body, Cow::Borrowed(&body.objects), tables, spans) {
+        Ok(iter) => iter.decode_partial(threads),
+        Err(e) => {
+            let network = DecodedFrames {
+                frames: Vec::new(),
+                spans: if spans { Some(Vec::new()) } else { None },
+            };
+            (network, Some(e))
+        }
+    }
+}
+
+/// Prepares the lookup tables needed to decode the network data, returning an iterator that
+/// decodes the frames lazily and, if requested, records where they were decoded from. The objects
+/// are only owned when the iterator outlives the body.
+pub(crate) fn frame_iter<'a>(
     header: &Header,
     body: &ReplayBody<'a>,
-) -> Result<NetworkFrames, NetworkError> {
+    objects: Cow<'a, [String]>,
+    tables: &ClassTables,
+    spans: bool,
+) -> Result<FrameIter<'a>, NetworkError> {
+    let frame_decoder = frame_decoder(header, objects, &body.net_cache, body.network_data, tables)?;
+
+    let iter = if header.num_frames().is_some() {
+        if frame_decoder.frames_len > body.network_data.len() {
+            return Err(NetworkError::TooManyFrames(frame_decoder.frames_len as i32));
+        }
+
+        FrameIter::new(frame_decoder, &body.keyframes)
+    } else {
+        FrameIter::empty(frame_decoder)
+    };
+
+    Ok(if spans { iter.with_bit_spans() } else { iter })
+}
+
+/// Encodes the frames into network data that, when decoded with the same header and body lists,
+/// will yield the same frames
+pub(crate) fn encode(
+    header: &Header,
+    objects: &[String],
+    net_cache: &[ClassNetCache],
+    frames: &[Frame],
+    tables: &ClassTables,
+) -> Result<EncodedFrames, WriteError> {
+    let frame_decoder = frame_decoder(header, Cow::Borrowed(objects), net_cache, &[], tables)
+        .map_err(|e| WriteError::NetworkError(Box::new(e)))?;
+    FrameEncoder::new(frame_decoder).encode(frames)
+}
+
+/// Builds the lookup tables shared by decoding and encoding the network data
+fn frame_decoder<'a>(
+    header: &Header,
+    objects: Cow<'a, [String]>,
+    net_cache: &[ClassNetCache],
+    network_data: &'a [u8],
+    tables: &ClassTables,
+) -> Result<FrameDecoder<'a>, NetworkError> {
     let version = VersionTriplet(
         header.major_version,
         header.minor_version,
@@ -48,19 +156,13 @@ pub(crate) fn parse<'a>(
     );
 
     // Create a parallel vector where each object has it's name normalized
-    let normalized_objects: Vec<&str> = body.objects.iter().map(|x| normalize_object(x)).collect();
+    let normalized_objects: Vec<&str> = objects.iter().map(|x| normalize_object(x)).collect();
 
     // Create a parallel vector where we lookup how to decode an object's initial trajectory
     // when they spawn as a new actor
-    let spawns: Vec<SpawnTrajectory> = body
-        .objects
+    let spawns: Vec<SpawnTrajectory> = objects
         .iter()
-        .map(|x| {
-            SPAWN_STATS
-                .get(x.deref())
-                .cloned()
-                .unwrap_or(SpawnTrajectory::None)
-        })
+        .map(|x| tables.spawn(x).unwrap_or(SpawnTrajectory::None))
         .collect();
 
     // Create a map of an object's normalized name to a list of indices in the object
@@ -75,8 +177,7 @@ pub(crate) fn parse<'a>(
     }
 
     // Map each object's name to it's index
-    let name_obj_ind: HashMap<&str, Vec<ObjectId>> = body
-        .objects
+    let name_obj_ind: HashMap<&str, Vec<ObjectId>> = objects
         .iter()
         .map(|name| {
             (
@@ -91,19 +192,14 @@ pub(crate) fn parse<'a>(
 
     let mut object_ind_attrs: FnvHashMap<ObjectId, FnvHashMap<StreamId, ObjectAttribute>> =
         Default::default();
-    for cache in &body.net_cache {
+    for cache in net_cache {
         let mut all_props: FnvHashMap<StreamId, ObjectAttribute> = cache
             .properties
             .iter()
             .map(|x| {
                 let attr = normalized_objects
                     .get(x.object_ind as usize)
-                    .map(|x| {
-                        ATTRIBUTES
-                            .get(x.deref())
-                            .cloned()
-                            .unwrap_or(AttributeTag::NotImplemented)
-                    })
+                    .map(|x| tables.attribute(x).unwrap_or(AttributeTag::NotImplemented))
                     .ok_or_else(|| NetworkError::StreamTooLargeIndex(x.stream_id, x.object_ind))?;
                 Ok((
                     StreamId(x.stream_id),
@@ -120,12 +216,18 @@ pub(crate) fn parse<'a>(
         // We are going to recursively resolve an object's name to find their direct parent.
         // Parents have parents as well (etc), so we repeatedly walk up the chain picking up
         // attributes on parent objects until we reach an object with no parent (`Core.Object`)
-        let mut object_name: &str = &*body
-            .objects
+        let mut object_name: &str = &*objects
             .get(cache.object_ind as usize)
             .ok_or_else(|| NetworkError::ObjectIdOutOfRange(ObjectId(cache.object_ind)))?;
 
-        while let Some(parent_name) = PARENT_CLASSES.get(object_name) {
+        // Declared parent classes may point back down the chain, which would never terminate
+        let mut visited = vec![object_name];
+        while let Some(parent_name) = tables.parent_class(object_name) {
+            if visited.contains(&parent_name) {
+                return Err(NetworkError::ParentClassCycle(String::from(parent_name)));
+            }
+
+            visited.push(parent_name);
             had_parent = true;
             if let Some(parent_ids) = name_obj_ind.get(parent_name) {
                 for parent_id in parent_ids {
@@ -142,11 +244,7 @@ pub(crate) fn parse<'a>(
         // parent and a parent cache id is set, try and find this parent id and carry down
         // their props.
         if !had_parent && cache.parent_id != 0 {
-            if let Some(parent) = body
-                .net_cache
-                .iter()
-                .find(|x| x.cache_id == cache.parent_id)
-            {
+            if let Some(parent) = net_cache.iter().find(|x| x.cache_id == cache.parent_id) {
                 if let Some(parent_attrs) = object_ind_attrs.get(&ObjectId(parent.object_ind)) {
                     all_props.extend(parent_attrs.iter());
                 }
@@ -156,7 +254,7 @@ pub(crate) fn parse<'a>(
         object_ind_attrs.insert(ObjectId(cache.object_ind), all_props);
     }
 
-    for (obj, parent) in object_classes().iter() {
+    for (obj, parent) in tables.object_classes().iter() {
         // It's ok if an object class doesn't appear in our replay. For instance, basketball
         // objects don't appear in a soccer replay.
         if let Some(object_ids) = normalized_name_obj_ind.get(obj) {
@@ -181,9 +279,8 @@ pub(crate) fn parse<'a>(
     }
 
     let object_ind_attributes: FnvHashMap<ObjectId, CacheInfo> = object_ind_attrs
-        .iter()
-        .map(|(obj_id, attrs)| {
-            let id = *obj_id;
+        .into_iter()
+        .map(|(id, attrs)| {
             let max = attrs.keys().map(|&x| i32::from(x)).max().unwrap_or(2) + 1;
             let max_bit_width = bitter::bit_width(max as u32);
             Ok((
@@ -206,28 +303,19 @@ pub(crate) fn parse<'a>(
     let num_frames = header.num_frames();
     let is_lan = header.match_type().map(|x| x == "Lan").unwrap_or(false);
 
-    if let Some(frame_len) = num_frames {
-        if frame_len as usize > body.network_data.len() {
-            return Err(NetworkError::TooManyFrames(frame_len));
-        }
-
-        let frame_decoder = FrameDecoder {
-            frames_len: frame_len as usize,
-            product_decoder,
-            max_channels,
-            channel_bits,
-            body,
-            spawns: &spawns,
-            object_ind_attributes,
-            version,
-            is_lan,
-        };
-        Ok(NetworkFrames {
-            frames: frame_decoder.decode_frames()?,
-        })
-    } else {
-        Ok(NetworkFrames { frames: Vec::new() })
-    }
+    Ok(FrameDecoder {
+        frames_len: num_frames.unwrap_or(0) as usize,
+        product_decoder,
+        max_channels,
+        channel_bits,
+        network_data,
+        objects,
+        spawns,
+        object_ind_attributes,
+        version,
+        is_lan,
+        record_spans: false,
+    })
 }
 
 #[cfg(test)]
@@ -1,8 +1,9 @@
+use crate::bit_writer::BitWriter;
 use crate::network::attributes::Attribute;
 use bitter::BitGet;
 use std::fmt;
 
-#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub struct Vector3f {
     pub x: f32,
     pub y: f32,
@@ -10,6 +11,30 @@ pub struct Vector3f {
 }
 
 impl Vector3f {
+    /// The length of the vector (eg: the speed of a velocity)
+    pub fn magnitude(&self) -> f32 {
+        (self.x * self.x + self.y * self.y + self.z * self.z).sqrt()
+    }
+
+    /// The straight line distance between two points
+    pub fn distance(&self, other: &Vector3f) -> f32 {
+        Vector3f {
+            x: self.x - other.x,
+            y: self.y - other.y,
+            z: self.z - other.z,
+        }
+        .magnitude()
+    }
+
+    /// Multiplies each component by the factor (eg: to convert to unreal units)
+    pub fn scale(&self, factor: f32) -> Vector3f {
+        Vector3f {
+            x: self.x * factor,
+            y: self.y * factor,
+            z: self.z * factor,
+        }
+    }
+
     pub fn decode(bits: &mut BitGet<'_>, net_version: i32) -> Option<Vector3f> {
         Vector3i::decode(bits, net_version).map(|vec| Vector3f {
             x: (vec.x as f32) / 100.0,
@@ -17,10 +42,19 @@ impl Vector3f {
             z: (vec.z as f32) / 100.0,
         })
     }
+
+    pub(crate) fn encode(&self, bits: &mut BitWriter, net_version: i32) {
+        Vector3i {
+            x: (self.x * 100.0).round() as i32,
+            y: (self.y * 100.0).round() as i32,
+            z: (self.z * 100.0).round() as i32,
+        }
+        .encode(bits, net_version)
+    }
 }
 
 /// An object's current vector
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Vector3i {
     pub x: i32,
     pub y: i32,
@@ -62,9 +96,30 @@ impl Vector3i {
             z: (dz as i32) - bias,
         }
     }
+
+    pub(crate) fn encode(&self, bits: &mut BitWriter, net_version: i32) {
+        let max_size_bits = if net_version >= 7 { 22 } else { 20 };
+
+        // Find the smallest size that can represent all components once biased
+        let largest = i64::from(self.x.max(self.y).max(self.z));
+        let smallest = i64::from(self.x.min(self.y).min(self.z));
+        let size_bits = (0..max_size_bits - 1)
+            .find(|size_bits| {
+                let bias = 1i64 << (size_bits + 1);
+                smallest >= -bias && largest < bias
+            })
+            .unwrap_or(max_size_bits - 1);
+
+        let bias = 1i64 << (size_bits + 1);
+        let bit_limit = (size_bits + 2) as i32;
+        bits.write_bits_max_computed(4, max_size_bits, size_bits);
+        bits.write_u32_bits((i64::from(self.x) + bias) as u32, bit_limit);
+        bits.write_u32_bits((i64::from(self.y) + bias) as u32, bit_limit);
+        bits.write_u32_bits((i64::from(self.z) + bias) as u32, bit_limit);
+    }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub struct Quaternion {
     pub x: f32,
     pub y: f32,
@@ -72,7 +127,87 @@ pub struct Quaternion {
     pub w: f32,
 }
 
+/// A rotation expressed as yaw (about the z axis), pitch (about the y axis), and roll (about the x
+/// axis) in radians.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct EulerAngles {
+    pub yaw: f32,
+    pub pitch: f32,
+    pub roll: f32,
+}
+
 impl Quaternion {
+    /// Converts the quaternion into yaw, pitch, and roll, where the rotations are applied in
+    /// that order. At the singularities (pitch of +/- 90 degrees) the pitch is clamped.
+    ///
+    /// ```
+    /// # use boxcars::Quaternion;
+    /// let quat = Quaternion {
+    ///     x: 0.0,
+    ///     y: 0.0,
+    ///     z: std::f32::consts::FRAC_1_SQRT_2,
+    ///     w: std::f32::consts::FRAC_1_SQRT_2,
+    /// };
+    ///
+    /// let euler = quat.to_euler();
+    /// assert!((euler.yaw - std::f32::consts::FRAC_PI_2).abs() < 1e-6);
+    /// assert!(euler.pitch.abs() < 1e-6);
+    /// assert!(euler.roll.abs() < 1e-6);
+    /// ```
+    pub fn to_euler(&self) -> EulerAngles {
+        let Quaternion { x, y, z, w } = *self;
+        let roll = (2.0 * (w * x + y * z)).atan2(1.0 - 2.0 * (x * x + y * y));
+        let pitch = (2.0 * (w * y - z * x)).clamp(-1.0, 1.0).asin();
+        let yaw = (2.0 * (w * z + x * y)).atan2(1.0 - 2.0 * (y * y + z * z));
+        EulerAngles { yaw, pitch, roll }
+    }
+
+    /// Spherical linear interpolation between two rotations, where `t` of 0 is `self` and 1 is
+    /// `other`. The shortest path between the two is taken.
+    pub fn slerp(&self, other: &Quaternion, t: f32) -> Quaternion {
+        let mut dot = self.x * other.x + self.y * other.y + self.z * other.z + self.w * other.w;
+
+        // q and -q are the same rotation, so flip one to take the shorter path
+        let sign = if dot < 0.0 {
+            dot = -dot;
+            -1.0
+        } else {
+            1.0
+        };
+
+        // When the rotations are nearly identical, fall back to a linear interpolation to avoid
+        // dividing by zero
+        let (a, b) = if dot > 0.9995 {
+            (1.0 - t, t)
+        } else {
+            let theta = dot.min(1.0).acos();
+            let sin_theta = theta.sin();
+            (
+                ((1.0 - t) * theta).sin() / sin_theta,
+                (t * theta).sin() / sin_theta,
+            )
+        };
+
+        let b = b * sign;
+        let result = Quaternion {
+            x: a * self.x + b * other.x,
+            y: a * self.y + b * other.y,
+            z: a * self.z + b * other.z,
+            w: a * self.w + b * other.w,
+        };
+
+        let norm =
+            (result.x * result.x + result.y * result.y + result.z * result.z + result.w * result.w)
+                .sqrt();
+
+        Quaternion {
+            x: result.x / norm,
+            y: result.y / norm,
+            z: result.z / norm,
+            w: result.w / norm,
+        }
+    }
+
     fn unpack(val: u32) -> f32 {
         let max_quat = 1.0 / std::f32::consts::SQRT_2;
         let max_value = (1 << 18) - 1;
@@ -81,6 +216,14 @@ impl Quaternion {
         range * max_quat
     }
 
+    fn pack(val: f32) -> u32 {
+        let max_quat = 1.0 / std::f32::consts::SQRT_2;
+        let max_value = (1 << 18) - 1;
+        let pos_range = (val / max_quat) / 2.0 + 0.5;
+        let packed = (pos_range * (max_value as f32)).round();
+        (packed.max(0.0) as u32).min(max_value)
+    }
+
     fn compressed_f32(bits: &mut BitGet<'_>) -> Option<f32> {
         // algorithm from jjbott/RocketLeagueReplayParser.
         // Note that this code is heavily adapted. I noticed that there were branches that should
@@ -100,6 +243,11 @@ impl Quaternion {
             .map(|x| x as f32 * (std::i16::MAX as f32).recip())
     }
 
+    fn encode_compressed_f32(val: f32, bits: &mut BitWriter) {
+        let x = (val * f32::from(i16::MAX)).round() as i32 - i32::from(i16::MIN);
+        bits.write_u16(x.max(0).min(i32::from(u16::MAX)) as u16);
+    }
+
     pub fn decode_compressed(bits: &mut BitGet<'_>) -> Option<Self> {
         if_chain! {
             if let Some(x) = Quaternion::compressed_f32(bits);
@@ -116,47 +264,101 @@ impl Quaternion {
     pub fn decode(bits: &mut BitGet<'_>) -> Option<Self> {
         if_chain! {
             if let Some(largest) = bits.read_u32_bits(2);
-            if let Some(a) = bits.read_u32_bits(18).map(Quaternion::unpack);
-            if let Some(b) = bits.read_u32_bits(18).map(Quaternion::unpack);
-            if let Some(c) = bits.read_u32_bits(18).map(Quaternion::unpack);
-            let extra = (1.0 - (a * a) - (b * b) - (c * c)).sqrt();
+            if let Some(a) = bits.read_u32_bits(18);
+            if let Some(b) = bits.read_u32_bits(18);
+            if let Some(c) = bits.read_u32_bits(18);
             then {
-                match largest {
-                    0 => Some(Quaternion {
-                        x: extra,
-                        y: a,
-                        z: b,
-                        w: c,
-                    }),
-                    1 => Some(Quaternion {
-                        x: a,
-                        y: extra,
-                        z: b,
-                        w: c,
-                    }),
-                    2 => Some(Quaternion {
-                        x: a,
-                        y: b,
-                        z: extra,
-                        w: c,
-                    }),
-                    3 => Some(Quaternion {
-                        x: a,
-                        y: b,
-                        z: c,
-                        w: extra,
-                    }),
-                    _ => unreachable!(),
-                }
+                Some(Quaternion::from_largest(largest, a, b, c))
             } else {
                 None
             }
         }
     }
+
+    fn from_largest(largest: u32, a: u32, b: u32, c: u32) -> Self {
+        let a = Quaternion::unpack(a);
+        let b = Quaternion::unpack(b);
+        let c = Quaternion::unpack(c);
+        let extra = (1.0 - (a * a) - (b * b) - (c * c)).sqrt();
+        match largest {
+            0 => Quaternion {
+                x: extra,
+                y: a,
+                z: b,
+                w: c,
+            },
+            1 => Quaternion {
+                x: a,
+                y: extra,
+                z: b,
+                w: c,
+            },
+            2 => Quaternion {
+                x: a,
+                y: b,
+                z: extra,
+                w: c,
+            },
+            3 => Quaternion {
+                x: a,
+                y: b,
+                z: c,
+                w: extra,
+            },
+            _ => unreachable!(),
+        }
+    }
+
+    pub(crate) fn encode_compressed(&self, bits: &mut BitWriter) {
+        Quaternion::encode_compressed_f32(self.x, bits);
+        Quaternion::encode_compressed_f32(self.y, bits);
+        Quaternion::encode_compressed_f32(self.z, bits);
+    }
+
+    /// The largest component is omitted from the encoding and is derived from the other three
+    /// when decoding. Since the packed components are lossy, look for the omitted component that
+    /// decodes back into the exact same quaternion, else fall back to the component with the
+    /// largest magnitude.
+    pub(crate) fn encode(&self, bits: &mut BitWriter) {
+        let components = [self.x, self.y, self.z, self.w];
+        let packed_without = |largest: usize| {
+            let mut packed = components
+                .iter()
+                .enumerate()
+                .filter(|&(i, _)| i != largest)
+                .map(|(_, &x)| Quaternion::pack(x));
+            (
+                packed.next().unwrap_or(0),
+                packed.next().unwrap_or(0),
+                packed.next().unwrap_or(0),
+            )
+        };
+
+        let largest = (0..4)
+            .find(|&i| {
+                let (a, b, c) = packed_without(i);
+                Quaternion::from_largest(i as u32, a, b, c) == *self
+            })
+            .or_else(|| {
+                (0..4).max_by(|&i, &j| {
+                    components[i]
+                        .abs()
+                        .partial_cmp(&components[j].abs())
+                        .unwrap_or(std::cmp::Ordering::Equal)
+                })
+            })
+            .unwrap_or(0);
+
+        let (a, b, c) = packed_without(largest);
+        bits.write_u32_bits(largest as u32, 2);
+        bits.write_u32_bits(a, 18);
+        bits.write_u32_bits(b, 18);
+        bits.write_u32_bits(c, 18);
+    }
 }
 
 /// An object's current rotation
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Rotation {
     pub yaw: Option<i8>,
     pub pitch: Option<i8>,
@@ -187,11 +389,17 @@ impl Rotation {
         let roll = bits.if_get_unchecked(BitGet::read_i8_unchecked);
         Rotation { yaw, pitch, roll }
     }
+
+    pub(crate) fn encode(&self, bits: &mut BitWriter) {
+        bits.if_write(self.yaw, BitWriter::write_i8);
+        bits.if_write(self.pitch, BitWriter::write_i8);
+        bits.if_write(self.roll, BitWriter::write_i8);
+    }
 }
 
 /// When a new actor spawns in rocket league it will either have a location, location and rotation,
 /// or none of the above
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
 pub enum SpawnTrajectory {
     None,
     Location,
@@ -200,7 +408,7 @@ pub enum SpawnTrajectory {
 
 /// Notifies that an actor has had one of their properties updated (most likely their rigid body
 /// state (location / rotation) has changed)
-#[derive(Debug, Clone, PartialEq, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct UpdatedAttribute {
     /// The actor that had an attribute updated
     pub actor_id: ActorId,
@@ -216,7 +424,7 @@ pub struct UpdatedAttribute {
 }
 
 /// Contains the time and any new information that occurred during a frame
-#[derive(Debug, Clone, PartialEq, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Frame {
     /// The time in seconds that the frame is recorded at
     pub time: f32,
@@ -234,10 +442,31 @@ pub struct Frame {
     pub updated_actors: Vec<UpdatedAttribute>,
 }
 
+/// A range of bits in the network data, where the end is exclusive
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BitSpan {
+    pub start: usize,
+    pub end: usize,
+}
+
+/// Where a frame and the items within it were decoded from in the network data. Only recorded
+/// when requested with `ParserBuilder::parse_with_bit_spans` or `FrameIter::with_bit_spans`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FrameSpans {
+    /// The entire frame, from its time to the bit that ends the list of actors
+    pub frame: BitSpan,
+
+    /// Parallel to `Frame::new_actors`: the name id, object id, and initial trajectory
+    pub new_actors: Vec<BitSpan>,
+
+    /// Parallel to `Frame::updated_actors`: the attribute's payload, which follows its stream id
+    pub updated_actors: Vec<BitSpan>,
+}
+
 /// A replay encodes a list of objects that appear in the network data. The index of an object in
 /// this list is used as a key in many places: reconstructing the attribute hierarchy and new
 /// actors in the network data.
-#[derive(Clone, Copy, PartialEq, PartialOrd, Eq, Ord, Debug, Hash, Serialize)]
+#[derive(Clone, Copy, PartialEq, PartialOrd, Eq, Ord, Debug, Hash, Serialize, Deserialize)]
 pub struct ObjectId(pub i32);
 
 impl From<ObjectId> for i32 {
@@ -261,7 +490,7 @@ impl fmt::Display for ObjectId {
 /// A `StreamId` is an attribute's object id in the network data. It is a more compressed form of
 /// the object id. Whereas the an object id might need to take up 9 bits, a stream id may only take
 /// up 6 bits.
-#[derive(Clone, Copy, PartialEq, PartialOrd, Eq, Ord, Debug, Hash, Serialize)]
+#[derive(Clone, Copy, PartialEq, PartialOrd, Eq, Ord, Debug, Hash, Serialize, Deserialize)]
 pub struct StreamId(pub i32);
 
 impl From<StreamId> for i32 {
@@ -278,7 +507,7 @@ impl fmt::Display for StreamId {
 
 /// An actor in the network data stream. Could identify a ball, car, etc. Ids are not unique
 /// across a replay (eg. an actor that is destroyed may have its id repurposed).
-#[derive(Clone, Copy, PartialEq, PartialOrd, Eq, Ord, Debug, Hash, Serialize)]
+#[derive(Clone, Copy, PartialEq, PartialOrd, Eq, Ord, Debug, Hash, Serialize, Deserialize)]
 pub struct ActorId(pub i32);
 
 impl From<ActorId> for i32 {
@@ -294,7 +523,7 @@ impl fmt::Display for ActorId {
 }
 
 /// Information for a new actor that appears in the game
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub struct NewActor {
     /// The id given to the new actor
     pub actor_id: ActorId,
@@ -310,7 +539,7 @@ pub struct NewActor {
 }
 
 /// Contains the optional location and rotation of an object when it spawns
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Trajectory {
     pub location: Option<Vector3i>,
     pub rotation: Option<Rotation>,
@@ -370,6 +599,16 @@ impl Trajectory {
             },
         }
     }
+
+    pub(crate) fn encode(&self, bits: &mut BitWriter, net_version: i32) {
+        if let Some(location) = self.location {
+            location.encode(bits, net_version);
+        }
+
+        if let Some(rotation) = self.rotation {
+            rotation.encode(bits);
+        }
+    }
 }
 
 /// Oftentimes a replay contains many different objects of the same type. For instance, each rumble
@@ -438,4 +677,139 @@ mod tests {
             }
         );
     }
+
+    #[test]
+    fn test_encode_vector() {
+        let vectors = [
+            Vector3i { x: 0, y: 0, z: 93 },
+            Vector3i { x: -1, y: 1, z: 0 },
+            Vector3i {
+                x: -400_000,
+                y: 1_234,
+                z: 524_287,
+            },
+        ];
+
+        let mut writer = BitWriter::new();
+        for v in &vectors {
+            v.encode(&mut writer, 5);
+            v.encode(&mut writer, 7);
+        }
+
+        let data = writer.into_bytes();
+        let mut bitter = BitGet::new(&data[..]);
+        for v in &vectors {
+            assert_eq!(Vector3i::decode(&mut bitter, 5).as_ref(), Some(v));
+            assert_eq!(Vector3i::decode(&mut bitter, 7).as_ref(), Some(v));
+        }
+    }
+
+    #[test]
+    fn test_encode_vector_matches_decode() {
+        let mut writer = BitWriter::new();
+        Vector3i { x: 0, y: 0, z: 93 }.encode(&mut writer, 5);
+        assert_eq!(
+            writer.into_bytes(),
+            vec![0b0000_0110, 0b0000_1000, 0b1101_1000, 0b0000_1101]
+        );
+    }
+
+    #[test]
+    fn test_encode_quaternion() {
+        let mut writer = BitWriter::new();
+        for &(largest, a, b, c) in &[
+            (0, 131_071, 131_071, 131_071),
+            (1, 100_000, 131_000, 160_000),
+            (3, 140_000, 120_000, 150_000),
+        ] {
+            writer.write_u32_bits(largest, 2);
+            writer.write_u32_bits(a, 18);
+            writer.write_u32_bits(b, 18);
+            writer.write_u32_bits(c, 18);
+        }
+
+        let data = writer.into_bytes();
+        let mut bitter = BitGet::new(&data[..]);
+        let quats: Vec<_> = (0..3)
+            .map(|_| Quaternion::decode(&mut bitter).unwrap())
+            .collect();
+
+        let mut writer = BitWriter::new();
+        for q in &quats {
+            q.encode(&mut writer);
+            q.encode_compressed(&mut writer);
+        }
+
+        let data = writer.into_bytes();
+        let mut bitter = BitGet::new(&data[..]);
+        for q in &quats {
+            assert_eq!(Quaternion::decode(&mut bitter).as_ref(), Some(q));
+            let compressed = Quaternion::decode_compressed(&mut bitter).unwrap();
+            assert!((compressed.x - q.x).abs() < 1e-4);
+            assert!((compressed.y - q.y).abs() < 1e-4);
+            assert!((compressed.z - q.z).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn test_quaternion_to_euler() {
+        let half = std::f32::consts::FRAC_1_SQRT_2;
+        let pitch = Quaternion {
+            x: 0.0,
+            y: half,
+            z: 0.0,
+            w: half,
+        }
+        .to_euler();
+        assert!((pitch.pitch - std::f32::consts::FRAC_PI_2).abs() < 1e-3);
+
+        let roll = Quaternion {
+            x: half,
+            y: 0.0,
+            z: 0.0,
+            w: half,
+        }
+        .to_euler();
+        assert!((roll.roll - std::f32::consts::FRAC_PI_2).abs() < 1e-6);
+        assert!(roll.yaw.abs() < 1e-6);
+        assert!(roll.pitch.abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_quaternion_slerp() {
+        let identity = Quaternion {
+            x: 0.0,
+            y: 0.0,
+            z: 0.0,
+            w: 1.0,
+        };
+
+        // A 180 degree yaw, expressed with a negative w so that the shortest path is taken
+        let turned = Quaternion {
+            x: 0.0,
+            y: 0.0,
+            z: -1.0,
+            w: 0.0,
+        };
+
+        let half = identity.slerp(&turned, 0.5);
+        let euler = half.to_euler();
+        assert!((euler.yaw.abs() - std::f32::consts::FRAC_PI_2).abs() < 1e-5);
+
+        let end = identity.slerp(&turned, 1.0).to_euler();
+        assert!((end.yaw.abs() - std::f32::consts::PI).abs() < 1e-5);
+        assert_eq!(identity.slerp(&identity, 0.3), identity);
+    }
+
+    #[test]
+    fn test_encode_rotation() {
+        let mut writer = BitWriter::new();
+        Rotation {
+            yaw: Some(2),
+            pitch: None,
+            roll: None,
+        }
+        .encode(&mut writer);
+        assert_eq!(writer.into_bytes(), vec![0b0000_0101, 0b0000_0000]);
+    }
 }
@@ -0,0 +1,240 @@
+//! Decodes the network data on multiple threads.
+//!
+//! Keyframes record the bit position where a frame starts, so the network data can be split at
+//! keyframes and each chunk decoded independently. The only state carried between frames is the
+//! actor table (which object an actor is an instance of) and it isn't known at a split point
+//! without decoding every frame before it. Instead of a serial pre-pass to build the actor table at
+//! each split point, which would decode every frame twice, a chunk is decoded speculatively with an
+//! empty actor table. The speculative decode either matches the serial decode or fails with a
+//! missing actor: the table is only consulted to find the object of an updated actor, and any
+//! actor found was announced within the chunk, so the serial table has the same object for it.
+//! Nothing relies on keyframes re-announcing the live actors for correctness, though when they do,
+//! as is typical, the speculative decode succeeds and the chunk isn't decoded again.
+//!
+//! The chunks are then stitched together in order while the true actor table is carried along.
+//! Any chunk that failed speculatively, or doesn't line up with where the previous chunk ended, is
+//! decoded again from where the previous chunk ended with the true actor table. The output is thus
+//! identical to the serial decode, including errors.
+use crate::errors::NetworkError;
+use crate::models::KeyFrame;
+use crate::network::attributes::AttributeDecoder;
+use crate::network::frame_decoder::{bits_at, DecodedFrame, DecodedFrames, FrameDecoder};
+use crate::network::models::{ActorId, Frame, FrameSpans, ObjectId};
+use fnv::FnvHashMap;
+use std::panic;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+/// A frame and the bit position in the network data where it starts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct SplitPoint {
+    frame: usize,
+    position: usize,
+}
+
+/// The result of decoding a chunk of frames
+struct Chunk {
+    frames: Vec<Frame>,
+
+    /// Parallel to the frames, when recording spans
+    spans: Vec<FrameSpans>,
+
+    /// Where the frame after the chunk starts
+    end: usize,
+
+    /// The actor table at the end of the chunk. Actors that weren't announced within the chunk
+    /// are only present if the chunk was seeded with them.
+    actors: FnvHashMap<ActorId, ObjectId>,
+
+    /// True if the network data ended within the chunk
+    finished: bool,
+    error: Option<NetworkError>,
+}
+
+/// Decodes frames from the starting point until the frame limit (exclusive) or the end of the
+/// network data. Mirrors the serial `FrameIter`.
+fn decode_chunk(
+    decoder: &FrameDecoder<'_>,
+    attr_decoder: &AttributeDecoder,
+    start: SplitPoint,
+    limit: Option<usize>,
+    mut actors: FnvHashMap<ActorId, ObjectId>,
+) -> Chunk {
+    let mut frames = Vec::with_capacity(limit.unwrap_or(decoder.frames_len) - start.frame);
+    let mut spans = Vec::new();
+    let mut new_actors = Vec::new();
+    let mut deleted_actors = Vec::new();
+    let mut updated_actors = Vec::new();
+
+    let mut bits = match bits_at(decoder.network_data, start.position) {
+        Some(bits) => bits,
+        None => {
+            return Chunk {
+                frames,
+                spans,
+                end: start.position,
+                actors,
+                finished: true,
+                error: Some(NetworkError::NotEnoughDataFor("Keyframe")),
+            }
+        }
+    };
+
+    let (finished, error) = loop {
+        if limit.filter(|&x| start.frame + frames.len() >= x).is_some() {
+            break (false, None);
+        }
+
+        if bits.is_empty() || start.frame + frames.len() >= decoder.frames_len {
+            break (true, decoder.decode_trailer(&mut bits).err());
+        }
+
+        let frame = decoder.decode_frame(
+            attr_decoder,
+            &mut bits,
+            &mut actors,
+            &mut new_actors,
+            &mut deleted_actors,
+            &mut updated_actors,
+        );
+
+        match frame {
+            Ok(DecodedFrame::EndFrame) => break (true, decoder.decode_trailer(&mut bits).err()),
+            Ok(DecodedFrame::Frame(frame, frame_spans)) => {
+                frames.push(frame);
+                spans.extend(frame_spans);
+            }
+            Err(e) => {
+                let frame = start.frame + frames.len();
+                let context = decoder.context(&actors, &new_actors, &updated_actors, frame, &bits);
+                break (true, Some(NetworkError::FrameError(e, Box::new(context))));
+            }
+        }
+    };
+
+    Chunk {
+        frames,
+        spans,
+        end: decoder.position(&bits),
+        actors,
+        finished,
+        error,
+    }
+}
+
+/// Speculatively decodes every chunk with an empty actor table across the given number of threads
+fn decode_chunks(
+    decoder: &FrameDecoder<'_>,
+    splits: &[SplitPoint],
+    threads: usize,
+) -> Vec<Option<Chunk>> {
+    let next = AtomicUsize::new(0);
+    let mut decoded: Vec<(usize, Chunk)> = thread::scope(|s| {
+        let handles: Vec<_> = (0..threads.min(splits.len()))
+            .map(|_| {
+                s.spawn(|| {
+                    let attr_decoder =
+                        AttributeDecoder::new(decoder.version, decoder.product_decoder);
+                    let mut result = Vec::new();
+                    loop {
+                        let i = next.fetch_add(1, Ordering::Relaxed);
+                        if i >= splits.len() {
+                            break result;
+                        }
+
+                        let limit = splits.get(i + 1).map(|x| x.frame);
+                        let chunk = decode_chunk(
+                            decoder,
+                            &attr_decoder,
+                            splits[i],
+                            limit,
+                            FnvHashMap::default(),
+                        );
+                        result.push((i, chunk));
+                    }
+                })
+            })
+            .collect();
+
+        // Propagate a worker's panic as the serial decode would have panicked too
+        handles
+            .into_iter()
+            .flat_map(|x| x.join().unwrap_or_else(|e| panic::resume_unwind(e)))
+            .collect()
+    });
+
+    let mut result: Vec<Option<Chunk>> = (0..splits.len()).map(|_| None).collect();
+    for (i, chunk) in decoded.drain(..) {
+        result[i] = Some(chunk);
+    }
+    result
+}
+
+/// Decodes the network data in parallel by splitting it at the given keyframes, which are assumed
+/// to be valid. Returns the frames decoded prior to an error alongside the error, exactly as the
+/// serial decode would.
+pub(crate) fn decode_partial(
+    decoder: &FrameDecoder<'_>,
+    keyframes: &[KeyFrame],
+    threads: usize,
+) -> (DecodedFrames, Option<NetworkError>) {
+    let splits: Vec<SplitPoint> = keyframes
+        .iter()
+        .map(|x| SplitPoint {
+            frame: x.frame as usize,
+            position: x.position as usize,
+        })
+        .collect();
+    let mut chunks = decode_chunks(decoder, &splits, threads);
+
+    let attr_decoder = AttributeDecoder::new(decoder.version, decoder.product_decoder);
+    let mut network = DecodedFrames {
+        frames: Vec::with_capacity(decoder.frames_len),
+        spans: if decoder.record_spans {
+            Some(Vec::with_capacity(decoder.frames_len))
+        } else {
+            None
+        },
+    };
+    let mut actors: FnvHashMap<ActorId, ObjectId> = FnvHashMap::default();
+    let mut position = 0;
+
+    for (i, split) in splits.iter().enumerate() {
+        let cursor = SplitPoint {
+            frame: network.frames.len(),
+            position,
+        };
+
+        let chunk = match chunks[i].take() {
+            Some(chunk) if chunk.error.is_none() && *split == cursor => {
+                // The chunk only knows of actors it has seen, so carry over the rest of the
+                // actors that are still alive
+                for frame in &chunk.frames {
+                    for deleted in &frame.deleted_actors {
+                        actors.remove(deleted);
+                    }
+                }
+                actors.extend(chunk.actors.iter());
+                chunk
+            }
+            _ => {
+                let limit = splits.get(i + 1).map(|x| x.frame);
+                let chunk = decode_chunk(decoder, &attr_decoder, cursor, limit, actors);
+                actors = chunk.actors.clone();
+                chunk
+            }
+        };
+
+        position = chunk.end;
+        network.frames.extend(chunk.frames);
+        if let Some(spans) = network.spans.as_mut() {
+            spans.extend(chunk.spans);
+        }
+
+        if chunk.finished || chunk.error.is_some() {
+            return (network, chunk.error);
+        }
+    }
+
+    (network, None)
+}
@@ -0,0 +1,226 @@
+use crate::errors::FrameError;
+use crate::network::actor_state::ActorStateTracker;
+use crate::network::attributes::{Attribute, RigidBody};
+use crate::network::models::{ActorId, EulerAngles, Frame, Vector3f};
+use crate::network::touches::unreal_units;
+use crate::network::ClassTables;
+use fnv::FnvHashMap;
+
+const CAR_CLASS: &str = "TAGame.Car_TA";
+const BALL_CLASS_PREFIX: &str = "TAGame.Ball_";
+
+/// The type of actor whose physics are tracked
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum PhysicsActor {
+    Ball,
+    Car,
+}
+
+/// The physics of an actor at a frame
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct PhysicsSample {
+    /// The index of the frame
+    pub frame: usize,
+
+    /// The frame's time in seconds
+    pub time: f32,
+    pub actor_id: ActorId,
+    pub actor: PhysicsActor,
+
+    /// The rigid body with its location and linear velocity in unreal units, whatever the network
+    /// version of the replay
+    pub rigid_body: RigidBody,
+
+    /// The rigid body's rotation converted to yaw, pitch, and roll
+    pub euler: EulerAngles,
+
+    /// True if the rigid body was not updated in this frame and was instead derived from the
+    /// surrounding updates
+    pub interpolated: bool,
+}
+
+/// All the rigid body updates of an actor's lifetime
+struct Track {
+    actor_id: ActorId,
+    actor: PhysicsActor,
+    created_frame: usize,
+    deleted_frame: Option<usize>,
+    updates: Vec<(usize, RigidBody)>,
+}
+
+/// Produces a dense time series of the ball and car physics, one sample per actor per frame.
+///
+/// The network data only contains rigid body updates when they change, so frames between two
+/// updates are linearly interpolated by frame time (with rotations spherically interpolated). A
+/// sleeping rigid body doesn't move, so it is carried forward until the next update, as is the
+/// last update until the actor is deleted. Frames before an actor's first update are omitted.
+///
+/// Older replays replicate rigid bodies in smaller units, so the network version of the replay
+/// (see `Replay::net_version`) is used to convert locations and linear velocities to unreal units.
+///
+/// Samples are ordered by frame and then by actor id. The ball and cars are recognized through the
+/// tables the replay was parsed with.
+///
+/// ```
+/// use boxcars::{physics_timeline, ClassTables, ParserBuilder, PhysicsActor};
+///
+/// let data = include_bytes!("../../assets/replays/good/rumble.replay");
+/// let replay = ParserBuilder::new(&data[..])
+///     .must_parse_network_data()
+///     .parse()
+///     .unwrap();
+///
+/// let frames = &replay.network_frames.as_ref().unwrap().frames;
+/// let tables = ClassTables::default();
+/// let samples = physics_timeline(&replay.objects, &tables, replay.net_version, frames).unwrap();
+/// let ball = samples.iter().find(|x| x.actor == PhysicsActor::Ball).unwrap();
+/// assert!(ball.rigid_body.location.z > 0.0);
+/// ```
+pub fn physics_timeline(
+    objects: &[String],
+    tables: &ClassTables,
+    net_version: Option<i32>,
+    frames: &[Frame],
+) -> Result<Vec<PhysicsSample>, FrameError> {
+    let (location_scale, velocity_scale) = unreal_units(net_version);
+    let mut tracker = ActorStateTracker::new(objects, tables);
+    let mut tracks: Vec<Track> = Vec::new();
+    let mut live_tracks: FnvHashMap<ActorId, usize> = FnvHashMap::default();
+
+    for (frame_ind, frame) in frames.iter().enumerate() {
+        tracker.process_frame(frame)?;
+
+        for actor_id in &frame.deleted_actors {
+            if let Some(ind) = live_tracks.remove(actor_id) {
+                tracks[ind].deleted_frame = Some(frame_ind);
+            }
+        }
+
+        for new_actor in &frame.new_actors {
+            let actor = match tracker.actor(new_actor.actor_id) {
+                Some(actor) if actor.created_frame == frame_ind => actor,
+                _ => continue,
+            };
+
+            if let Some(ind) = live_tracks.remove(&new_actor.actor_id) {
+                tracks[ind].deleted_frame = Some(frame_ind);
+            }
+
+            let kind = if actor.is_a(CAR_CLASS) {
+                PhysicsActor::Car
+            } else if actor
+                .class_name
+                .filter(|x| x.starts_with(BALL_CLASS_PREFIX))
+                .is_some()
+            {
+                PhysicsActor::Ball
+            } else {
+                continue;
+            };
+
+            live_tracks.insert(new_actor.actor_id, tracks.len());
+            tracks.push(Track {
+                actor_id: new_actor.actor_id,
+                actor: kind,
+                created_frame: frame_ind,
+                deleted_frame: None,
+                updates: Vec::new(),
+            });
+        }
+
+        for update in &frame.updated_actors {
+            if let Attribute::RigidBody(body) = update.attribute {
+                if let Some(&ind) = live_tracks.get(&update.actor_id) {
+                    let updates = &mut tracks[ind].updates;
+
+                    // Only the last update of a frame is kept
+                    if updates.last().map(|&(x, _)| x) == Some(frame_ind) {
+                        updates.pop();
+                    }
+                    let body = RigidBody {
+                        location: body.location.scale(location_scale),
+                        linear_velocity: body.linear_velocity.map(|x| x.scale(velocity_scale)),
+                        ..body
+                    };
+                    updates.push((frame_ind, body));
+                }
+            }
+        }
+    }
+
+    let mut samples = Vec::new();
+    for track in &tracks {
+        sample_track(track, frames, &mut samples);
+    }
+
+    samples.sort_by_key(|x| (x.frame, x.actor_id));
+    Ok(samples)
+}
+
+fn sample_track(track: &Track, frames: &[Frame], samples: &mut Vec<PhysicsSample>) {
+    let end = track.deleted_frame.unwrap_or(frames.len());
+    let mut sample = |frame: usize, rigid_body: RigidBody, interpolated: bool| {
+        samples.push(PhysicsSample {
+            frame,
+            time: frames[frame].time,
+            actor_id: track.actor_id,
+            actor: track.actor,
+            rigid_body,
+            euler: rigid_body.rotation.to_euler(),
+            interpolated,
+        })
+    };
+
+    for (i, &(frame, body)) in track.updates.iter().enumerate() {
+        debug_assert!(frame >= track.created_frame);
+        sample(frame, body, false);
+
+        let (next_frame, next) = match track.updates.get(i + 1) {
+            Some(&(next_frame, next)) => (next_frame, Some(next)),
+            None => (end, None),
+        };
+
+        for between in frame + 1..next_frame {
+            let body = match next {
+                Some(next) if !body.sleeping => {
+                    let start = frames[frame].time;
+                    let span = frames[next_frame].time - start;
+                    let t = if span > 0.0 {
+                        (frames[between].time - start) / span
+                    } else {
+                        0.0
+                    };
+                    interpolate(&body, &next, t)
+                }
+                _ => body,
+            };
+
+            sample(between, body, true);
+        }
+    }
+}
+
+fn interpolate(a: &RigidBody, b: &RigidBody, t: f32) -> RigidBody {
+    RigidBody {
+        sleeping: a.sleeping,
+        location: lerp(&a.location, &b.location, t),
+        rotation: a.rotation.slerp(&b.rotation, t),
+        linear_velocity: lerp_opt(a.linear_velocity, b.linear_velocity, t),
+        angular_velocity: lerp_opt(a.angular_velocity, b.angular_velocity, t),
+    }
+}
+
+fn lerp(a: &Vector3f, b: &Vector3f, t: f32) -> Vector3f {
+    Vector3f {
+        x: a.x + (b.x - a.x) * t,
+        y: a.y + (b.y - a.y) * t,
+        z: a.z + (b.z - a.z) * t,
+    }
+}
+
+fn lerp_opt(a: Option<Vector3f>, b: Option<Vector3f>, t: f32) -> Option<Vector3f> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(lerp(&a, &b, t)),
+        (a, _) => a,
+    }
+}
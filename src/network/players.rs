@@ -0,0 +1,481 @@
+use crate::errors::FrameError;
+use crate::network::actor_state::ActorStateTracker;
+use crate::network::attributes::{Attribute, RemoteId};
+use crate::network::models::{ActorId, Frame};
+use crate::network::ClassTables;
+use fnv::FnvHashMap;
+
+const PRI_CLASS: &str = "TAGame.PRI_TA";
+const CAR_CLASS: &str = "TAGame.Car_TA";
+const BOOST_CLASS: &str = "TAGame.CarComponent_Boost_TA";
+const DODGE_CLASS: &str = "TAGame.CarComponent_Dodge_TA";
+const CAR_PRI: &str = "Engine.Pawn:PlayerReplicationInfo";
+const COMPONENT_CAR: &str = "TAGame.CarComponent_TA:Vehicle";
+const PLAYER_NAME: &str = "Engine.PlayerReplicationInfo:PlayerName";
+const PLAYER_ID: &str = "Engine.PlayerReplicationInfo:UniqueId";
+const PLAYER_TEAM: &str = "Engine.PlayerReplicationInfo:Team";
+
+/// An actor owned by a player for a span of frames
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct OwnedActor {
+    pub actor_id: ActorId,
+
+    /// The index of the frame where the player took ownership of the actor
+    pub start_frame: usize,
+
+    /// The index of the frame where the player no longer owns the actor (eg: the car was
+    /// demolished). `None` if the player owned the actor through the last processed frame.
+    pub end_frame: Option<usize>,
+}
+
+/// A player in the match as identified by their player replication info (PRI) actor. Each car
+/// (after a respawn or demolition) and car component owned by the player is recorded.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Player {
+    /// The player's PRI actor
+    pub actor_id: ActorId,
+
+    /// The index of the frame where the player's PRI actor was created
+    pub created_frame: usize,
+    pub name: Option<String>,
+    pub remote_id: Option<RemoteId>,
+
+    /// The team number (0 or 1) that the player is on
+    pub team: Option<i32>,
+    pub cars: Vec<OwnedActor>,
+    pub boosts: Vec<OwnedActor>,
+    pub dodges: Vec<OwnedActor>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Component {
+    Boost,
+    Dodge,
+}
+
+/// The index of a player and the index of the owned actor within one of the player's lists
+type Ownership = (usize, usize);
+
+/// Resolves which player owns which cars and car components by following the links between the
+/// actors in the network data:
+///
+/// - A car links to its player's PRI through the `Engine.Pawn:PlayerReplicationInfo` attribute
+/// - A car component links to its car through the `TAGame.CarComponent_TA:Vehicle` attribute
+/// - A PRI links to a team through the `Engine.PlayerReplicationInfo:Team` attribute
+///
+/// Since actor ids are recycled, ownership ends when an actor is deleted or its id is reused.
+///
+/// ```
+/// use boxcars::{ClassTables, ParserBuilder, PlayerResolver};
+///
+/// let data = include_bytes!("../../assets/replays/good/rumble.replay");
+/// let replay = ParserBuilder::new(&data[..])
+///     .must_parse_network_data()
+///     .parse()
+///     .unwrap();
+///
+/// let tables = ClassTables::default();
+/// let mut resolver = PlayerResolver::new(&replay.objects, &tables);
+/// for frame in &replay.network_frames.as_ref().unwrap().frames {
+///     resolver.process_frame(frame).unwrap();
+/// }
+///
+/// let player = resolver
+///     .players()
+///     .iter()
+///     .find(|x| x.name.as_deref() == Some("comagoosie"))
+///     .unwrap();
+///
+/// assert_eq!(player.team, Some(0));
+/// assert!(!player.cars.is_empty());
+/// ```
+#[derive(Debug, Clone)]
+pub struct PlayerResolver<'a> {
+    tracker: ActorStateTracker<'a>,
+    players: Vec<Player>,
+
+    /// Live PRI actors to their player
+    pri_players: FnvHashMap<ActorId, usize>,
+
+    /// Live cars to their current owner
+    car_owners: FnvHashMap<ActorId, Ownership>,
+
+    /// Live components to the car they are attached to
+    component_cars: FnvHashMap<ActorId, (Component, ActorId)>,
+
+    /// Live components to their current owner
+    component_owners: FnvHashMap<ActorId, Ownership>,
+}
+
+impl<'a> PlayerResolver<'a> {
+    /// Creates a resolver from the replay's objects and the tables the replay was parsed with
+    pub fn new(objects: &'a [String], tables: &'a ClassTables) -> Self {
+        PlayerResolver {
+            tracker: ActorStateTracker::new(objects, tables),
+            players: Vec::new(),
+            pri_players: FnvHashMap::default(),
+            car_owners: FnvHashMap::default(),
+            component_cars: FnvHashMap::default(),
+            component_owners: FnvHashMap::default(),
+        }
+    }
+
+    /// The players seen thus far
+    pub fn players(&self) -> &[Player] {
+        &self.players
+    }
+
+    pub fn into_players(self) -> Vec<Player> {
+        self.players
+    }
+
+    /// The underlying actor state, which is kept up to date with the processed frames
+    pub fn tracker(&self) -> &ActorStateTracker<'a> {
+        &self.tracker
+    }
+
+    /// The player that currently owns the car or car component
+    pub fn owner(&self, actor_id: ActorId) -> Option<&Player> {
+        self.owner_index(actor_id)
+            .map(|player| &self.players[player])
+    }
+
+    /// The index into `players` of the player that currently owns the car or car component
+    pub(crate) fn owner_index(&self, actor_id: ActorId) -> Option<usize> {
+        self.car_owners
+            .get(&actor_id)
+            .or_else(|| self.component_owners.get(&actor_id))
+            .map(|&(player, _)| player)
+    }
+
+    pub fn process_frame(&mut self, frame: &Frame) -> Result<(), FrameError> {
+        let frame_ind = self.tracker.frames_processed();
+        self.tracker.process_frame(frame)?;
+
+        // New actors (that aren't live actors re-announced in a keyframe) implicitly delete the
+        // actor that previously occupied the id
+        let created: Vec<ActorId> = frame
+            .new_actors
+            .iter()
+            .map(|x| x.actor_id)
+            .filter(|&x| {
+                self.tracker
+                    .actor(x)
+                    .filter(|x| x.created_frame == frame_ind)
+                    .is_some()
+            })
+            .collect();
+
+        for &actor_id in frame.deleted_actors.iter().chain(created.iter()) {
+            self.remove_actor(actor_id, frame_ind);
+        }
+
+        for &actor_id in &created {
+            let is_pri = self
+                .tracker
+                .actor(actor_id)
+                .filter(|x| x.is_a(PRI_CLASS))
+                .is_some();
+
+            if is_pri {
+                self.pri_players.insert(actor_id, self.players.len());
+                self.players.push(Player {
+                    actor_id,
+                    created_frame: frame_ind,
+                    name: None,
+                    remote_id: None,
+                    team: None,
+                    cars: Vec::new(),
+                    boosts: Vec::new(),
+                    dodges: Vec::new(),
+                });
+            }
+        }
+
+        for update in &frame.updated_actors {
+            let actor = match self.tracker.actor(update.actor_id) {
+                Some(actor) => actor,
+                None => continue,
+            };
+
+            let attribute_name = self
+                .tracker
+                .object_name(update.object_id)
+                .unwrap_or_default();
+
+            match (attribute_name, &update.attribute) {
+                (CAR_PRI, Attribute::ActiveActor(link)) if actor.is_a(CAR_CLASS) => {
+                    let owner = self.car_owners.get(&update.actor_id).map(|x| x.0);
+                    if link.active
+                        && owner.is_some()
+                        && owner == self.pri_players.get(&link.actor).cloned()
+                    {
+                        continue;
+                    }
+
+                    self.end_car_ownership(update.actor_id, frame_ind);
+                    if link.active {
+                        self.start_car_ownership(update.actor_id, link.actor, frame_ind);
+                    }
+                }
+                (COMPONENT_CAR, Attribute::ActiveActor(link)) => {
+                    let component = if actor.is_a(BOOST_CLASS) {
+                        Component::Boost
+                    } else if actor.is_a(DODGE_CLASS) {
+                        Component::Dodge
+                    } else {
+                        continue;
+                    };
+
+                    let car = self.component_cars.get(&update.actor_id).map(|x| x.1);
+                    if link.active && car == Some(link.actor) {
+                        continue;
+                    }
+
+                    self.end_component_ownership(update.actor_id, frame_ind);
+                    self.component_cars.remove(&update.actor_id);
+                    if link.active {
+                        self.component_cars
+                            .insert(update.actor_id, (component, link.actor));
+                        if let Some(&(player, _)) = self.car_owners.get(&link.actor) {
+                            self.start_component_ownership(update.actor_id, player, frame_ind);
+                        }
+                    }
+                }
+                (PLAYER_NAME, Attribute::String(name)) => {
+                    if let Some(player) = self.pri_player(update.actor_id) {
+                        player.name = Some(name.clone());
+                    }
+                }
+                (PLAYER_ID, Attribute::UniqueId(id)) => {
+                    if let Some(player) = self.pri_player(update.actor_id) {
+                        player.remote_id = Some(id.remote_id.clone());
+                    }
+                }
+                (PLAYER_TEAM, Attribute::ActiveActor(link)) if link.active => {
+                    // Players that leave are unlinked from their team, so the last team is kept
+                    let team = self
+                        .tracker
+                        .actor(link.actor)
+                        .and_then(|x| team_number(x.object_name));
+                    if let (Some(team), Some(player)) = (team, self.pri_player(update.actor_id)) {
+                        player.team = Some(team);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    fn pri_player(&mut self, actor_id: ActorId) -> Option<&mut Player> {
+        let ind = *self.pri_players.get(&actor_id)?;
+        Some(&mut self.players[ind])
+    }
+
+    fn remove_actor(&mut self, actor_id: ActorId, frame: usize) {
+        self.pri_players.remove(&actor_id);
+        self.end_car_ownership(actor_id, frame);
+        self.end_component_ownership(actor_id, frame);
+        self.component_cars.remove(&actor_id);
+    }
+
+    fn start_car_ownership(&mut self, car: ActorId, pri: ActorId, frame: usize) {
+        let player = match self.pri_players.get(&pri) {
+            Some(&player) => player,
+            None => return,
+        };
+
+        let cars = &mut self.players[player].cars;
+        self.car_owners.insert(car, (player, cars.len()));
+        cars.push(OwnedActor {
+            actor_id: car,
+            start_frame: frame,
+            end_frame: None,
+        });
+
+        // Components may have been attached to the car before the car was linked to the player
+        let components: Vec<ActorId> = self
+            .component_cars
+            .iter()
+            .filter(|(_, &(_, x))| x == car)
+            .map(|(&component, _)| component)
+            .collect();
+
+        for component in components {
+            self.end_component_ownership(component, frame);
+            self.start_component_ownership(component, player, frame);
+        }
+    }
+
+    fn end_car_ownership(&mut self, car: ActorId, frame: usize) {
+        if let Some((player, ind)) = self.car_owners.remove(&car) {
+            self.players[player].cars[ind].end_frame = Some(frame);
+
+            let components: Vec<ActorId> = self
+                .component_cars
+                .iter()
+                .filter(|(_, &(_, x))| x == car)
+                .map(|(&component, _)| component)
+                .collect();
+
+            for component in components {
+                self.end_component_ownership(component, frame);
+            }
+        }
+    }
+
+    fn start_component_ownership(&mut self, component: ActorId, player: usize, frame: usize) {
+        let kind = match self.component_cars.get(&component) {
+            Some(&(kind, _)) => kind,
+            None => return,
+        };
+
+        let owned = match kind {
+            Component::Boost => &mut self.players[player].boosts,
+            Component::Dodge => &mut self.players[player].dodges,
+        };
+
+        self.component_owners
+            .insert(component, (player, owned.len()));
+        owned.push(OwnedActor {
+            actor_id: component,
+            start_frame: frame,
+            end_frame: None,
+        });
+    }
+
+    fn end_component_ownership(&mut self, component: ActorId, frame: usize) {
+        let kind = self.component_cars.get(&component).map(|&(kind, _)| kind);
+        if let (Some((player, ind)), Some(kind)) = (self.component_owners.remove(&component), kind)
+        {
+            let owned = match kind {
+                Component::Boost => &mut self.players[player].boosts,
+                Component::Dodge => &mut self.players[player].dodges,
+            };
+            owned[ind].end_frame = Some(frame);
+        }
+    }
+}
+
+/// Teams are spawned from a per team archetype
+fn team_number(object_name: &str) -> Option<i32> {
+    match object_name {
+        "Archetypes.Teams.Team0" => Some(0),
+        "Archetypes.Teams.Team1" => Some(1),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::attributes::ActiveActor;
+    use crate::network::models::{NewActor, ObjectId, StreamId, Trajectory, UpdatedAttribute};
+
+    fn objects() -> Vec<String> {
+        vec![
+            String::from("TAGame.Default__PRI_TA"),
+            String::from("Archetypes.Car.Car_Default"),
+            String::from(CAR_PRI),
+            String::from(PLAYER_NAME),
+        ]
+    }
+
+    fn new_actor(actor: i32, object: i32) -> NewActor {
+        NewActor {
+            actor_id: ActorId(actor),
+            name_id: None,
+            object_id: ObjectId(object),
+            initial_trajectory: Trajectory {
+                location: None,
+                rotation: None,
+            },
+        }
+    }
+
+    fn update(actor: i32, object: i32, attribute: Attribute) -> UpdatedAttribute {
+        UpdatedAttribute {
+            actor_id: ActorId(actor),
+            stream_id: StreamId(0),
+            object_id: ObjectId(object),
+            attribute,
+        }
+    }
+
+    fn link(actor: i32) -> Attribute {
+        Attribute::ActiveActor(ActiveActor {
+            active: true,
+            actor: ActorId(actor),
+        })
+    }
+
+    fn frame(
+        new_actors: Vec<NewActor>,
+        deleted_actors: Vec<ActorId>,
+        updated_actors: Vec<UpdatedAttribute>,
+    ) -> Frame {
+        Frame {
+            time: 0.0,
+            delta: 0.0,
+            new_actors,
+            deleted_actors,
+            updated_actors,
+        }
+    }
+
+    #[test]
+    fn test_resolver_recycled_actor_ids() {
+        let objects = objects();
+        let tables = ClassTables::default();
+        let mut resolver = PlayerResolver::new(&objects, &tables);
+        resolver
+            .process_frame(&frame(
+                vec![new_actor(1, 0), new_actor(2, 1)],
+                vec![],
+                vec![
+                    update(1, 3, Attribute::String(String::from("alpha"))),
+                    update(2, 2, link(1)),
+                ],
+            ))
+            .unwrap();
+
+        // The player leaves and their PRI and car actor ids are given to a new player
+        resolver
+            .process_frame(&frame(vec![], vec![ActorId(1), ActorId(2)], vec![]))
+            .unwrap();
+        resolver
+            .process_frame(&frame(
+                vec![new_actor(1, 0), new_actor(2, 1)],
+                vec![],
+                vec![
+                    update(1, 3, Attribute::String(String::from("beta"))),
+                    update(2, 2, link(1)),
+                ],
+            ))
+            .unwrap();
+
+        let players = resolver.players();
+        assert_eq!(players.len(), 2);
+        assert_eq!(players[0].name.as_deref(), Some("alpha"));
+        assert_eq!(
+            players[0].cars,
+            vec![OwnedActor {
+                actor_id: ActorId(2),
+                start_frame: 0,
+                end_frame: Some(1),
+            }]
+        );
+        assert_eq!(players[1].name.as_deref(), Some("beta"));
+        assert_eq!(
+            players[1].cars,
+            vec![OwnedActor {
+                actor_id: ActorId(2),
+                start_frame: 2,
+                end_frame: None,
+            }]
+        );
+        assert_eq!(resolver.owner(ActorId(2)), Some(&players[1]));
+    }
+}
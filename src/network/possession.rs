@@ -0,0 +1,197 @@
+use crate::errors::AnalysisError;
+use crate::models::Replay;
+use crate::network::models::ActorId;
+use crate::network::players::PlayerResolver;
+use crate::network::touches::{Touch, TouchDetector};
+use crate::network::ClassTables;
+
+/// Who is in possession of the ball at a frame, which is whoever touched the ball last. There is no
+/// possession before the first touch of a ball (eg: before a kickoff).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+pub struct PossessionFrame {
+    /// The team number (0 or 1) in possession
+    pub team: Option<i32>,
+
+    /// The PRI actor of the player in possession (see `Player::actor_id`)
+    pub player: Option<ActorId>,
+}
+
+/// The time a team spent in possession of the ball
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct TeamPossession {
+    pub team: i32,
+
+    /// Seconds in possession
+    pub time: f32,
+
+    /// The share (from 0 to 100) of the time that either team was in possession
+    pub percentage: f32,
+}
+
+/// The time a player spent in possession of the ball
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PlayerPossession {
+    /// The player's PRI actor (see `Player::actor_id`)
+    pub player: ActorId,
+    pub name: Option<String>,
+
+    /// The team number (0 or 1) that the player is on
+    pub team: Option<i32>,
+
+    /// Seconds in possession
+    pub time: f32,
+
+    /// The share (from 0 to 100) of the time that any player was in possession
+    pub percentage: f32,
+}
+
+/// The touches of a match and the possession derived from them
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Possession {
+    pub touches: Vec<Touch>,
+
+    /// The possession at each frame, indexed by frame
+    pub frames: Vec<PossessionFrame>,
+
+    /// The teams that were in possession, ordered by team number
+    pub teams: Vec<TeamPossession>,
+    pub players: Vec<PlayerPossession>,
+}
+
+/// Detects the touches in the network frames (see `TouchDetector`) and derives a possession
+/// timeline from them: the team and player that last touched the ball is in possession until
+/// someone else touches it or the ball is destroyed (eg: after a goal). Possession percentages are
+/// weighted by the frame durations. The ball and players are found with the tables the replay was
+/// parsed with.
+///
+/// ```
+/// use boxcars::{possession, ClassTables, ParserBuilder};
+///
+/// let data = include_bytes!("../../assets/replays/good/rumble.replay");
+/// let replay = ParserBuilder::new(&data[..])
+///     .must_parse_network_data()
+///     .parse()
+///     .unwrap();
+///
+/// let possession = possession(&replay, &ClassTables::default()).unwrap();
+/// let total: f32 = possession.teams.iter().map(|x| x.percentage).sum();
+/// assert!((total - 100.0).abs() < 0.01);
+/// ```
+pub fn possession(replay: &Replay, tables: &ClassTables) -> Result<Possession, AnalysisError> {
+    let frames = &replay
+        .network_frames
+        .as_ref()
+        .ok_or(AnalysisError::MissingNetworkFrames)?
+        .frames;
+
+    let mut resolver = PlayerResolver::new(&replay.objects, tables);
+    let mut detector = TouchDetector::new().with_net_version(replay.net_version);
+    let mut timeline: Vec<PossessionFrame> = Vec::with_capacity(frames.len());
+    let mut team_times: Vec<(i32, f32)> = Vec::new();
+    let mut player_times: Vec<f32> = Vec::new();
+
+    // The ball last touched, who touched it, and the index of their player. The player is resolved
+    // through the car's owner at the time of the touch, as PRI actor ids are recycled.
+    let mut current: Option<(ActorId, PossessionFrame, Option<usize>)> = None;
+
+    for (frame_ind, frame) in frames.iter().enumerate() {
+        resolver
+            .process_frame(frame)
+            .map_err(|e| AnalysisError::FrameError(frame_ind, e))?;
+
+        let touched = detector.touches().len();
+        detector.process_frame(&resolver, frame);
+
+        // The ball is no longer alive if it was deleted or its actor id was reused
+        let destroyed = current
+            .filter(|&(ball, _, _)| {
+                resolver
+                    .tracker()
+                    .actor(ball)
+                    .filter(|x| x.created_frame != frame_ind)
+                    .is_none()
+            })
+            .is_some();
+
+        if destroyed {
+            current = None;
+        }
+
+        if let Some(touch) = detector.touches()[touched..].last() {
+            current = Some((
+                touch.ball,
+                PossessionFrame {
+                    team: Some(touch.team),
+                    player: touch.player,
+                },
+                touch.car.and_then(|x| resolver.owner_index(x)),
+            ));
+        }
+
+        let possessor = current.map(|(_, x, _)| x).unwrap_or_default();
+        timeline.push(possessor);
+
+        if let Some(team) = possessor.team {
+            match team_times.iter_mut().find(|x| x.0 == team) {
+                Some(time) => time.1 += frame.delta,
+                None => team_times.push((team, frame.delta)),
+            }
+        }
+
+        player_times.resize(resolver.players().len(), 0.0);
+        if let Some(player) = current.and_then(|(_, _, x)| x) {
+            player_times[player] += frame.delta;
+        }
+    }
+
+    team_times.sort_by_key(|x| x.0);
+    let team_total: f32 = team_times.iter().map(|x| x.1).sum();
+    let teams = team_times
+        .into_iter()
+        .map(|(team, time)| TeamPossession {
+            team,
+            time,
+            percentage: percentage(time, team_total),
+        })
+        .collect();
+
+    let player_total: f32 = player_times.iter().sum();
+    let players = resolver
+        .players()
+        .iter()
+        .zip(player_times)
+        .map(|(player, time)| PlayerPossession {
+            player: player.actor_id,
+            name: player.name.clone(),
+            team: player.team,
+            time,
+            percentage: percentage(time, player_total),
+        })
+        .collect();
+
+    Ok(Possession {
+        touches: detector.into_touches(),
+        frames: timeline,
+        teams,
+        players,
+    })
+}
+
+fn percentage(time: f32, total: f32) -> f32 {
+    if total > 0.0 {
+        time * 100.0 / total
+    } else {
+        0.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_percentage() {
+        assert_eq!(percentage(1.0, 4.0), 25.0);
+        assert_eq!(percentage(0.0, 0.0), 0.0);
+    }
+}
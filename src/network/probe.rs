@@ -0,0 +1,262 @@
+//! # Probing
+//!
+//! When a Rocket League patch introduces an attribute, decoding stops with a
+//! `FrameError::MissingAttribute` at the first update of it. Working out how the attribute is
+//! encoded is mostly guesswork, which the prober speeds up by:
+//!
+//! - Listing the attributes in the replay's net cache that boxcars doesn't know how to decode
+//! - Dumping the raw bits of the network data from where decoding failed
+//! - Decoding the failing attribute as each of the existing `AttributeTag`s and recording which
+//!   of them allow decoding to continue for the next several frames
+//!
+//! A candidate that continues is not necessarily correct (many attributes are a few bits and
+//! decoding may only fail much later), but it narrows down what to try with `ClassTables`.
+
+use crate::errors::{NetworkError, ParseError};
+use crate::network::frame_decoder::bits_at;
+use crate::network::models::normalize_object;
+use crate::network::{AttributeTag, ClassTables, ObjectId};
+use crate::parser::ParserBuilder;
+use crate::report::ErrorReport;
+use std::cmp::Reverse;
+
+/// The number of updates before the failure to include in the error report
+const RECENT_UPDATES: usize = 10;
+
+/// Diagnoses replays with attributes that boxcars can't decode.
+///
+/// ```
+/// use boxcars::{AttributeProber, AttributeTag, ClassTables};
+///
+/// // Pretend that boxcars doesn't know how to decode which team last hit the ball
+/// let tables = ClassTables::new().with_attribute(
+///     "TAGame.Ball_TA:HitTeamNum",
+///     AttributeTag::NotImplemented,
+/// );
+///
+/// let data = include_bytes!("../../assets/replays/good/rumble.replay");
+/// let report = AttributeProber::new(&data[..])
+///     .with_tables(&tables)
+///     .probe()
+///     .unwrap();
+///
+/// let failure = report.failure.unwrap();
+/// assert_eq!(
+///     failure.report.attribute_name.as_deref(),
+///     Some("TAGame.Ball_TA:HitTeamNum")
+/// );
+///
+/// // The team is a byte
+/// let continues: Vec<_> = failure
+///     .candidates
+///     .iter()
+///     .filter(|x| x.continues)
+///     .map(|x| x.tag)
+///     .collect();
+/// assert!(continues.contains(&AttributeTag::Byte));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct AttributeProber<'a> {
+    data: &'a [u8],
+    tables: Option<&'a ClassTables>,
+    lookahead: usize,
+    dump_bits: usize,
+}
+
+/// The findings of an `AttributeProber`
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ProbeReport {
+    /// Attributes in the net cache without a known way to decode them (or that are declared as
+    /// `NotImplemented`), ordered by object id
+    pub unknown_attributes: Vec<UnknownAttribute>,
+
+    /// The first failure to decode the network data, if any
+    pub failure: Option<ProbeFailure>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct UnknownAttribute {
+    pub object_id: ObjectId,
+    pub name: String,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ProbeFailure {
+    pub report: ErrorReport,
+
+    /// The network data from where decoding failed, as `0` and `1` in the order that the bits
+    /// are read
+    pub bits: String,
+
+    /// The outcome of decoding the failing attribute as each tag, ordered from the furthest
+    /// decoded. Empty when the failure isn't for an attribute with a name.
+    pub candidates: Vec<ProbeCandidate>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ProbeCandidate {
+    pub tag: AttributeTag,
+
+    /// The number of frames decoded from the failing frame onwards, up to the lookahead
+    pub frames: usize,
+
+    /// Decoding continued for the lookahead or until the end of the network data
+    pub continues: bool,
+}
+
+impl<'a> AttributeProber<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        AttributeProber {
+            data,
+            tables: None,
+            lookahead: 100,
+            dump_bits: 128,
+        }
+    }
+
+    /// Decode the network data with additional object mappings that extend or override the
+    /// built-in tables
+    pub fn with_tables(mut self, tables: &'a ClassTables) -> Self {
+        self.tables = Some(tables);
+        self
+    }
+
+    /// The number of frames that a candidate must decode, starting with the failing frame, to be
+    /// considered to continue decoding. Defaults to 100.
+    pub fn with_lookahead(mut self, frames: usize) -> Self {
+        self.lookahead = frames;
+        self
+    }
+
+    /// The number of bits to dump from where decoding failed. Defaults to 128.
+    pub fn with_dump_bits(mut self, bits: usize) -> Self {
+        self.dump_bits = bits;
+        self
+    }
+
+    pub fn probe(self) -> Result<ProbeReport, ParseError> {
+        let default_tables = ClassTables::default();
+        let tables = self.tables.unwrap_or(&default_tables);
+        let replay = ParserBuilder::new(self.data)
+            .never_parse_network_data()
+            .parse()?;
+
+        let mut unknown_attributes: Vec<UnknownAttribute> = replay
+            .net_cache
+            .iter()
+            .flat_map(|x| x.properties.iter())
+            .filter_map(|x| {
+                let name = replay.objects.get(x.object_ind as usize)?;
+                match tables.attribute(normalize_object(name)) {
+                    None | Some(AttributeTag::NotImplemented) => Some(UnknownAttribute {
+                        object_id: ObjectId(x.object_ind),
+                        name: name.clone(),
+                    }),
+                    Some(_) => None,
+                }
+            })
+            .collect();
+        unknown_attributes.sort_by_key(|x| x.object_id);
+        unknown_attributes.dedup();
+
+        let failure = match self.builder(tables).parse_streaming() {
+            Ok((_, mut frames)) => match frames.find_map(Result::err) {
+                Some(NetworkError::FrameError(err, context)) => {
+                    let bits = bits_at(frames.network_data(), context.bit_position)
+                        .map(|mut x| {
+                            (0..self.dump_bits)
+                                .map_while(|_| x.read_bit())
+                                .map(|x| if x { '1' } else { '0' })
+                                .collect()
+                        })
+                        .unwrap_or_default();
+
+                    let error = NetworkError::FrameError(err, context);
+                    let report = error.report(RECENT_UPDATES);
+                    let candidates = match (report.attribute_name.as_ref(), report.frame) {
+                        (Some(name), Some(frame)) => self.candidates(tables, name, frame),
+                        _ => Vec::new(),
+                    };
+
+                    Some(ProbeFailure {
+                        report,
+                        bits,
+                        candidates,
+                    })
+                }
+                Some(e) => Some(ProbeFailure {
+                    report: e.report(RECENT_UPDATES),
+                    bits: String::new(),
+                    candidates: Vec::new(),
+                }),
+                None => None,
+            },
+            Err(e) => Some(ProbeFailure {
+                report: e.report(RECENT_UPDATES),
+                bits: String::new(),
+                candidates: Vec::new(),
+            }),
+        };
+
+        Ok(ProbeReport {
+            unknown_attributes,
+            failure,
+        })
+    }
+
+    fn builder<'b>(&self, tables: &'b ClassTables) -> ParserBuilder<'b>
+    where
+        'a: 'b,
+    {
+        ParserBuilder::new(self.data)
+            .never_check_crc()
+            .with_tables(tables)
+    }
+
+    /// Decodes from the failing frame with the attribute as each candidate tag
+    fn candidates(&self, tables: &ClassTables, name: &str, frame: usize) -> Vec<ProbeCandidate> {
+        let mut result: Vec<ProbeCandidate> = AttributeTag::ALL
+            .iter()
+            .filter(|&&tag| tag != AttributeTag::NotImplemented)
+            .map(|&tag| self.candidate(tables, name, tag, frame))
+            .collect();
+
+        result.sort_by_key(|x| (!x.continues, Reverse(x.frames)));
+        result
+    }
+
+    fn candidate(
+        &self,
+        tables: &ClassTables,
+        name: &str,
+        tag: AttributeTag,
+        frame: usize,
+    ) -> ProbeCandidate {
+        let tables = tables.clone().with_attribute(normalize_object(name), tag);
+        let mut result = ProbeCandidate {
+            tag,
+            frames: 0,
+            continues: false,
+        };
+
+        let mut frames = match self.builder(&tables).parse_streaming() {
+            Ok((_, frames)) => frames,
+            Err(_) => return result,
+        };
+
+        if frames.seek_frame(frame).is_err() {
+            return result;
+        }
+
+        while result.frames < self.lookahead {
+            match frames.next() {
+                Some(Ok(_)) => result.frames += 1,
+                Some(Err(_)) => return result,
+                None => break,
+            }
+        }
+
+        result.continues = true;
+        result
+    }
+}
@@ -0,0 +1,202 @@
+use crate::data::{object_classes, ATTRIBUTES, PARENT_CLASSES, SPAWN_STATS};
+use crate::network::{AttributeTag, SpawnTrajectory};
+use std::collections::HashMap;
+
+/// Extends or overrides the built-in tables that describe how to decode the network data.
+///
+/// New Rocket League patches routinely introduce objects that boxcars doesn't know about yet.
+/// Instead of waiting for a new release, the missing entries can be supplied at runtime, either
+/// built programmatically or deserialized (with any serde format, such as JSON or TOML). Entries
+/// take precedence over the built-in tables and any object not mentioned falls back to them.
+///
+/// ```
+/// use boxcars::{AttributeTag, ClassTables, SpawnTrajectory};
+///
+/// let tables = ClassTables::new()
+///     .with_attribute("TAGame.Ball_TA:NewProperty", AttributeTag::Int)
+///     .with_spawn("Archetypes.Ball.Ball_New", SpawnTrajectory::LocationAndRotation)
+///     .with_object_class("Archetypes.Ball.Ball_New", "TAGame.Ball_TA");
+///
+/// let json = r#"{
+///     "attributes": { "TAGame.Ball_TA:NewProperty": "Int" },
+///     "spawns": { "Archetypes.Ball.Ball_New": "LocationAndRotation" },
+///     "object_classes": { "Archetypes.Ball.Ball_New": "TAGame.Ball_TA" }
+/// }"#;
+/// let loaded: ClassTables = serde_json::from_str(json).unwrap();
+/// assert_eq!(loaded, tables);
+///
+/// let data = include_bytes!("../../assets/replays/good/rumble.replay");
+/// let replay = boxcars::ParserBuilder::new(&data[..])
+///     .with_tables(&tables)
+///     .must_parse_network_data()
+///     .parse();
+/// assert!(replay.is_ok());
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct ClassTables {
+    /// Maps an attribute's object name (eg: `TAGame.Ball_TA:GameEvent`) to how it is decoded
+    attributes: HashMap<String, AttributeTag>,
+
+    /// Maps an object name to the trajectory it is spawned with
+    spawns: HashMap<String, SpawnTrajectory>,
+
+    /// Maps a class to the class it inherits attributes from
+    parent_classes: HashMap<String, String>,
+
+    /// Maps an archetype object to the class it is an instance of
+    object_classes: HashMap<String, String>,
+}
+
+impl ClassTables {
+    pub fn new() -> Self {
+        ClassTables::default()
+    }
+
+    /// Decode the attribute with the given object name as the given tag
+    pub fn with_attribute<S: Into<String>>(mut self, name: S, tag: AttributeTag) -> Self {
+        self.attributes.insert(name.into(), tag);
+        self
+    }
+
+    /// New actors of the given object will be decoded with the given initial trajectory
+    pub fn with_spawn<S: Into<String>>(mut self, name: S, trajectory: SpawnTrajectory) -> Self {
+        self.spawns.insert(name.into(), trajectory);
+        self
+    }
+
+    /// Declares that a class inherits the attributes of the parent class. Decoding fails with
+    /// `NetworkError::ParentClassCycle` if the parent classes end up forming a cycle.
+    pub fn with_parent_class<S, T>(mut self, class: S, parent: T) -> Self
+    where
+        S: Into<String>,
+        T: Into<String>,
+    {
+        self.parent_classes.insert(class.into(), parent.into());
+        self
+    }
+
+    /// Declares that an archetype object is an instance of the given class
+    pub fn with_object_class<S, T>(mut self, object: S, class: T) -> Self
+    where
+        S: Into<String>,
+        T: Into<String>,
+    {
+        self.object_classes.insert(object.into(), class.into());
+        self
+    }
+
+    pub(crate) fn attribute(&self, name: &str) -> Option<AttributeTag> {
+        self.attributes
+            .get(name)
+            .or_else(|| ATTRIBUTES.get(name))
+            .cloned()
+    }
+
+    pub(crate) fn spawn(&self, name: &str) -> Option<SpawnTrajectory> {
+        self.spawns
+            .get(name)
+            .or_else(|| SPAWN_STATS.get(name))
+            .cloned()
+    }
+
+    pub(crate) fn parent_class(&self, class: &str) -> Option<&str> {
+        self.parent_classes
+            .get(class)
+            .map(|x| x.as_str())
+            .or_else(|| PARENT_CLASSES.get(class).cloned())
+    }
+
+    /// The built-in object classes merged with the declared ones
+    pub(crate) fn object_classes(&self) -> Vec<(&str, &str)> {
+        let mut result: Vec<(&str, &str)> = object_classes()
+            .into_iter()
+            .filter(|(obj, _)| !self.object_classes.contains_key(*obj))
+            .collect();
+
+        let mut extra: Vec<(&str, &str)> = self
+            .object_classes
+            .iter()
+            .map(|(obj, class)| (obj.as_str(), class.as_str()))
+            .collect();
+        extra.sort();
+        result.extend(extra);
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tables_fall_back_to_builtin() {
+        let tables = ClassTables::new();
+        assert_eq!(
+            tables.attribute("TAGame.RBActor_TA:ReplicatedRBState"),
+            Some(AttributeTag::RigidBody)
+        );
+        assert_eq!(
+            tables.spawn("TAGame.Ball_TA"),
+            Some(SpawnTrajectory::LocationAndRotation)
+        );
+        assert_eq!(tables.parent_class("Engine.Actor"), Some("Core.Object"));
+        assert_eq!(tables.object_classes().len(), object_classes().len());
+        assert_eq!(tables.attribute("Unknown:Property"), None);
+    }
+
+    #[test]
+    fn test_tables_override_builtin() {
+        let tables = ClassTables::new()
+            .with_attribute("TAGame.RBActor_TA:ReplicatedRBState", AttributeTag::Int)
+            .with_attribute("TAGame.New_TA:Property", AttributeTag::Float)
+            .with_spawn("TAGame.Ball_TA", SpawnTrajectory::Location)
+            .with_parent_class("Engine.Actor", "Engine.Info")
+            .with_object_class("Archetypes.Car.Car_Default", "TAGame.Ball_TA")
+            .with_object_class("Archetypes.Car.Car_New", "TAGame.Car_TA");
+
+        assert_eq!(
+            tables.attribute("TAGame.RBActor_TA:ReplicatedRBState"),
+            Some(AttributeTag::Int)
+        );
+        assert_eq!(
+            tables.attribute("TAGame.New_TA:Property"),
+            Some(AttributeTag::Float)
+        );
+        assert_eq!(
+            tables.spawn("TAGame.Ball_TA"),
+            Some(SpawnTrajectory::Location)
+        );
+        assert_eq!(tables.parent_class("Engine.Actor"), Some("Engine.Info"));
+
+        let classes = tables.object_classes();
+        assert_eq!(classes.len(), object_classes().len() + 1);
+        let car_classes: Vec<_> = classes
+            .iter()
+            .filter(|(obj, _)| *obj == "Archetypes.Car.Car_Default")
+            .collect();
+        assert_eq!(
+            car_classes,
+            vec![&("Archetypes.Car.Car_Default", "TAGame.Ball_TA")]
+        );
+        assert!(classes.contains(&("Archetypes.Car.Car_New", "TAGame.Car_TA")));
+    }
+
+    #[test]
+    fn test_tables_deserialize() {
+        let json = r#"{
+            "attributes": { "TAGame.New_TA:Property": "QWord" },
+            "parent_classes": { "TAGame.New_TA": "TAGame.Car_TA" }
+        }"#;
+        let tables: ClassTables = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            tables,
+            ClassTables::new()
+                .with_attribute("TAGame.New_TA:Property", AttributeTag::QWord)
+                .with_parent_class("TAGame.New_TA", "TAGame.Car_TA")
+        );
+
+        let bad = r#"{ "attributes": { "TAGame.New_TA:Property": "Bogus" } }"#;
+        assert!(serde_json::from_str::<ClassTables>(bad).is_err());
+    }
+}
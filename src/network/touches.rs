@@ -0,0 +1,331 @@
+use crate::network::actor_state::ActorState;
+use crate::network::attributes::{Attribute, RigidBody};
+use crate::network::models::{ActorId, Frame, Vector3f};
+use crate::network::players::{Player, PlayerResolver};
+use fnv::FnvHashMap;
+
+const BALL_CLASS_PREFIX: &str = "TAGame.Ball_";
+const HIT_TEAM: &str = "TAGame.Ball_TA:HitTeamNum";
+const RIGID_BODY: &str = "TAGame.RBActor_TA:ReplicatedRBState";
+
+/// How close (in unreal units) a car must be to the ball to have touched it. The ball's radius is
+/// about 93 and a car is about 120 long, plus some slack for the time between updates.
+const TOUCH_DISTANCE: f32 = 250.0;
+
+/// How much (in unreal units per second) the ball's velocity must change between two updates to
+/// have been touched. Gravity alone changes it by about 20 between updates.
+const VELOCITY_CHANGE: f32 = 500.0;
+
+/// Seconds during which further contact by the same car is considered part of the same touch
+const TOUCH_COOLDOWN: f32 = 0.25;
+
+const ZERO: Vector3f = Vector3f {
+    x: 0.0,
+    y: 0.0,
+    z: 0.0,
+};
+
+/// A player touching the ball
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Touch {
+    /// The index of the frame
+    pub frame: usize,
+
+    /// The frame's time in seconds
+    pub time: f32,
+    pub ball: ActorId,
+
+    /// The team number (0 or 1) of the player that touched the ball
+    pub team: i32,
+
+    /// The car that touched the ball. `None` if none of the team's cars have a known location.
+    pub car: Option<ActorId>,
+
+    /// The PRI actor of the car's player (see `Player::actor_id`)
+    pub player: Option<ActorId>,
+    pub player_name: Option<String>,
+}
+
+/// The last known state of a live ball
+#[derive(Debug, Clone, Default)]
+struct BallState {
+    hit_team: Option<u8>,
+    velocity: Option<Vector3f>,
+
+    /// The index of the last touch of the ball
+    last_touch: Option<usize>,
+}
+
+/// Detects touches by combining two sources:
+///
+/// - The ball's `TAGame.Ball_TA:HitTeamNum` attribute, which is the team of the last player to
+///   touch the ball. A change in the team (or the first value for a new ball) is a touch, as
+///   keyframes replicate the value again. The touch is attributed to the team's car that is
+///   closest to the ball.
+/// - The ball's rigid body, as the team doesn't change when a player touches the ball after a
+///   teammate (or touches it again). A sudden change in the ball's velocity while a car is within
+///   touching distance is a touch by that car.
+///
+/// Frames are fed in order after they have been processed by a `PlayerResolver`.
+///
+/// ```
+/// use boxcars::{ClassTables, ParserBuilder, PlayerResolver, TouchDetector};
+///
+/// let data = include_bytes!("../../assets/replays/good/rumble.replay");
+/// let replay = ParserBuilder::new(&data[..])
+///     .must_parse_network_data()
+///     .parse()
+///     .unwrap();
+///
+/// let tables = ClassTables::default();
+/// let mut resolver = PlayerResolver::new(&replay.objects, &tables);
+/// let mut detector = TouchDetector::new().with_net_version(replay.net_version);
+/// for frame in &replay.network_frames.as_ref().unwrap().frames {
+///     resolver.process_frame(frame).unwrap();
+///     detector.process_frame(&resolver, frame);
+/// }
+///
+/// let touches = detector.touches();
+/// assert!(touches
+///     .iter()
+///     .any(|x| x.player_name.as_deref() == Some("comagoosie")));
+/// ```
+#[derive(Debug, Clone)]
+pub struct TouchDetector {
+    /// Live balls to their last known state
+    balls: FnvHashMap<ActorId, BallState>,
+    touches: Vec<Touch>,
+
+    /// Multipliers that convert replicated locations and velocities to unreal units
+    location_scale: f32,
+    velocity_scale: f32,
+}
+
+impl Default for TouchDetector {
+    fn default() -> Self {
+        TouchDetector {
+            balls: FnvHashMap::default(),
+            touches: Vec::new(),
+            location_scale: 1.0,
+            velocity_scale: 1.0,
+        }
+    }
+}
+
+impl TouchDetector {
+    /// Creates a detector for replays that replicate rigid bodies in unreal units (network version
+    /// 5 and later). See `with_net_version` for older replays.
+    pub fn new() -> Self {
+        TouchDetector::default()
+    }
+
+    /// The network version of the replay (see `Replay::net_version`), which determines the units
+    /// of the replicated rigid bodies
+    pub fn with_net_version(mut self, net_version: Option<i32>) -> Self {
+        let (location_scale, velocity_scale) = unreal_units(net_version);
+        self.location_scale = location_scale;
+        self.velocity_scale = velocity_scale;
+        self
+    }
+
+    /// The touches detected thus far
+    pub fn touches(&self) -> &[Touch] {
+        &self.touches
+    }
+
+    pub fn into_touches(self) -> Vec<Touch> {
+        self.touches
+    }
+
+    /// Detects the touches in a frame that the resolver has already processed
+    pub fn process_frame(&mut self, resolver: &PlayerResolver<'_>, frame: &Frame) {
+        let tracker = resolver.tracker();
+        let frame_ind = tracker.frames_processed() - 1;
+        let (location_scale, velocity_scale) = (self.location_scale, self.velocity_scale);
+        let balls = &mut self.balls;
+        let touches = &mut self.touches;
+
+        for actor_id in &frame.deleted_actors {
+            balls.remove(actor_id);
+        }
+
+        for actor in &frame.new_actors {
+            if tracker
+                .actor(actor.actor_id)
+                .filter(|x| x.created_frame == frame_ind)
+                .is_some()
+            {
+                balls.remove(&actor.actor_id);
+            }
+        }
+
+        // The team is more reliable than proximity, so it is considered first
+        for update in &frame.updated_actors {
+            let team = match update.attribute {
+                Attribute::Byte(team) => team,
+                _ => continue,
+            };
+
+            if tracker.object_name(update.object_id).ok() != Some(HIT_TEAM) {
+                continue;
+            }
+
+            let ball = match tracker.actor(update.actor_id) {
+                Some(ball) if is_ball(ball) => ball,
+                _ => continue,
+            };
+
+            let state = balls.entry(update.actor_id).or_default();
+            if state.hit_team.replace(team) == Some(team) {
+                continue;
+            }
+
+            // The touch may have already been detected from the ball's velocity
+            let team = i32::from(team);
+            let last_touch = state.last_touch.map(|x| &touches[x]);
+            if last_touch
+                .filter(|x| x.team == team && frame.time - x.time <= TOUCH_COOLDOWN)
+                .is_some()
+            {
+                continue;
+            }
+
+            let toucher = rigid_body(ball)
+                .and_then(|body| closest_player(resolver, Some(team), &body.location))
+                .map(|(car, player, _)| (car, player));
+
+            state.last_touch = Some(touches.len());
+            touches.push(Touch {
+                frame: frame_ind,
+                time: frame.time,
+                ball: update.actor_id,
+                team,
+                car: toucher.map(|(car, _)| car),
+                player: toucher.map(|(_, player)| player.actor_id),
+                player_name: toucher.and_then(|(_, player)| player.name.clone()),
+            });
+        }
+
+        for update in &frame.updated_actors {
+            let body = match update.attribute {
+                Attribute::RigidBody(body) => body,
+                _ => continue,
+            };
+
+            if tracker
+                .actor(update.actor_id)
+                .filter(|x| is_ball(x))
+                .is_none()
+            {
+                continue;
+            }
+
+            // The velocity isn't replicated while the ball is asleep
+            let velocity = body.linear_velocity.unwrap_or(ZERO);
+            let state = balls.entry(update.actor_id).or_default();
+            let change = match state.velocity.replace(velocity) {
+                Some(previous) => previous.distance(&velocity) * velocity_scale,
+                None => continue,
+            };
+
+            if change < VELOCITY_CHANGE {
+                continue;
+            }
+
+            let (car, player) = match closest_player(resolver, None, &body.location) {
+                Some((car, player, distance)) if distance * location_scale <= TOUCH_DISTANCE => {
+                    (car, player)
+                }
+                _ => continue,
+            };
+
+            let team = match player.team {
+                Some(team) => team,
+                None => continue,
+            };
+
+            let last_touch = state.last_touch.map(|x| &touches[x]);
+            let is_same_touch = last_touch
+                .filter(|x| {
+                    x.frame == frame_ind
+                        || (x.car == Some(car) && frame.time - x.time <= TOUCH_COOLDOWN)
+                })
+                .is_some();
+
+            if is_same_touch {
+                continue;
+            }
+
+            state.last_touch = Some(touches.len());
+            touches.push(Touch {
+                frame: frame_ind,
+                time: frame.time,
+                ball: update.actor_id,
+                team,
+                car: Some(car),
+                player: Some(player.actor_id),
+                player_name: player.name.clone(),
+            });
+        }
+    }
+}
+
+/// Older replays replicate locations in hundredths of an unreal unit and velocities in tenths.
+/// Returns the multipliers to convert them to unreal units.
+pub(crate) fn unreal_units(net_version: Option<i32>) -> (f32, f32) {
+    if net_version.filter(|&x| x >= 5).is_some() {
+        (1.0, 1.0)
+    } else {
+        (100.0, 10.0)
+    }
+}
+
+pub(crate) fn is_ball(actor: &ActorState<'_>) -> bool {
+    actor
+        .class_name
+        .filter(|x| x.starts_with(BALL_CLASS_PREFIX))
+        .is_some()
+}
+
+/// The latest rigid body of the actor
+pub(crate) fn rigid_body(actor: &ActorState<'_>) -> Option<RigidBody> {
+    match actor.attribute(RIGID_BODY) {
+        Some(Attribute::RigidBody(body)) => Some(*body),
+        _ => None,
+    }
+}
+
+/// The car (and its player and distance) that is currently closest to the location, optionally
+/// restricted to a team
+fn closest_player<'a>(
+    resolver: &'a PlayerResolver<'_>,
+    team: Option<i32>,
+    location: &Vector3f,
+) -> Option<(ActorId, &'a Player, f32)> {
+    resolver
+        .players()
+        .iter()
+        .filter(|player| team.is_none() || player.team == team)
+        .filter_map(|player| {
+            let car = player.cars.last().filter(|x| x.end_frame.is_none())?;
+            let body = resolver
+                .tracker()
+                .actor(car.actor_id)
+                .and_then(rigid_body)?;
+            Some((car.actor_id, player, body.location.distance(location)))
+        })
+        .min_by(|a, b| a.2.total_cmp(&b.2))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unreal_units() {
+        assert_eq!(unreal_units(None), (100.0, 10.0));
+        assert_eq!(unreal_units(Some(2)), (100.0, 10.0));
+        assert_eq!(unreal_units(Some(5)), (1.0, 1.0));
+        assert_eq!(unreal_units(Some(10)), (1.0, 1.0));
+    }
+}
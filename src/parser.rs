@@ -40,7 +40,8 @@
 //!  - Skip next 8 bytes (there's debate about what this means, but it doesn't matter)
 //!  - Decode the value based on the value type:
 //!    - "BoolProperty": read byte. Does it equal 1?
-//!    - "ByteProperty": read two strings (unless the first seen is related to steam / ps4)
+//!    - "ByteProperty": read two strings, the enum name and value (unless the first seen is
+//!      related to steam / ps4)
 //!    - "FloatProperty": little endian encoded 32bit float
 //!    - "IntProperty": 32bit signed integer
 //!    - "NameProperty": read string
@@ -195,10 +196,11 @@
 use crate::core_parser::CoreParser;
 use crate::crc::calc_crc;
 use crate::errors::{NetworkError, ParseError};
-use crate::header::{self, Header};
+use crate::header::{self, Header, HeaderScan};
 use crate::models::*;
-use crate::network;
+use crate::network::{self, ClassTables, DecodedFrames, FrameIter, FrameSpans};
 use crate::parsing_utils::{le_f32, le_i32};
+use std::borrow::Cow;
 
 /// Determines under what circumstances the parser should perform the crc check for replay
 /// corruption. Since the crc check is the most time consuming part when parsing the header,
@@ -234,6 +236,10 @@ pub enum NetworkParse {
     /// Attempt to parse the network data, but if unsuccessful ignore the error
     /// and continue parsing
     IgnoreOnError,
+
+    /// Attempt to parse the network data, but if unsuccessful keep the frames that were decoded
+    /// prior to the error and record the error in `Replay::network_error`
+    Partial,
 }
 
 /// The main entry point to parsing replays in boxcars. Allows one to customize parsing options,
@@ -243,6 +249,8 @@ pub struct ParserBuilder<'a> {
     data: &'a [u8],
     crc_check: Option<CrcCheck>,
     network_parse: Option<NetworkParse>,
+    tables: Option<&'a ClassTables>,
+    threads: Option<usize>,
 }
 
 impl<'a> ParserBuilder<'a> {
@@ -251,6 +259,8 @@ impl<'a> ParserBuilder<'a> {
             data,
             crc_check: None,
             network_parse: None,
+            tables: None,
+            threads: None,
         }
     }
 
@@ -289,19 +299,118 @@ impl<'a> ParserBuilder<'a> {
         self
     }
 
+    pub fn partial_network_data_on_error(mut self) -> ParserBuilder<'a> {
+        self.network_parse = Some(NetworkParse::Partial);
+        self
+    }
+
     pub fn with_network_parse(mut self, parse: NetworkParse) -> ParserBuilder<'a> {
         self.network_parse = Some(parse);
         self
     }
 
+    /// Decode the network data with additional object mappings that extend or override the
+    /// built-in tables
+    pub fn with_tables(mut self, tables: &'a ClassTables) -> ParserBuilder<'a> {
+        self.tables = Some(tables);
+        self
+    }
+
+    /// Decode the network data on the given number of threads by splitting it at the keyframes.
+    /// The decoded frames are identical to decoding on a single thread, which is the default.
+    pub fn with_network_threads(mut self, threads: usize) -> ParserBuilder<'a> {
+        self.threads = Some(threads);
+        self
+    }
+
     pub fn parse(self) -> Result<Replay, ParseError> {
         let mut parser = Parser::new(
             self.data,
             self.crc_check.unwrap_or(CrcCheck::OnError),
             self.network_parse.unwrap_or(NetworkParse::IgnoreOnError),
+            self.tables,
         );
+        parser.threads = self.threads.unwrap_or(1);
+        parser.parse().map(|(replay, _)| replay)
+    }
+
+    /// Parses the replay and records where each frame, new actor, and attribute is decoded from
+    /// in the network data. The spans are parallel to the replay's network frames and are `None`
+    /// when the network data wasn't decoded. When streaming, see `FrameIter::with_bit_spans`
+    /// instead.
+    ///
+    /// ```
+    /// use boxcars::ParserBuilder;
+    ///
+    /// let data = include_bytes!("../assets/replays/good/rumble.replay");
+    /// let (replay, spans) = ParserBuilder::new(&data[..])
+    ///     .must_parse_network_data()
+    ///     .parse_with_bit_spans()
+    ///     .unwrap();
+    ///
+    /// let frames = replay.network_frames.unwrap().frames;
+    /// let spans = spans.unwrap();
+    /// assert_eq!(spans.len(), frames.len());
+    ///
+    /// // Frames are laid out back to back
+    /// assert_eq!(spans[0].frame.end, spans[1].frame.start);
+    /// assert_eq!(spans[1].updated_actors.len(), frames[1].updated_actors.len());
+    /// ```
+    pub fn parse_with_bit_spans(self) -> Result<(Replay, Option<Vec<FrameSpans>>), ParseError> {
+        let mut parser = Parser::new(
+            self.data,
+            self.crc_check.unwrap_or(CrcCheck::OnError),
+            self.network_parse.unwrap_or(NetworkParse::IgnoreOnError),
+            self.tables,
+        );
+        parser.threads = self.threads.unwrap_or(1);
+        parser.bit_spans = true;
         parser.parse()
     }
+
+    /// Parses only the header section, for when a few header properties are needed from many
+    /// replays. The body and footer are never read, only the header crc is checked (as
+    /// configured), and the network parse option is ignored. The header properties are decoded on
+    /// demand through the returned `HeaderScan`.
+    ///
+    /// ```
+    /// use boxcars::{HeaderProp, ParserBuilder};
+    ///
+    /// let data = include_bytes!("../assets/replays/good/rumble.replay");
+    /// let header = ParserBuilder::new(&data[..])
+    ///     .always_check_crc()
+    ///     .parse_header()
+    ///     .unwrap();
+    ///
+    /// assert_eq!(header.game_type, "TAGame.Replay_Soccar_TA");
+    ///
+    /// let props = header.properties_for(&["MapName", "NumFrames"]).unwrap();
+    /// assert_eq!(props[0], Some(HeaderProp::Name(String::from("stadium_foggy_p"))));
+    /// assert_eq!(props[1].as_ref().and_then(|x| x.as_i32()), Some(7744));
+    /// ```
+    pub fn parse_header(self) -> Result<HeaderScan<'a>, ParseError> {
+        let mut parser = Parser::new(
+            self.data,
+            self.crc_check.unwrap_or(CrcCheck::OnError),
+            NetworkParse::Never,
+            self.tables,
+        );
+        parser.scan_header()
+    }
+
+    /// Parses the header and body of the replay, but instead of decoding all the network data
+    /// upfront, returns an iterator that decodes one frame at a time. The returned replay will
+    /// not contain any network frames. The network parse option is ignored, as it is up to the
+    /// caller how to handle errors from the iterator.
+    pub fn parse_streaming(self) -> Result<(Replay, FrameIter<'a>), ParseError> {
+        let mut parser = Parser::new(
+            self.data,
+            self.crc_check.unwrap_or(CrcCheck::OnError),
+            self.network_parse.unwrap_or(NetworkParse::IgnoreOnError),
+            self.tables,
+        );
+        parser.parse_streaming()
+    }
 }
 
 /// Intermediate parsing structure for the body / footer
@@ -319,24 +428,158 @@ pub struct ReplayBody<'a> {
     pub network_data: &'a [u8],
 }
 
+/// The header and body of a replay prior to decoding the network data
+struct ReplaySections<'a> {
+    header_size: i32,
+    header_crc: u32,
+    header: Header,
+    content_size: i32,
+    content_crc: u32,
+    body: ReplayBody<'a>,
+}
+
+impl<'a> ReplaySections<'a> {
+    fn into_replay(
+        self,
+        network_frames: Option<NetworkFrames>,
+        network_error: Option<NetworkError>,
+    ) -> Replay {
+        Replay {
+            header_size: self.header_size,
+            header_crc: self.header_crc,
+            major_version: self.header.major_version,
+            minor_version: self.header.minor_version,
+            net_version: self.header.net_version,
+            game_type: self.header.game_type,
+            properties: self.header.properties,
+            content_size: self.content_size,
+            content_crc: self.content_crc,
+            network_frames,
+            network_error,
+            levels: self.body.levels,
+            keyframes: self.body.keyframes,
+            debug_info: self.body.debug_info,
+            tick_marks: self.body.tick_marks,
+            packages: self.body.packages,
+            objects: self.body.objects,
+            names: self.body.names,
+            class_indices: self.body.class_indices,
+            net_cache: self.body.net_cache,
+        }
+    }
+}
+
+/// Performs a crc check of a section as configured, given the result of parsing it
+pub(crate) fn check_crc<T>(
+    check: CrcCheck,
+    data: &[u8],
+    crc: u32,
+    section: &str,
+    result: Result<T, ParseError>,
+) -> Result<T, ParseError> {
+    match check {
+        CrcCheck::Always => {
+            let actual = calc_crc(data);
+            if actual != crc {
+                Err(ParseError::CrcMismatch(crc, actual))
+            } else {
+                result
+            }
+        }
+        CrcCheck::OnError => result.map_err(|e| -> ParseError {
+            let actual = calc_crc(data);
+            if actual != crc {
+                ParseError::CorruptReplay(String::from(section), Box::new(e))
+            } else {
+                e
+            }
+        }),
+        CrcCheck::Never => result,
+    }
+}
+
 /// Holds the current state of parsing a replay
 #[derive(Debug, Clone, PartialEq)]
 pub struct Parser<'a> {
     core: CoreParser<'a>,
     crc_check: CrcCheck,
     network_parse: NetworkParse,
+    tables: Option<&'a ClassTables>,
+    threads: usize,
+    bit_spans: bool,
 }
 
 impl<'a> Parser<'a> {
-    fn new(data: &'a [u8], crc_check: CrcCheck, network_parse: NetworkParse) -> Self {
+    fn new(
+        data: &'a [u8],
+        crc_check: CrcCheck,
+        network_parse: NetworkParse,
+        tables: Option<&'a ClassTables>,
+    ) -> Self {
         Parser {
             core: CoreParser::new(data),
             crc_check,
             network_parse,
+            tables,
+            threads: 1,
+            bit_spans: false,
         }
     }
 
-    fn parse(&mut self) -> Result<Replay, ParseError> {
+    fn parse(&mut self) -> Result<(Replay, Option<Vec<FrameSpans>>), ParseError> {
+        let sections = self.parse_sections()?;
+        let mut network_error = None;
+        let network: Option<DecodedFrames> = match self.network_parse {
+            NetworkParse::Always => Some(
+                self.parse_network(&sections.header, &sections.body)
+                    .map_err(|x| ParseError::NetworkError(Box::new(x)))?,
+            ),
+            NetworkParse::IgnoreOnError => self
+                .parse_network(&sections.header, &sections.body)
+                .map_err(|x| ParseError::NetworkError(Box::new(x)))
+                .ok(),
+            NetworkParse::Partial => {
+                let default_tables = ClassTables::default();
+                let tables = self.tables.unwrap_or(&default_tables);
+                let (frames, error) = network::parse_partial(
+                    &sections.header,
+                    &sections.body,
+                    tables,
+                    self.threads,
+                    self.bit_spans,
+                );
+                network_error = error;
+                Some(frames)
+            }
+            NetworkParse::Never => None,
+        };
+
+        let (network, spans) = match network {
+            Some(DecodedFrames { frames, spans }) => (Some(NetworkFrames { frames }), spans),
+            None => (None, None),
+        };
+
+        Ok((sections.into_replay(network, network_error), spans))
+    }
+
+    fn parse_streaming(&mut self) -> Result<(Replay, FrameIter<'a>), ParseError> {
+        let sections = self.parse_sections()?;
+        let default_tables = ClassTables::default();
+        let tables = self.tables.unwrap_or(&default_tables);
+        // The iterator outlives the body, whose objects are moved into the replay
+        let objects = Cow::Owned(sections.body.objects.clone());
+        let frames = network::frame_iter(
+            &sections.header,
+            &sections.body,
+            objects,
+            tables,
+            self.bit_spans,
+        )
+        .map_err(|x| ParseError::NetworkError(Box::new(x)))?;
+        Ok((sections.into_replay(None, None), frames))
+    }
+
+    fn parse_sections(&mut self) -> Result<ReplaySections<'a>, ParseError> {
         let header_size = self.core.take_i32("header size")?;
         let header_crc = self.core.take_u32("header crc")?;
 
@@ -356,38 +599,32 @@ impl<'a> Parser<'a> {
 
         let body = self.crc_section(content_data, content_crc as u32, "body", Self::parse_body)?;
 
-        let network: Option<NetworkFrames> = match self.network_parse {
-            NetworkParse::Always => Some(
-                self.parse_network(&header, &body)
-                    .map_err(|x| ParseError::NetworkError(Box::new(x)))?,
-            ),
-            NetworkParse::IgnoreOnError => self
-                .parse_network(&header, &body)
-                .map_err(|x| ParseError::NetworkError(Box::new(x)))
-                .ok(),
-            NetworkParse::Never => None,
-        };
-
-        Ok(Replay {
+        Ok(ReplaySections {
             header_size,
             header_crc,
-            major_version: header.major_version,
-            minor_version: header.minor_version,
-            net_version: header.net_version,
-            game_type: header.game_type,
-            properties: header.properties,
+            header,
             content_size,
             content_crc,
-            network_frames: network,
-            levels: body.levels,
-            keyframes: body.keyframes,
-            debug_info: body.debug_info,
-            tick_marks: body.tick_marks,
-            packages: body.packages,
-            objects: body.objects,
-            names: body.names,
-            class_indices: body.class_indices,
-            net_cache: body.net_cache,
+            body,
+        })
+    }
+
+    fn scan_header(&mut self) -> Result<HeaderScan<'a>, ParseError> {
+        let header_size = self.core.take_i32("header size")?;
+        let header_crc = self.core.take_u32("header crc")?;
+
+        let header_data = self.core.view_data(header_size as usize).map_err(|e| {
+            ParseError::ParseError("header data", self.core.bytes_read(), Box::new(e))
+        })?;
+
+        // Once the crc has been verified, the properties decoded later on can't be corrupt
+        let property_check = match self.crc_check {
+            CrcCheck::OnError => CrcCheck::OnError,
+            CrcCheck::Always | CrcCheck::Never => CrcCheck::Never,
+        };
+
+        self.crc_section(header_data, header_crc, "header", |parser| {
+            header::scan_header(&mut parser.core, header_data, header_crc, property_check)
         })
     }
 
@@ -395,8 +632,10 @@ impl<'a> Parser<'a> {
         &mut self,
         header: &Header,
         body: &ReplayBody<'_>,
-    ) -> Result<NetworkFrames, NetworkError> {
-        network::parse(header, body)
+    ) -> Result<DecodedFrames, NetworkError> {
+        let default_tables = ClassTables::default();
+        let tables = self.tables.unwrap_or(&default_tables);
+        network::parse(header, body, tables, self.threads, self.bit_spans)
     }
 
     fn parse_header(&mut self) -> Result<Header, ParseError> {
@@ -415,26 +654,7 @@ impl<'a> Parser<'a> {
         F: FnMut(&mut Self) -> Result<T, ParseError>,
     {
         let result = f(self);
-
-        match self.crc_check {
-            CrcCheck::Always => {
-                let actual = calc_crc(data);
-                if actual != crc as u32 {
-                    Err(ParseError::CrcMismatch(crc, actual))
-                } else {
-                    result
-                }
-            }
-            CrcCheck::OnError => result.map_err(|e| -> ParseError {
-                let actual = calc_crc(data);
-                if actual != crc as u32 {
-                    ParseError::CorruptReplay(String::from(section), Box::new(e))
-                } else {
-                    e
-                }
-            }),
-            CrcCheck::Never => result,
-        }
+        check_crc(self.crc_check, data, crc, section, result)
     }
 
     fn parse_body(&mut self) -> Result<ReplayBody<'a>, ParseError> {
@@ -566,6 +786,7 @@ mod tests {
             &data[0x12ca..0x12ca + 508],
             CrcCheck::Never,
             NetworkParse::Never,
+            None,
         );
         let frames = parser.parse_keyframe().unwrap();
         assert_eq!(frames.len(), 42);
@@ -580,6 +801,7 @@ mod tests {
             &data[0xf6cce..0xf6d50],
             CrcCheck::Never,
             NetworkParse::Never,
+            None,
         );
         let ticks = parser.parse_tickmarks().unwrap();
 
@@ -595,21 +817,21 @@ mod tests {
 
     #[test]
     fn test_the_parsing_empty() {
-        let mut parser = Parser::new(&[], CrcCheck::Never, NetworkParse::Never);
+        let mut parser = Parser::new(&[], CrcCheck::Never, NetworkParse::Never, None);
         assert!(parser.parse().is_err());
     }
 
     #[test]
     fn test_the_parsing_text_too_long() {
         let data = include_bytes!("../assets/replays/bad/fuzz-string-too-long.replay");
-        let mut parser = Parser::new(&data[..], CrcCheck::Never, NetworkParse::Never);
+        let mut parser = Parser::new(&data[..], CrcCheck::Never, NetworkParse::Never, None);
         assert!(parser.parse().is_err())
     }
 
     #[test]
     fn test_the_parsing_text_too_long2() {
         let data = include_bytes!("../assets/replays/bad/fuzz-string-too-long2.replay");
-        let mut parser = Parser::new(&data[..], CrcCheck::Never, NetworkParse::Always);
+        let mut parser = Parser::new(&data[..], CrcCheck::Never, NetworkParse::Always, None);
         let err = parser.parse().unwrap_err();
         assert!(format!("{}", err).contains("Unexpected size for string: -1912602609"));
     }
@@ -617,21 +839,21 @@ mod tests {
     #[test]
     fn test_fuzz_corpus_slice_index() {
         let data = include_bytes!("../assets/replays/bad/fuzz-slice-index.replay");
-        let mut parser = Parser::new(&data[..], CrcCheck::Never, NetworkParse::Never);
+        let mut parser = Parser::new(&data[..], CrcCheck::Never, NetworkParse::Never, None);
         assert!(parser.parse().is_err())
     }
 
     #[test]
     fn test_the_fuzz_corpus_abs_panic() {
         let data = include_bytes!("../assets/replays/bad/fuzz-corpus.replay");
-        let mut parser = Parser::new(&data[..], CrcCheck::Never, NetworkParse::Never);
+        let mut parser = Parser::new(&data[..], CrcCheck::Never, NetworkParse::Never, None);
         assert!(parser.parse().is_err())
     }
 
     #[test]
     fn test_the_fuzz_corpus_large_list() {
         let data = include_bytes!("../assets/replays/bad/fuzz-list-too-large.replay");
-        let mut parser = Parser::new(&data[..], CrcCheck::Never, NetworkParse::Never);
+        let mut parser = Parser::new(&data[..], CrcCheck::Never, NetworkParse::Never, None);
         let err = parser.parse().unwrap_err();
         assert!(format!("{}", err)
             .starts_with("Could not decode replay debug info at offset (1010894): list of size"));
@@ -640,7 +862,7 @@ mod tests {
     #[test]
     fn test_the_fuzz_corpus_large_list_on_error_crc() {
         let data = include_bytes!("../assets/replays/bad/fuzz-list-too-large.replay");
-        let mut parser = Parser::new(&data[..], CrcCheck::OnError, NetworkParse::Never);
+        let mut parser = Parser::new(&data[..], CrcCheck::OnError, NetworkParse::Never, None);
         let err = parser.parse().unwrap_err();
         assert_eq!(
             "Failed to parse body and crc check failed. Replay is corrupt",
@@ -654,7 +876,7 @@ mod tests {
     #[test]
     fn test_the_fuzz_corpus_large_list_always_crc() {
         let data = include_bytes!("../assets/replays/bad/fuzz-list-too-large.replay");
-        let mut parser = Parser::new(&data[..], CrcCheck::Always, NetworkParse::Never);
+        let mut parser = Parser::new(&data[..], CrcCheck::Always, NetworkParse::Never, None);
         let err = parser.parse().unwrap_err();
         assert_eq!(
             "Crc mismatch. Expected 3765941959 but received 1314727725",
@@ -666,7 +888,7 @@ mod tests {
     #[test]
     fn test_the_fuzz_object_id_too_large() {
         let data = include_bytes!("../assets/replays/bad/fuzz-large-object-id.replay");
-        let mut parser = Parser::new(&data[..], CrcCheck::Never, NetworkParse::Always);
+        let mut parser = Parser::new(&data[..], CrcCheck::Never, NetworkParse::Always, None);
         let err = parser.parse().unwrap_err();
         assert_eq!("Object Id of 1547 exceeds range", format!("{}", err));
         assert!(err.source().is_some());
@@ -675,7 +897,7 @@ mod tests {
     #[test]
     fn test_the_fuzz_too_many_frames() {
         let data = include_bytes!("../assets/replays/bad/fuzz-too-many-frames.replay");
-        let mut parser = Parser::new(&data[..], CrcCheck::Never, NetworkParse::Always);
+        let mut parser = Parser::new(&data[..], CrcCheck::Never, NetworkParse::Always, None);
         let err = parser.parse().unwrap_err();
         assert_eq!("Too many frames to decode: 738197735", format!("{}", err));
         assert!(err.source().is_some());
@@ -687,7 +909,7 @@ mod tests {
 
         // Changing this byte won't make the parsing fail but will make the crc check fail
         data[4775] = 100;
-        let mut parser = Parser::new(&data[..], CrcCheck::Always, NetworkParse::Never);
+        let mut parser = Parser::new(&data[..], CrcCheck::Always, NetworkParse::Never, None);
         let res = parser.parse();
         assert!(res.is_err());
         assert_eq!(
@@ -695,7 +917,7 @@ mod tests {
             format!("{}", res.unwrap_err())
         );
 
-        parser = Parser::new(&data[..], CrcCheck::OnError, NetworkParse::Never);
+        parser = Parser::new(&data[..], CrcCheck::OnError, NetworkParse::Never, None);
         assert!(parser.parse().is_ok());
     }
 }
@@ -47,3 +47,23 @@ pub fn decode_windows1252(input: &[u8]) -> Result<String, ParseError> {
         Ok(String::from(s))
     }
 }
+
+/// The inverse of `decode_windows1252` and `decode_utf16`. Returns the length prefix of the
+/// string and the encoded bytes (including the null terminator). Windows-1252 is preferred but if
+/// the string contains a character not found in windows-1252 it is encoded as UTF-16, which is
+/// denoted by a negative length.
+pub fn encode_text_bytes(input: &str) -> (i32, Vec<u8>) {
+    let (data, _, had_errors) = WINDOWS_1252.encode(input);
+    if !had_errors {
+        let mut data = data.into_owned();
+        data.push(0);
+        (data.len() as i32, data)
+    } else {
+        let mut data: Vec<u8> = input
+            .encode_utf16()
+            .flat_map(|x| x.to_le_bytes().to_vec())
+            .collect();
+        data.extend_from_slice(&[0, 0]);
+        (-(data.len() as i32 / 2), data)
+    }
+}
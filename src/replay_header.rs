@@ -0,0 +1,323 @@
+//! # Replay Header
+//!
+//! The header properties are decoded as loosely typed key value pairs (`HeaderProp`), as there is
+//! no spec that dictates which properties exist. In practice, there are a handful of well known
+//! properties (goals, highlights, player stats, and match metadata) that are interpreted here into
+//! strongly typed structures.
+//!
+//! Top level properties are optional, as they are omitted when not applicable (eg: `Team0Score` is
+//! absent when team 0 doesn't score). When a property is present but doesn't have the expected
+//! type, or an element of an array is missing a field, an error is returned.
+
+use crate::errors::HeaderError;
+use crate::models::{HeaderProp, Replay};
+
+/// Strongly typed view of the well known header properties
+#[derive(Serialize, PartialEq, Debug, Clone)]
+pub struct ReplayHeader {
+    pub replay_name: Option<String>,
+    pub map_name: Option<String>,
+    pub match_type: Option<String>,
+
+    /// When the replay was recorded. The format has changed over time (eg: `2016-04-13 13-58-00`
+    /// and `2016-05-08:16-26`), so it is left unparsed.
+    pub date: Option<String>,
+    pub team_size: Option<i32>,
+    pub team0_score: Option<i32>,
+    pub team1_score: Option<i32>,
+    pub num_frames: Option<i32>,
+    pub record_fps: Option<f32>,
+    pub goals: Vec<Goal>,
+    pub highlights: Vec<Highlight>,
+    pub player_stats: Vec<PlayerStats>,
+}
+
+/// A goal as recorded in the header's `Goals` property
+#[derive(Serialize, PartialEq, Debug, Clone)]
+pub struct Goal {
+    pub frame: i32,
+    pub player_name: String,
+    pub player_team: i32,
+}
+
+/// A highlight as recorded in the header's `HighLights` property. The car and ball names are
+/// object instance names (eg: `Car_TA_0` and `Ball_TA_1`), with a ball name of `None` for
+/// highlights that aren't ball related (which the replay records as the name `None`).
+#[derive(Serialize, PartialEq, Debug, Clone)]
+pub struct Highlight {
+    pub frame: i32,
+    pub car_name: String,
+    pub ball_name: Option<String>,
+}
+
+/// A player's end of game stats as recorded in the header's `PlayerStats` property
+#[derive(Serialize, PartialEq, Debug, Clone)]
+pub struct PlayerStats {
+    pub name: String,
+
+    /// The online platform of the player (eg: `OnlinePlatform_Steam`)
+    pub platform: Option<String>,
+    pub online_id: Option<u64>,
+    pub team: i32,
+    pub score: i32,
+    pub goals: i32,
+    pub assists: i32,
+    pub saves: i32,
+    pub shots: i32,
+    pub is_bot: bool,
+}
+
+impl ReplayHeader {
+    /// Interprets the given header properties
+    pub fn from_properties(properties: &[(String, HeaderProp)]) -> Result<Self, HeaderError> {
+        let props = Properties::new(properties, "");
+        Ok(ReplayHeader {
+            replay_name: props.string("ReplayName")?,
+            map_name: props.string("MapName")?,
+            match_type: props.string("MatchType")?,
+            date: props.string("Date")?,
+            team_size: props.i32("TeamSize")?,
+            team0_score: props.i32("Team0Score")?,
+            team1_score: props.i32("Team1Score")?,
+            num_frames: props.i32("NumFrames")?,
+            record_fps: props.float("RecordFPS")?,
+            goals: props.array("Goals", goal)?,
+            highlights: props.array("HighLights", highlight)?,
+            player_stats: props.array("PlayerStats", player_stats)?,
+        })
+    }
+}
+
+impl Replay {
+    /// Interprets the well known header properties into a `ReplayHeader`
+    ///
+    /// ```
+    /// let data = include_bytes!("../assets/replays/good/rumble.replay");
+    /// let replay = boxcars::ParserBuilder::new(&data[..])
+    ///     .never_parse_network_data()
+    ///     .parse()
+    ///     .unwrap();
+    ///
+    /// let header = replay.typed_header().unwrap();
+    /// assert_eq!(header.team0_score, Some(5));
+    /// assert_eq!(header.goals[0].player_name, "Cakeboss");
+    /// assert_eq!(header.player_stats[0].name, "comagoosie");
+    /// ```
+    pub fn typed_header(&self) -> Result<ReplayHeader, HeaderError> {
+        ReplayHeader::from_properties(&self.properties)
+    }
+}
+
+fn goal(props: &Properties<'_>) -> Result<Goal, HeaderError> {
+    Ok(Goal {
+        frame: props.required("frame", HeaderProp::as_i32, "int")?,
+        player_name: props.required("PlayerName", to_string, "string")?,
+        player_team: props.required("PlayerTeam", HeaderProp::as_i32, "int")?,
+    })
+}
+
+fn highlight(props: &Properties<'_>) -> Result<Highlight, HeaderError> {
+    Ok(Highlight {
+        frame: props.required("frame", HeaderProp::as_i32, "int")?,
+        car_name: props.required("CarName", to_string, "string")?,
+        ball_name: props.string("BallName")?.filter(|x| x != "None"),
+    })
+}
+
+fn player_stats(props: &Properties<'_>) -> Result<PlayerStats, HeaderError> {
+    Ok(PlayerStats {
+        name: props.required("Name", to_string, "string")?,
+        platform: props.optional("Platform", |x| x.as_byte().map(String::from), "byte")?,
+        online_id: props.optional("OnlineID", HeaderProp::as_u64, "qword")?,
+        team: props.required("Team", HeaderProp::as_i32, "int")?,
+        score: props.required("Score", HeaderProp::as_i32, "int")?,
+        goals: props.required("Goals", HeaderProp::as_i32, "int")?,
+        assists: props.required("Assists", HeaderProp::as_i32, "int")?,
+        saves: props.required("Saves", HeaderProp::as_i32, "int")?,
+        shots: props.required("Shots", HeaderProp::as_i32, "int")?,
+        is_bot: props.required("bBot", HeaderProp::as_bool, "bool")?,
+    })
+}
+
+fn to_string(prop: &HeaderProp) -> Option<String> {
+    prop.as_string().map(String::from)
+}
+
+/// A set of properties and the path to them, so that errors can pinpoint the offending property
+struct Properties<'a> {
+    properties: &'a [(String, HeaderProp)],
+    path: &'a str,
+}
+
+impl<'a> Properties<'a> {
+    fn new(properties: &'a [(String, HeaderProp)], path: &'a str) -> Self {
+        Properties { properties, path }
+    }
+
+    fn path_of(&self, key: &str) -> String {
+        format!("{}{}", self.path, key)
+    }
+
+    fn optional<T, F>(
+        &self,
+        key: &str,
+        f: F,
+        expected: &'static str,
+    ) -> Result<Option<T>, HeaderError>
+    where
+        F: FnOnce(&'a HeaderProp) -> Option<T>,
+    {
+        match self.properties.iter().find(|(k, _)| k == key) {
+            Some((_, prop)) => f(prop)
+                .map(Some)
+                .ok_or_else(|| HeaderError::UnexpectedType {
+                    property: self.path_of(key),
+                    expected,
+                }),
+            None => Ok(None),
+        }
+    }
+
+    fn required<T, F>(&self, key: &str, f: F, expected: &'static str) -> Result<T, HeaderError>
+    where
+        F: FnOnce(&'a HeaderProp) -> Option<T>,
+    {
+        self.optional(key, f, expected)?
+            .ok_or_else(|| HeaderError::MissingProperty(self.path_of(key)))
+    }
+
+    fn string(&self, key: &str) -> Result<Option<String>, HeaderError> {
+        self.optional(key, to_string, "string")
+    }
+
+    fn i32(&self, key: &str) -> Result<Option<i32>, HeaderError> {
+        self.optional(key, HeaderProp::as_i32, "int")
+    }
+
+    fn float(&self, key: &str) -> Result<Option<f32>, HeaderError> {
+        self.optional(key, HeaderProp::as_float, "float")
+    }
+
+    /// Interprets each element of an array property. An absent array is treated as empty.
+    fn array<T, F>(&self, key: &str, f: F) -> Result<Vec<T>, HeaderError>
+    where
+        F: Fn(&Properties<'_>) -> Result<T, HeaderError>,
+    {
+        let arr = self.optional(key, HeaderProp::as_array, "array")?;
+        arr.into_iter()
+            .flatten()
+            .enumerate()
+            .map(|(i, el)| {
+                let path = format!("{}[{}].", self.path_of(key), i);
+                f(&Properties::new(el, &path))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn prop(key: &str, val: HeaderProp) -> (String, HeaderProp) {
+        (String::from(key), val)
+    }
+
+    #[test]
+    fn typed_header_empty() {
+        let header = ReplayHeader::from_properties(&[]).unwrap();
+        assert_eq!(header.replay_name, None);
+        assert_eq!(header.goals, Vec::new());
+        assert_eq!(header.player_stats, Vec::new());
+    }
+
+    #[test]
+    fn typed_header_goals() {
+        let props = vec![prop(
+            "Goals",
+            HeaderProp::Array(vec![vec![
+                prop("frame", HeaderProp::Int(441)),
+                prop("PlayerName", HeaderProp::Str(String::from("Cakeboss"))),
+                prop("PlayerTeam", HeaderProp::Int(1)),
+            ]]),
+        )];
+
+        let header = ReplayHeader::from_properties(&props).unwrap();
+        assert_eq!(
+            header.goals,
+            vec![Goal {
+                frame: 441,
+                player_name: String::from("Cakeboss"),
+                player_team: 1,
+            }]
+        );
+    }
+
+    #[test]
+    fn typed_header_highlights() {
+        let highlight = |ball: &str| {
+            vec![
+                prop("frame", HeaderProp::Int(100)),
+                prop("CarName", HeaderProp::Name(String::from("Car_TA_0"))),
+                prop("BallName", HeaderProp::Name(String::from(ball))),
+            ]
+        };
+        let props = vec![prop(
+            "HighLights",
+            HeaderProp::Array(vec![highlight("Ball_TA_1"), highlight("None")]),
+        )];
+
+        let header = ReplayHeader::from_properties(&props).unwrap();
+        let balls: Vec<_> = header
+            .highlights
+            .iter()
+            .map(|x| x.ball_name.as_deref())
+            .collect();
+        assert_eq!(balls, vec![Some("Ball_TA_1"), None]);
+    }
+
+    #[test]
+    fn typed_header_wrong_type() {
+        let props = vec![prop("Team0Score", HeaderProp::Str(String::from("5")))];
+        let err = ReplayHeader::from_properties(&props).unwrap_err();
+        assert_eq!(
+            err,
+            HeaderError::UnexpectedType {
+                property: String::from("Team0Score"),
+                expected: "int",
+            }
+        );
+        assert_eq!(
+            err.to_string(),
+            "Header property Team0Score was expected to be of type int"
+        );
+    }
+
+    #[test]
+    fn typed_header_missing_nested() {
+        let props = vec![prop(
+            "Goals",
+            HeaderProp::Array(vec![
+                vec![
+                    prop("frame", HeaderProp::Int(441)),
+                    prop("PlayerName", HeaderProp::Str(String::from("Cakeboss"))),
+                    prop("PlayerTeam", HeaderProp::Int(1)),
+                ],
+                vec![
+                    prop("frame", HeaderProp::Int(1738)),
+                    prop("PlayerTeam", HeaderProp::Int(0)),
+                ],
+            ]),
+        )];
+
+        let err = ReplayHeader::from_properties(&props).unwrap_err();
+        assert_eq!(
+            err,
+            HeaderError::MissingProperty(String::from("Goals[1].PlayerName"))
+        );
+        assert_eq!(
+            err.to_string(),
+            "Header property Goals[1].PlayerName is missing"
+        );
+    }
+}
@@ -0,0 +1,350 @@
+//! # Error reports
+//!
+//! The message of a `ParseError` is meant for people, so tools that collect and triage failing
+//! replays would need to scrape it. An `ErrorReport` instead captures the details of the failure
+//! as fields that can be serialized: the sections being decoded and the byte offset into the
+//! replay, and for network data errors the frame, the bit position within the network data, the
+//! actor and attribute that failed, the updates decoded just before the failure, and the replay's
+//! version.
+
+use crate::data::ATTRIBUTES;
+use crate::errors::{FrameContext, FrameError, NetworkError, ParseError};
+use crate::network::attributes::Attribute;
+use crate::network::{ActorId, ObjectId, StreamId, UpdatedAttribute};
+
+/// A serializable description of where and why a replay failed to parse
+#[derive(Serialize, PartialEq, Debug, Clone)]
+pub struct ErrorReport {
+    /// The error's message
+    pub message: String,
+
+    /// The sections of the replay being decoded, from outermost to innermost (eg: `header data`,
+    /// `header properties`)
+    pub sections: Vec<String>,
+
+    /// The crc check failed, so the replay is corrupt
+    pub corrupt: bool,
+
+    /// The byte offset into the replay of the innermost section
+    pub byte_offset: Option<i32>,
+
+    /// The bit position in the network data where decoding failed
+    pub bit_position: Option<usize>,
+
+    /// The index of the network frame that failed to decode
+    pub frame: Option<usize>,
+
+    pub actor_id: Option<ActorId>,
+
+    /// The object of the actor
+    pub object_id: Option<ObjectId>,
+    pub object_name: Option<String>,
+
+    /// The attribute's stream id on the actor's object
+    pub stream_id: Option<StreamId>,
+
+    /// The attribute's name, resolved through the actor object's attributes
+    pub attribute_name: Option<String>,
+
+    /// The attribute is known to boxcars. An unknown attribute is likely due to a Rocket League
+    /// update.
+    pub attribute_known: Option<bool>,
+
+    /// The most recent updates decoded before the failure, oldest first
+    pub recent_updates: Vec<ReportedUpdate>,
+
+    pub major_version: Option<i32>,
+    pub minor_version: Option<i32>,
+    pub net_version: Option<i32>,
+}
+
+/// An attribute update that was decoded before the failure
+#[derive(Serialize, PartialEq, Debug, Clone)]
+pub struct ReportedUpdate {
+    pub frame: usize,
+    pub actor_id: ActorId,
+    pub stream_id: StreamId,
+    pub object_id: ObjectId,
+    pub attribute_name: Option<String>,
+    pub attribute: Attribute,
+}
+
+impl ErrorReport {
+    fn new(message: String) -> Self {
+        ErrorReport {
+            message,
+            sections: Vec::new(),
+            corrupt: false,
+            byte_offset: None,
+            bit_position: None,
+            frame: None,
+            actor_id: None,
+            object_id: None,
+            object_name: None,
+            stream_id: None,
+            attribute_name: None,
+            attribute_known: None,
+            recent_updates: Vec::new(),
+            major_version: None,
+            minor_version: None,
+            net_version: None,
+        }
+    }
+
+    fn add_parse_error(&mut self, error: &ParseError, max_updates: usize) {
+        match error {
+            ParseError::ParseError(section, bytes_read, inner) => {
+                self.sections.push(String::from(*section));
+                self.byte_offset = Some(*bytes_read);
+                self.add_parse_error(inner, max_updates);
+            }
+            ParseError::CorruptReplay(section, inner) => {
+                self.sections.push(section.clone());
+                self.corrupt = true;
+                self.add_parse_error(inner, max_updates);
+            }
+            ParseError::CrcMismatch(_, _) => self.corrupt = true,
+            ParseError::NetworkError(inner) => self.add_network_error(inner, max_updates),
+            _ => {}
+        }
+    }
+
+    fn add_network_error(&mut self, error: &NetworkError, max_updates: usize) {
+        self.sections.push(String::from("network data"));
+        match error {
+            NetworkError::ObjectIdOutOfRange(obj) => self.object_id = Some(*obj),
+            NetworkError::FrameError(err, context) => {
+                self.add_frame_error(err, context);
+                self.recent_updates = recent_updates(context, max_updates);
+            }
+            _ => {}
+        }
+    }
+
+    fn add_frame_error(&mut self, error: &FrameError, context: &FrameContext) {
+        self.frame = Some(context.frame);
+        self.bit_position = Some(context.bit_position);
+        self.major_version = Some(context.major_version);
+        self.minor_version = Some(context.minor_version);
+        self.net_version = Some(context.net_version);
+
+        match *error {
+            FrameError::ObjectIdOutOfRange { obj } => self.object_id = Some(obj),
+            FrameError::MissingActor { actor } => self.actor_id = Some(actor),
+            FrameError::MissingCache {
+                actor,
+                actor_object,
+            } => {
+                self.actor_id = Some(actor);
+                self.object_id = Some(actor_object);
+            }
+            FrameError::MissingAttribute {
+                actor,
+                actor_object,
+                attribute_stream,
+            }
+            | FrameError::AttributeError {
+                actor,
+                actor_object,
+                attribute_stream,
+                ..
+            } => {
+                self.actor_id = Some(actor);
+                self.object_id = Some(actor_object);
+                self.stream_id = Some(attribute_stream);
+                self.attribute_name = context
+                    .object_attributes
+                    .get(&actor_object)
+                    .and_then(|x| x.get(&attribute_stream))
+                    .and_then(|x| object_name(context, *x));
+                self.attribute_known = self
+                    .attribute_name
+                    .as_ref()
+                    .map(|x| ATTRIBUTES.contains_key(x.as_str()));
+            }
+            _ => {}
+        }
+
+        self.object_name = self.object_id.and_then(|x| object_name(context, x));
+    }
+}
+
+fn object_name(context: &FrameContext, object_id: ObjectId) -> Option<String> {
+    context.objects.get(usize::from(object_id)).cloned()
+}
+
+/// The last updates of the decoded frames and of the frame that failed
+fn recent_updates(context: &FrameContext, max_updates: usize) -> Vec<ReportedUpdate> {
+    let decoded = context
+        .frames
+        .iter()
+        .enumerate()
+        .flat_map(|(i, frame)| frame.updated_actors.iter().map(move |x| (i, x)));
+    let current = context.updated_actors.iter().map(|x| (context.frame, x));
+    let updates: Vec<(usize, &UpdatedAttribute)> = decoded.chain(current).collect();
+
+    updates[updates.len().saturating_sub(max_updates)..]
+        .iter()
+        .map(|&(frame, update)| ReportedUpdate {
+            frame,
+            actor_id: update.actor_id,
+            stream_id: update.stream_id,
+            object_id: update.object_id,
+            attribute_name: object_name(context, update.object_id),
+            attribute: update.attribute.clone(),
+        })
+        .collect()
+}
+
+impl ParseError {
+    /// Describes the error as an `ErrorReport` with at most `max_updates` of the updates decoded
+    /// before the failure.
+    ///
+    /// ```
+    /// use boxcars::{AttributeTag, ClassTables, ParserBuilder};
+    ///
+    /// // Pretend that boxcars doesn't know how to decode which team last hit the ball
+    /// let tables = ClassTables::new().with_attribute(
+    ///     "TAGame.Ball_TA:HitTeamNum",
+    ///     AttributeTag::NotImplemented,
+    /// );
+    ///
+    /// let data = include_bytes!("../assets/replays/good/rumble.replay");
+    /// let error = ParserBuilder::new(&data[..])
+    ///     .with_tables(&tables)
+    ///     .must_parse_network_data()
+    ///     .parse()
+    ///     .unwrap_err();
+    ///
+    /// let report = error.report(10);
+    /// assert_eq!(
+    ///     report.attribute_name.as_deref(),
+    ///     Some("TAGame.Ball_TA:HitTeamNum")
+    /// );
+    /// assert!(report.frame.is_some());
+    /// assert_eq!(report.recent_updates.len(), 10);
+    /// ```
+    pub fn report(&self, max_updates: usize) -> ErrorReport {
+        let mut report = ErrorReport::new(self.to_string());
+        report.add_parse_error(self, max_updates);
+        report
+    }
+}
+
+impl NetworkError {
+    /// Describes the error as an `ErrorReport` with at most `max_updates` of the updates decoded
+    /// before the failure. Useful for the error of a partially parsed replay.
+    pub fn report(&self, max_updates: usize) -> ErrorReport {
+        let mut report = ErrorReport::new(self.to_string());
+        report.add_network_error(self, max_updates);
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::AttributeError;
+    use crate::network::Frame;
+    use fnv::FnvHashMap;
+
+    fn update(actor: i32, stream: i32) -> UpdatedAttribute {
+        UpdatedAttribute {
+            actor_id: ActorId(actor),
+            stream_id: StreamId(stream),
+            object_id: ObjectId(1),
+            attribute: Attribute::Int(stream),
+        }
+    }
+
+    fn context() -> FrameContext {
+        let mut attributes = FnvHashMap::default();
+        attributes.insert(StreamId(3), ObjectId(1));
+        let mut object_attributes = FnvHashMap::default();
+        object_attributes.insert(ObjectId(0), attributes);
+
+        FrameContext {
+            objects: vec![
+                String::from("Archetypes.Ball.Ball_Default"),
+                String::from("TAGame.Ball_TA:HitTeamNum"),
+            ],
+            object_attributes,
+            frames: vec![Frame {
+                time: 0.0,
+                delta: 0.0,
+                new_actors: Vec::new(),
+                deleted_actors: Vec::new(),
+                updated_actors: vec![update(5, 1), update(5, 2)],
+            }],
+            actors: FnvHashMap::default(),
+            new_actors: Vec::new(),
+            updated_actors: vec![update(5, 4)],
+            frame: 1,
+            bit_position: 1234,
+            major_version: 868,
+            minor_version: 29,
+            net_version: 10,
+        }
+    }
+
+    #[test]
+    fn test_frame_error_report() {
+        let err = FrameError::AttributeError {
+            actor: ActorId(5),
+            actor_object: ObjectId(0),
+            attribute_stream: StreamId(3),
+            error: AttributeError::Unimplemented,
+        };
+
+        let network = NetworkError::FrameError(err, Box::new(context()));
+        let error = ParseError::ParseError(
+            "body",
+            100,
+            Box::new(ParseError::NetworkError(Box::new(network))),
+        );
+
+        let report = error.report(2);
+        assert_eq!(report.sections, vec!["body", "network data"]);
+        assert_eq!(report.byte_offset, Some(100));
+        assert_eq!(report.frame, Some(1));
+        assert_eq!(report.bit_position, Some(1234));
+        assert_eq!(report.actor_id, Some(ActorId(5)));
+        assert_eq!(
+            report.object_name.as_deref(),
+            Some("Archetypes.Ball.Ball_Default")
+        );
+        assert_eq!(report.stream_id, Some(StreamId(3)));
+        assert_eq!(
+            report.attribute_name.as_deref(),
+            Some("TAGame.Ball_TA:HitTeamNum")
+        );
+        assert_eq!(report.attribute_known, Some(true));
+        assert_eq!(report.net_version, Some(10));
+
+        let recent: Vec<_> = report
+            .recent_updates
+            .iter()
+            .map(|x| (x.frame, x.stream_id))
+            .collect();
+        assert_eq!(recent, vec![(0, StreamId(2)), (1, StreamId(4))]);
+    }
+
+    #[test]
+    fn test_corrupt_report() {
+        let error = ParseError::CorruptReplay(
+            String::from("header"),
+            Box::new(ParseError::ParseError(
+                "header properties",
+                40,
+                Box::new(ParseError::ZeroSize),
+            )),
+        );
+
+        let report = error.report(10);
+        assert!(report.corrupt);
+        assert_eq!(report.sections, vec!["header", "header properties"]);
+        assert_eq!(report.byte_offset, Some(40));
+        assert_eq!(report.frame, None);
+        assert!(report.recent_updates.is_empty());
+    }
+}
@@ -1,5 +1,7 @@
+use serde::de::{self, Deserialize, Deserializer};
 use serde::Serializer;
 use std::fmt::Display;
+use std::str::FromStr;
 
 /// For the times when the `Display` string is more appropriate than the default serialization
 /// strategy. This function is useful for 64bit integers, as 64bit integers can't be represented
@@ -14,3 +16,14 @@ where
 {
     serializer.collect_str(data)
 }
+
+/// The inverse of `display_it`: parses the value from its `Display` string
+pub fn parse_it<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+where
+    T: FromStr,
+    T::Err: Display,
+    D: Deserializer<'de>,
+{
+    let s = String::deserialize(deserializer)?;
+    s.parse().map_err(de::Error::custom)
+}
@@ -0,0 +1,308 @@
+//! # Trimming
+//!
+//! A clip of a replay (eg: the seconds leading up to a goal) is cut into a standalone replay by
+//! keeping a range of its network frames. Replays can only be played back from a keyframe, as
+//! keyframes re-announce every live actor and the bulk of their attributes, so the clip starts at
+//! the keyframe at or before the start of the range. Actors that are alive at the keyframe but not
+//! re-announced by it are spawned in the first frame along with their latest attributes.
+//!
+//! The frame indices of the keyframes, tick marks, debug info, and the header's `NumFrames`,
+//! `Goals`, and `HighLights` are rewritten to be relative to the clip, and those outside of the
+//! clip are dropped. The sizes and crcs are recomputed when the clip is written with
+//! `ReplayWriter`.
+
+use crate::errors::TrimError;
+use crate::models::{HeaderProp, NetworkFrames, Replay};
+use crate::network::{ActorId, Frame, NewActor, StreamId, UpdatedAttribute};
+use fnv::FnvHashMap;
+use std::ops::Range;
+
+/// The network frames to keep
+#[derive(Debug, Clone, PartialEq)]
+pub enum TrimRange {
+    /// Frame indices, where the end is exclusive
+    Frames(Range<usize>),
+
+    /// Frame times in seconds (see `Frame::time`), where the end is exclusive
+    Time(Range<f32>),
+}
+
+/// An actor's spawn and the latest value of each of its attributes
+#[derive(Debug, Clone)]
+struct LiveActor {
+    spawn: NewActor,
+    attributes: FnvHashMap<StreamId, UpdatedAttribute>,
+}
+
+/// Cuts the range of network frames out of the replay into a new replay, which can be written with
+/// `ReplayWriter`.
+///
+/// ```
+/// use boxcars::{trim, ParserBuilder, ReplayWriter, TrimRange};
+///
+/// let data = include_bytes!("../assets/replays/good/rumble.replay");
+/// let replay = ParserBuilder::new(&data[..])
+///     .must_parse_network_data()
+///     .parse()
+///     .unwrap();
+///
+/// // The 10 seconds before the first goal
+/// let header = replay.typed_header().unwrap();
+/// let goal = header.goals[0].frame as usize;
+/// let frames = &replay.network_frames.as_ref().unwrap().frames;
+/// let end = frames[goal].time;
+///
+/// let clip = trim(&replay, TrimRange::Time(end - 10.0..end)).unwrap();
+/// let written = ReplayWriter::new(&clip).write().unwrap();
+///
+/// let reparsed = ParserBuilder::new(&written[..])
+///     .always_check_crc()
+///     .must_parse_network_data()
+///     .parse()
+///     .unwrap();
+/// assert_eq!(reparsed.network_frames, clip.network_frames);
+/// ```
+pub fn trim(replay: &Replay, range: TrimRange) -> Result<Replay, TrimError> {
+    let frames = &replay
+        .network_frames
+        .as_ref()
+        .ok_or(TrimError::MissingNetworkFrames)?
+        .frames;
+
+    let (start, end) = match range {
+        TrimRange::Frames(range) => (range.start, range.end.min(frames.len())),
+        TrimRange::Time(range) => (
+            frames.partition_point(|x| x.time < range.start),
+            frames.partition_point(|x| x.time < range.end),
+        ),
+    };
+
+    if start >= end {
+        return Err(TrimError::EmptyRange);
+    }
+
+    let cut = replay
+        .keyframes
+        .iter()
+        .map(|x| x.frame as usize)
+        .filter(|&x| x <= start)
+        .max()
+        .unwrap_or(0);
+
+    let mut actors: FnvHashMap<ActorId, LiveActor> = FnvHashMap::default();
+    for frame in &frames[..cut] {
+        apply_frame(&mut actors, frame);
+    }
+
+    let mut clip = frames[cut..end].to_vec();
+    respawn(&mut clip[0], actors);
+
+    let in_clip = |frame: i32| frame >= 0 && cut <= frame as usize && (frame as usize) < end;
+    let shift = |frame: i32| frame - cut as i32;
+
+    let mut result = replay.clone();
+    result.network_frames = Some(NetworkFrames { frames: clip });
+    result.network_error = None;
+    result.properties = trim_properties(&replay.properties, end - cut, in_clip, shift);
+
+    result.keyframes.retain(|x| in_clip(x.frame));
+    result.tick_marks.retain(|x| in_clip(x.frame));
+    result.debug_info.retain(|x| in_clip(x.frame));
+
+    for x in &mut result.keyframes {
+        x.frame = shift(x.frame);
+    }
+
+    for x in &mut result.tick_marks {
+        x.frame = shift(x.frame);
+    }
+
+    for x in &mut result.debug_info {
+        x.frame = shift(x.frame);
+    }
+
+    Ok(result)
+}
+
+/// Applies the frame's deleted, new, and updated actors (in that order) to the live actors
+fn apply_frame(actors: &mut FnvHashMap<ActorId, LiveActor>, frame: &Frame) {
+    for actor_id in &frame.deleted_actors {
+        actors.remove(actor_id);
+    }
+
+    for actor in &frame.new_actors {
+        // Keyframes re-announce live actors, which keep their attributes
+        match actors.get_mut(&actor.actor_id) {
+            Some(live) if live.spawn.object_id == actor.object_id => live.spawn = *actor,
+            _ => {
+                actors.insert(
+                    actor.actor_id,
+                    LiveActor {
+                        spawn: *actor,
+                        attributes: FnvHashMap::default(),
+                    },
+                );
+            }
+        }
+    }
+
+    for update in &frame.updated_actors {
+        if let Some(live) = actors.get_mut(&update.actor_id) {
+            live.attributes.insert(update.stream_id, update.clone());
+        }
+    }
+}
+
+/// Rewrites the first frame of the clip so that it doesn't refer to actors from before the clip:
+/// deletions are dropped and live actors that aren't re-announced are spawned.
+fn respawn(frame: &mut Frame, mut actors: FnvHashMap<ActorId, LiveActor>) {
+    for actor_id in frame.deleted_actors.drain(..) {
+        actors.remove(&actor_id);
+    }
+
+    for actor in &frame.new_actors {
+        actors.remove(&actor.actor_id);
+    }
+
+    let mut missing: Vec<_> = actors.into_values().collect();
+    missing.sort_by_key(|x| x.spawn.actor_id);
+
+    let mut updates = Vec::new();
+    for actor in missing {
+        frame.new_actors.push(actor.spawn);
+        let mut attributes: Vec<_> = actor.attributes.into_values().collect();
+        attributes.sort_by_key(|x| x.stream_id);
+        updates.extend(attributes);
+    }
+
+    updates.append(&mut frame.updated_actors);
+    frame.updated_actors = updates;
+}
+
+fn trim_properties<F, G>(
+    properties: &[(String, HeaderProp)],
+    num_frames: usize,
+    in_clip: F,
+    shift: G,
+) -> Vec<(String, HeaderProp)>
+where
+    F: Fn(i32) -> bool,
+    G: Fn(i32) -> i32,
+{
+    let frame_of = |x: &[(String, HeaderProp)]| {
+        x.iter()
+            .find(|(key, _)| key == "frame")
+            .and_then(|(_, value)| value.as_i32())
+    };
+
+    properties
+        .iter()
+        .map(|(key, value)| {
+            let value = match (key.as_str(), value) {
+                ("NumFrames", HeaderProp::Int(_)) => HeaderProp::Int(num_frames as i32),
+                ("Goals", HeaderProp::Array(elements))
+                | ("HighLights", HeaderProp::Array(elements)) => HeaderProp::Array(
+                    elements
+                        .iter()
+                        .filter(|x| frame_of(x).filter(|&f| in_clip(f)).is_some())
+                        .map(|x| {
+                            let mut x = x.clone();
+                            for (key, value) in x.iter_mut() {
+                                if let ("frame", HeaderProp::Int(frame)) = (key.as_str(), value) {
+                                    *frame = shift(*frame);
+                                }
+                            }
+                            x
+                        })
+                        .collect(),
+                ),
+                _ => value.clone(),
+            };
+            (key.clone(), value)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::{ObjectId, Trajectory};
+
+    fn spawn(actor: i32, object: i32) -> NewActor {
+        NewActor {
+            actor_id: ActorId(actor),
+            name_id: None,
+            object_id: ObjectId(object),
+            initial_trajectory: Trajectory {
+                location: None,
+                rotation: None,
+            },
+        }
+    }
+
+    fn update(actor: i32, stream: i32, value: i32) -> UpdatedAttribute {
+        UpdatedAttribute {
+            actor_id: ActorId(actor),
+            stream_id: StreamId(stream),
+            object_id: ObjectId(stream),
+            attribute: crate::Attribute::Int(value),
+        }
+    }
+
+    fn frame(
+        new_actors: Vec<NewActor>,
+        deleted: Vec<i32>,
+        updates: Vec<UpdatedAttribute>,
+    ) -> Frame {
+        Frame {
+            time: 0.0,
+            delta: 0.0,
+            new_actors,
+            deleted_actors: deleted.into_iter().map(ActorId).collect(),
+            updated_actors: updates,
+        }
+    }
+
+    #[test]
+    fn test_respawn() {
+        let mut actors = FnvHashMap::default();
+        apply_frame(
+            &mut actors,
+            &frame(
+                vec![spawn(1, 10), spawn(2, 20), spawn(3, 30)],
+                vec![],
+                vec![update(1, 5, 1), update(2, 5, 2), update(1, 5, 3)],
+            ),
+        );
+
+        // Actor 1 is re-announced, 2 is left out, and 3 is deleted
+        let mut first = frame(vec![spawn(1, 10)], vec![3], vec![update(1, 6, 4)]);
+        respawn(&mut first, actors);
+
+        assert_eq!(first.new_actors, vec![spawn(1, 10), spawn(2, 20)]);
+        assert!(first.deleted_actors.is_empty());
+        assert_eq!(first.updated_actors, vec![update(2, 5, 2), update(1, 6, 4)]);
+    }
+
+    #[test]
+    fn test_trim_properties() {
+        let goal = |frame| vec![(String::from("frame"), HeaderProp::Int(frame))];
+        let properties = vec![
+            (String::from("NumFrames"), HeaderProp::Int(1000)),
+            (
+                String::from("Goals"),
+                HeaderProp::Array(vec![goal(50), goal(250), goal(500)]),
+            ),
+        ];
+
+        let in_clip = |x| (200..400).contains(&x);
+        let result = trim_properties(&properties, 200, in_clip, |x| x - 200);
+        assert_eq!(
+            result,
+            vec![
+                (String::from("NumFrames"), HeaderProp::Int(200)),
+                (String::from("Goals"), HeaderProp::Array(vec![goal(50)])),
+            ]
+        );
+    }
+}
@@ -0,0 +1,153 @@
+//! JavaScript bindings for parsing replays in the browser, enabled by the `wasm` feature. The
+//! crate is compiled as a WebAssembly module with:
+//!
+//! ```bash
+//! cargo rustc --lib --release --features wasm --target wasm32-unknown-unknown --crate-type cdylib
+//! wasm-bindgen --target web --out-dir pkg target/wasm32-unknown-unknown/release/boxcars.wasm
+//! ```
+//!
+//! Replays are returned as JSON strings (to be given to `JSON.parse`) instead of JavaScript
+//! objects, so that 64bit integers, which are serialized as strings, survive intact.
+//!
+//! ```js
+//! import init, { parse, parseHeader, ParseOptions } from "./pkg/boxcars.js";
+//!
+//! await init();
+//! const data = new Uint8Array(await file.arrayBuffer());
+//! const header = JSON.parse(parseHeader(data));
+//!
+//! const options = new ParseOptions();
+//! options.network = "partial";
+//! const replay = JSON.parse(parse(data, options));
+//! ```
+use crate::parser::{CrcCheck, NetworkParse, ParserBuilder};
+use wasm_bindgen::prelude::*;
+
+/// The `ParserBuilder` options, which are set from JavaScript with strings
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ParseOptions {
+    crc_check: CrcCheck,
+    network_parse: NetworkParse,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        ParseOptions {
+            crc_check: CrcCheck::OnError,
+            network_parse: NetworkParse::Always,
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl ParseOptions {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> ParseOptions {
+        ParseOptions::default()
+    }
+
+    /// When to validate the replay's crc: `always`, `never`, or `onerror` (the default)
+    #[wasm_bindgen(setter)]
+    pub fn set_crc(&mut self, value: &str) -> Result<(), JsValue> {
+        self.crc_check = crc_check(value).map_err(|e| JsValue::from_str(&e))?;
+        Ok(())
+    }
+
+    /// How to handle the network data: `always` (the default), `never`, `ignore`, or `partial`
+    #[wasm_bindgen(setter)]
+    pub fn set_network(&mut self, value: &str) -> Result<(), JsValue> {
+        self.network_parse = network_parse(value).map_err(|e| JsValue::from_str(&e))?;
+        Ok(())
+    }
+}
+
+/// Parses the replay and returns it as a JSON string. Throws the error message on failure.
+#[wasm_bindgen]
+pub fn parse(data: &[u8], options: &ParseOptions) -> Result<String, JsValue> {
+    to_json(data, options).map_err(|e| JsValue::from_str(&e))
+}
+
+/// Parses only the header (skipping the network data, which accounts for nearly all of the
+/// parsing time) and returns the replay as a JSON string. Throws the error message on failure.
+#[wasm_bindgen(js_name = parseHeader)]
+pub fn parse_header(data: &[u8]) -> Result<String, JsValue> {
+    to_json(data, &header_options()).map_err(|e| JsValue::from_str(&e))
+}
+
+fn header_options() -> ParseOptions {
+    ParseOptions {
+        network_parse: NetworkParse::Never,
+        ..ParseOptions::default()
+    }
+}
+
+fn to_json(data: &[u8], options: &ParseOptions) -> Result<String, String> {
+    let replay = ParserBuilder::new(data)
+        .with_crc_check(options.crc_check)
+        .with_network_parse(options.network_parse)
+        .parse()
+        .map_err(|e| e.to_string())?;
+    serde_json::to_string(&replay).map_err(|e| e.to_string())
+}
+
+fn crc_check(value: &str) -> Result<CrcCheck, String> {
+    match value {
+        "always" => Ok(CrcCheck::Always),
+        "never" => Ok(CrcCheck::Never),
+        "onerror" => Ok(CrcCheck::OnError),
+        _ => Err(format!("Unrecognized crc option: {}", value)),
+    }
+}
+
+fn network_parse(value: &str) -> Result<NetworkParse, String> {
+    match value {
+        "always" => Ok(NetworkParse::Always),
+        "never" => Ok(NetworkParse::Never),
+        "ignore" => Ok(NetworkParse::IgnoreOnError),
+        "partial" => Ok(NetworkParse::Partial),
+        _ => Err(format!("Unrecognized network option: {}", value)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_options() {
+        assert_eq!(crc_check("never"), Ok(CrcCheck::Never));
+        assert_eq!(network_parse("partial"), Ok(NetworkParse::Partial));
+        assert!(crc_check("sometimes").is_err());
+        assert!(network_parse("").is_err());
+    }
+
+    #[test]
+    fn test_header_keeps_64bit_ids() {
+        let data = include_bytes!("../assets/replays/good/rumble.replay");
+        let json = to_json(&data[..], &header_options()).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert!(value["network_frames"].is_null());
+        assert!(json.contains(r#"{"QWord":"76561198101748375"}"#));
+    }
+
+    #[test]
+    fn test_parse_network() {
+        let data = include_bytes!("../assets/replays/good/rumble.replay");
+        let json = to_json(&data[..], &ParseOptions::default()).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert!(!value["network_frames"]["frames"]
+            .as_array()
+            .unwrap()
+            .is_empty());
+
+        // Remote ids are serialized as strings
+        assert!(json.contains(r#""remote_id":{"Steam":"76561198025655598"}"#));
+    }
+
+    #[test]
+    fn test_parse_error() {
+        let error = to_json(&[0; 10], &ParseOptions::default()).unwrap_err();
+        assert!(!error.is_empty());
+    }
+}
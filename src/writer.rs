@@ -0,0 +1,157 @@
+//! # Writing
+//!
+//! The inverse of parsing: a `Replay` is written back out in the binary replay format (as
+//! detailed in the parsing module) so that replays can be edited and then loaded by Rocket League
+//! or another parser.
+//!
+//! The header and body sizes and crcs are recomputed from the written data, so the sizes and crcs
+//! stored on the replay are ignored. The network data is encoded from the replay's network
+//! frames and the keyframe positions are updated to point at the newly encoded frames.
+//!
+//! A few pieces of data are discarded when parsing, so placeholders are written in their stead:
+//!
+//! - The unused bit of a new actor
+//! - Loadout data that isn't retained by `Loadout`
+
+use crate::core_writer::CoreWriter;
+use crate::crc::calc_crc;
+use crate::errors::WriteError;
+use crate::header::{self, Header};
+use crate::models::*;
+use crate::network::{self, ClassTables};
+
+/// Serializes a replay into the binary format that Rocket League uses. Writing a parsed replay and
+/// parsing it again will yield the same replay, sans the sizes and crcs, which are recomputed.
+///
+/// A frame's actors are written as its deleted, then new, and then updated actors, which is not
+/// necessarily the order that they were decoded in. Reparsing yields the same frames all the
+/// same, except a frame that updates an actor it deletes (without spawning it again) can't be
+/// written and is a `WriteError::DeletedActorUpdated`.
+///
+/// ```
+/// use boxcars::{ParserBuilder, ReplayWriter};
+///
+/// let data = include_bytes!("../assets/replays/good/rumble.replay");
+/// let replay = ParserBuilder::new(&data[..])
+///     .must_parse_network_data()
+///     .parse()
+///     .unwrap();
+///
+/// let written = ReplayWriter::new(&replay).write().unwrap();
+/// let reparsed = ParserBuilder::new(&written[..])
+///     .always_check_crc()
+///     .must_parse_network_data()
+///     .parse()
+///     .unwrap();
+///
+/// assert_eq!(reparsed.network_frames, replay.network_frames);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReplayWriter<'a> {
+    replay: &'a Replay,
+    tables: Option<&'a ClassTables>,
+}
+
+impl<'a> ReplayWriter<'a> {
+    pub fn new(replay: &'a Replay) -> Self {
+        ReplayWriter {
+            replay,
+            tables: None,
+        }
+    }
+
+    /// Encode the network data with the same tables that the replay was parsed with
+    pub fn with_tables(mut self, tables: &'a ClassTables) -> ReplayWriter<'a> {
+        self.tables = Some(tables);
+        self
+    }
+
+    /// Writes the replay. Network frames are required, as without them there is no network data
+    /// to write.
+    pub fn write(&self) -> Result<Vec<u8>, WriteError> {
+        let header = Header {
+            major_version: self.replay.major_version,
+            minor_version: self.replay.minor_version,
+            net_version: self.replay.net_version,
+            game_type: self.replay.game_type.clone(),
+            properties: self.replay.properties.clone(),
+        };
+
+        let mut header_writer = CoreWriter::new();
+        header::write_header(&mut header_writer, &header);
+        let header_data = header_writer.into_bytes();
+
+        let content_data = self.write_body(&header)?;
+
+        let mut w = CoreWriter::new();
+        w.write_i32(header_data.len() as i32);
+        w.write_u32(calc_crc(&header_data));
+        w.write_bytes(&header_data);
+        w.write_i32(content_data.len() as i32);
+        w.write_u32(calc_crc(&content_data));
+        w.write_bytes(&content_data);
+        Ok(w.into_bytes())
+    }
+
+    fn write_body(&self, header: &Header) -> Result<Vec<u8>, WriteError> {
+        let replay = self.replay;
+        let frames = replay
+            .network_frames
+            .as_ref()
+            .map(|x| &x.frames[..])
+            .ok_or(WriteError::MissingNetworkFrames)?;
+
+        let default_tables = ClassTables::default();
+        let tables = self.tables.unwrap_or(&default_tables);
+        let encoded = network::encode(header, &replay.objects, &replay.net_cache, frames, tables)?;
+
+        let mut w = CoreWriter::new();
+        w.text_list(&replay.levels);
+        w.list_of(&replay.keyframes, |s, x| {
+            let position = encoded
+                .frame_positions
+                .get(x.frame as usize)
+                .map(|&x| x as i32)
+                .unwrap_or(x.position);
+
+            s.write_f32(x.time);
+            s.write_i32(x.frame);
+            s.write_i32(position);
+        });
+
+        w.write_i32(encoded.data.len() as i32);
+        w.write_bytes(&encoded.data);
+
+        w.list_of(&replay.debug_info, |s, x| {
+            s.write_i32(x.frame);
+            s.write_text(&x.user);
+            s.write_text(&x.text);
+        });
+
+        w.list_of(&replay.tick_marks, |s, x| {
+            s.write_text(&x.description);
+            s.write_i32(x.frame);
+        });
+
+        w.text_list(&replay.packages);
+        w.text_list(&replay.objects);
+        w.text_list(&replay.names);
+
+        w.list_of(&replay.class_indices, |s, x| {
+            s.write_str(&x.class);
+            s.write_i32(x.index);
+        });
+
+        w.list_of(&replay.net_cache, |s, x| {
+            s.write_i32(x.object_ind);
+            s.write_i32(x.parent_id);
+            s.write_i32(x.cache_id);
+            s.list_of(&x.properties, |s, prop| {
+                s.write_i32(prop.object_ind);
+                s.write_i32(prop.stream_id);
+            });
+        });
+
+        Ok(w.into_bytes())
+    }
+}
@@ -1,6 +1,7 @@
 use boxcars::attributes::{ActiveActor, Demolish, Pickup, RigidBody, StatEvent, Welded};
 use boxcars::{
-    self, ActorId, NetworkError, ParseError, ParserBuilder, Quaternion, Trajectory, Vector3f, Vector3i,
+    self, ActorId, Anonymizer, AttributeTag, ClassTables, NetworkError, ObjectId, ParseError,
+    ParserBuilder, Quaternion, ReplayWriter, Trajectory, TrimRange, Vector3f, Vector3i, WriteError,
 };
 
 #[test]
@@ -462,3 +463,1057 @@ fn test_rumble_actor_id() {
         .collect();
     assert_eq!(pickups[264].instigator, Some(ActorId(-1)));
 }
+
+#[test]
+fn test_frame_iter_matches_parse() {
+    let data = include_bytes!("../assets/replays/good/01d3e5.replay");
+    let replay = ParserBuilder::new(&data[..])
+        .never_check_crc()
+        .must_parse_network_data()
+        .parse()
+        .unwrap();
+
+    let (streamed, frame_iter) = ParserBuilder::new(&data[..])
+        .never_check_crc()
+        .parse_streaming()
+        .unwrap();
+
+    assert_eq!(frame_iter.frames_len(), 393);
+    assert!(streamed.network_frames.is_none());
+    assert_eq!(streamed.objects, replay.objects);
+
+    let frames = frame_iter.collect::<Result<Vec<_>, _>>().unwrap();
+    assert_eq!(frames, replay.network_frames.unwrap().frames);
+}
+
+fn assert_write_round_trip(data: &[u8]) {
+    let replay = ParserBuilder::new(data)
+        .always_check_crc()
+        .must_parse_network_data()
+        .parse()
+        .unwrap();
+
+    let written = ReplayWriter::new(&replay).write().unwrap();
+    let mut reparsed = ParserBuilder::new(&written[..])
+        .always_check_crc()
+        .must_parse_network_data()
+        .parse()
+        .unwrap();
+
+    assert_eq!(
+        reparsed.header_size as usize + reparsed.content_size as usize + 16,
+        written.len()
+    );
+
+    // Sizes, crcs, and the bit positions of the keyframes are recomputed when written
+    assert_eq!(reparsed.keyframes.len(), replay.keyframes.len());
+    reparsed.header_size = replay.header_size;
+    reparsed.header_crc = replay.header_crc;
+    reparsed.content_size = replay.content_size;
+    reparsed.content_crc = replay.content_crc;
+    reparsed.keyframes = replay.keyframes.clone();
+    assert_eq!(reparsed, replay);
+}
+
+#[test]
+fn test_write_round_trip() {
+    assert_write_round_trip(include_bytes!("../assets/replays/good/01d3e5.replay"));
+    assert_write_round_trip(include_bytes!("../assets/replays/good/0ca5.replay"));
+    assert_write_round_trip(include_bytes!("../assets/replays/good/6688.replay"));
+    assert_write_round_trip(include_bytes!("../assets/replays/good/7588d.replay"));
+    assert_write_round_trip(include_bytes!("../assets/replays/good/a9df3.replay"));
+    assert_write_round_trip(include_bytes!("../assets/replays/good/no-frames.replay"));
+    assert_write_round_trip(include_bytes!("../assets/replays/good/rumble.replay"));
+    assert_write_round_trip(include_bytes!("../assets/replays/good/small-frames.replay"));
+    assert_write_round_trip(include_bytes!("../assets/replays/good/soccar-lan.replay"));
+    assert_write_round_trip(include_bytes!("../assets/replays/good/e978.replay"));
+}
+
+#[test]
+fn test_write_deleted_actor_updated() {
+    let data = include_bytes!("../assets/replays/good/rumble.replay");
+    let mut replay = ParserBuilder::new(&data[..])
+        .must_parse_network_data()
+        .parse()
+        .unwrap();
+
+    let frames = &mut replay.network_frames.as_mut().unwrap().frames;
+    let actor = frames[100].updated_actors[0].actor_id;
+    frames[100].deleted_actors.push(actor);
+
+    let err = ReplayWriter::new(&replay).write().unwrap_err();
+    assert_eq!(err, WriteError::DeletedActorUpdated { frame: 100, actor });
+}
+
+#[test]
+fn test_write_requires_network_frames() {
+    let data = include_bytes!("../assets/replays/good/rumble.replay");
+    let replay = ParserBuilder::new(&data[..])
+        .never_parse_network_data()
+        .parse()
+        .unwrap();
+
+    let err = ReplayWriter::new(&replay).write().unwrap_err();
+    assert_eq!(err, WriteError::MissingNetworkFrames);
+}
+
+#[test]
+fn test_typed_header() {
+    let data = include_bytes!("../assets/replays/good/rumble.replay");
+    let replay = ParserBuilder::new(&data[..])
+        .never_parse_network_data()
+        .parse()
+        .unwrap();
+
+    let header = replay.typed_header().unwrap();
+    assert_eq!(header.replay_name.as_deref(), Some("rumble"));
+    assert_eq!(header.team0_score, Some(5));
+    assert_eq!(header.team1_score, Some(2));
+    assert_eq!(header.goals.len(), 7);
+    assert_eq!(
+        header.goals[1],
+        boxcars::Goal {
+            frame: 1738,
+            player_name: String::from("Sasha Kaun"),
+            player_team: 0,
+        }
+    );
+
+    let stats = &header.player_stats[0];
+    assert_eq!(stats.name, "comagoosie");
+    assert_eq!(stats.platform.as_deref(), Some("OnlinePlatform_Steam"));
+    assert_eq!(stats.online_id, Some(76561198101748375));
+    assert_eq!(stats.score, 90);
+    assert!(!stats.is_bot);
+}
+
+#[test]
+fn test_typed_header_all_replays() {
+    for entry in std::fs::read_dir("assets/replays/good").unwrap() {
+        let path = entry.unwrap().path();
+        let data = std::fs::read(&path).unwrap();
+        let replay = ParserBuilder::new(&data[..])
+            .never_parse_network_data()
+            .parse()
+            .unwrap();
+
+        let header = replay.typed_header().unwrap();
+        for goal in &header.goals {
+            assert!(goal.player_team == 0 || goal.player_team == 1);
+        }
+    }
+}
+
+#[test]
+fn test_player_resolver() {
+    let data = include_bytes!("../assets/replays/good/e978.replay");
+    let replay = ParserBuilder::new(&data[..])
+        .must_parse_network_data()
+        .parse()
+        .unwrap();
+
+    let tables = ClassTables::default();
+    let mut resolver = boxcars::PlayerResolver::new(&replay.objects, &tables);
+    for frame in &replay.network_frames.as_ref().unwrap().frames {
+        resolver.process_frame(frame).unwrap();
+    }
+
+    let header = replay.typed_header().unwrap();
+    let players = resolver.players();
+    assert_eq!(players.len(), header.player_stats.len());
+    for stats in &header.player_stats {
+        let player = players
+            .iter()
+            .find(|x| x.name.as_ref() == Some(&stats.name))
+            .unwrap();
+        assert_eq!(player.team, Some(stats.team));
+        assert!(player.remote_id.is_some());
+
+        // Each respawn (eg: after a demolition or goal) is a new car with new components
+        assert!(player.cars.len() > 1);
+        assert_eq!(player.boosts.len(), player.cars.len());
+        assert_eq!(player.dodges.len(), player.cars.len());
+        for cars in player.cars.windows(2) {
+            assert!(cars[0].end_frame.unwrap() <= cars[1].start_frame);
+        }
+    }
+}
+
+#[test]
+fn test_physics_timeline() {
+    let data = include_bytes!("../assets/replays/good/rumble.replay");
+    let replay = ParserBuilder::new(&data[..])
+        .must_parse_network_data()
+        .parse()
+        .unwrap();
+
+    let frames = &replay.network_frames.as_ref().unwrap().frames;
+    let tables = ClassTables::default();
+    let samples =
+        boxcars::physics_timeline(&replay.objects, &tables, replay.net_version, frames).unwrap();
+    assert!(samples
+        .windows(2)
+        .all(|x| (x[0].frame, x[0].actor_id) < (x[1].frame, x[1].actor_id)));
+
+    // Once the ball has been updated, it has a sample every frame until it is destroyed
+    let ball: Vec<_> = samples
+        .iter()
+        .filter(|x| x.actor == boxcars::PhysicsActor::Ball)
+        .collect();
+    assert!(ball.iter().any(|x| x.interpolated));
+    assert!(ball.iter().any(|x| !x.interpolated));
+
+    // Interpolated samples lie between the updates that surround them
+    for window in ball.windows(3) {
+        let (a, b, c) = (window[0], window[1], window[2]);
+        if !a.interpolated && b.interpolated && !c.interpolated && a.actor_id == c.actor_id {
+            let (lo, hi) = if a.rigid_body.location.x < c.rigid_body.location.x {
+                (a.rigid_body.location.x, c.rigid_body.location.x)
+            } else {
+                (c.rigid_body.location.x, a.rigid_body.location.x)
+            };
+            assert!(lo <= b.rigid_body.location.x && b.rigid_body.location.x <= hi);
+        }
+    }
+
+    let cars = samples
+        .iter()
+        .filter(|x| x.actor == boxcars::PhysicsActor::Car && x.frame == 1000)
+        .count();
+    assert_eq!(cars, 6);
+}
+
+#[test]
+fn test_physics_timeline_unreal_units() {
+    // The replay predates network version 5, so its rigid bodies are replicated in smaller units
+    let data = include_bytes!("../assets/replays/good/rlcs.replay");
+    let replay = ParserBuilder::new(&data[..])
+        .must_parse_network_data()
+        .parse()
+        .unwrap();
+
+    let frames = &replay.network_frames.as_ref().unwrap().frames;
+    let tables = ClassTables::default();
+    let samples =
+        boxcars::physics_timeline(&replay.objects, &tables, replay.net_version, frames).unwrap();
+
+    // The field is about 10240 unreal units long
+    assert!(samples
+        .iter()
+        .all(|x| x.rigid_body.location.y.abs() < 6000.0));
+    assert!(samples
+        .iter()
+        .any(|x| x.rigid_body.location.y.abs() > 4000.0));
+}
+
+#[test]
+fn test_tables_parent_class_cycle() {
+    // The built-in tables have `Engine.Info` inherit from `Engine.Actor`
+    let data = include_bytes!("../assets/replays/good/rumble.replay");
+    let tables = ClassTables::new().with_parent_class("Engine.Actor", "Engine.Info");
+    let err = ParserBuilder::new(&data[..])
+        .with_tables(&tables)
+        .must_parse_network_data()
+        .parse()
+        .unwrap_err();
+
+    match err {
+        ParseError::NetworkError(e) => assert!(matches!(*e, NetworkError::ParentClassCycle(_))),
+        e => panic!("unexpected error: {}", e),
+    }
+}
+
+#[test]
+fn test_partial_network_data() {
+    let data = include_bytes!("../assets/replays/good/rumble.replay");
+    let full = ParserBuilder::new(&data[..])
+        .must_parse_network_data()
+        .parse()
+        .unwrap();
+    let all_frames = full.network_frames.unwrap().frames;
+    assert!(full.network_error.is_none());
+
+    // Pretend that the first ball touch is an attribute we don't know how to decode
+    let tables = ClassTables::new()
+        .with_attribute("TAGame.Ball_TA:HitTeamNum", AttributeTag::NotImplemented);
+    let replay = ParserBuilder::new(&data[..])
+        .with_tables(&tables)
+        .partial_network_data_on_error()
+        .parse()
+        .unwrap();
+
+    let frames = replay.network_frames.unwrap().frames;
+    assert!(!frames.is_empty());
+    assert!(frames.len() < all_frames.len());
+    assert_eq!(&frames[..], &all_frames[..frames.len()]);
+
+    match replay.network_error {
+        Some(NetworkError::FrameError(_, context)) => assert!(context.frames.is_empty()),
+        x => panic!("Expecting a frame error, not {:?}", x),
+    }
+
+    let replay = ParserBuilder::new(&data[..])
+        .with_tables(&tables)
+        .ignore_network_data_on_error()
+        .parse()
+        .unwrap();
+    assert!(replay.network_frames.is_none());
+    assert!(replay.network_error.is_none());
+}
+
+fn assert_json_round_trip(data: &[u8]) {
+    let replay = ParserBuilder::new(data)
+        .must_parse_network_data()
+        .parse()
+        .unwrap();
+
+    let json = serde_json::to_string(&replay).unwrap();
+    let reloaded: boxcars::Replay = serde_json::from_str(&json).unwrap();
+    assert_eq!(reloaded, replay);
+}
+
+#[test]
+fn test_json_round_trip() {
+    assert_json_round_trip(include_bytes!("../assets/replays/good/01d3e5.replay"));
+    assert_json_round_trip(include_bytes!("../assets/replays/good/6688.replay"));
+    assert_json_round_trip(include_bytes!("../assets/replays/good/rumble.replay"));
+    assert_json_round_trip(include_bytes!("../assets/replays/good/e978.replay"));
+}
+
+#[test]
+fn test_json_round_trip_all_headers() {
+    for entry in std::fs::read_dir("assets/replays/good").unwrap() {
+        let path = entry.unwrap().path();
+        let data = std::fs::read(&path).unwrap();
+        let replay = ParserBuilder::new(&data[..])
+            .never_parse_network_data()
+            .parse()
+            .unwrap();
+
+        let json = serde_json::to_string(&replay).unwrap();
+        let reloaded: boxcars::Replay = serde_json::from_str(&json).unwrap();
+        assert_eq!(reloaded, replay, "{}", path.display());
+    }
+}
+
+#[test]
+fn test_parallel_network_decode() {
+    for entry in std::fs::read_dir("assets/replays/good").unwrap() {
+        let path = entry.unwrap().path();
+        let data = std::fs::read(&path).unwrap();
+        let serial = ParserBuilder::new(&data[..])
+            .must_parse_network_data()
+            .parse()
+            .unwrap();
+
+        let parallel = ParserBuilder::new(&data[..])
+            .must_parse_network_data()
+            .with_network_threads(4)
+            .parse()
+            .unwrap();
+
+        assert_eq!(parallel, serial, "{}", path.display());
+    }
+}
+
+#[test]
+fn test_parallel_network_decode_error() {
+    let data = include_bytes!("../assets/replays/good/rumble.replay");
+    let tables = ClassTables::new()
+        .with_attribute("TAGame.Ball_TA:HitTeamNum", AttributeTag::NotImplemented);
+
+    let parse = |threads| {
+        ParserBuilder::new(&data[..])
+            .with_tables(&tables)
+            .must_parse_network_data()
+            .with_network_threads(threads)
+            .parse()
+            .unwrap_err()
+    };
+    assert_eq!(parse(4), parse(1));
+
+    let partial = |threads| {
+        ParserBuilder::new(&data[..])
+            .with_tables(&tables)
+            .partial_network_data_on_error()
+            .with_network_threads(threads)
+            .parse()
+            .unwrap()
+    };
+    assert_eq!(partial(4), partial(1));
+}
+
+#[test]
+fn test_parallel_network_decode_bad_keyframes() {
+    let mut data = include_bytes!("../assets/replays/good/rumble.replay").to_vec();
+
+    // Nudge the bit position of the second and third keyframes so that decoding from them is
+    // garbage and the parallel decode has to fall back to decoding those chunks serially
+    let keyframes = 0x12ca + 4;
+    for i in 1..3 {
+        let position = keyframes + i * 12 + 8;
+        data[position] = data[position].wrapping_add(1);
+    }
+
+    let parse = |threads| {
+        ParserBuilder::new(&data[..])
+            .never_check_crc()
+            .must_parse_network_data()
+            .with_network_threads(threads)
+            .parse()
+            .unwrap()
+    };
+
+    let serial = parse(1);
+    assert_eq!(serial.keyframes[1].position, 137274);
+    assert_eq!(parse(4), serial);
+}
+
+#[test]
+fn test_seek_frame() {
+    for file in &["rumble.replay", "3d07e.replay"] {
+        let data = std::fs::read(format!("assets/replays/good/{}", file)).unwrap();
+        let (_, frames) = ParserBuilder::new(&data[..]).parse_streaming().unwrap();
+        let all = frames.collect::<Result<Vec<_>, _>>().unwrap();
+
+        let (_, mut frames) = ParserBuilder::new(&data[..]).parse_streaming().unwrap();
+        for &target in &[all.len() / 2, 10, all.len() - 1, 500, all.len() + 10, 0] {
+            frames.seek_frame(target).unwrap();
+            assert_eq!(frames.current_frame(), target.min(all.len()));
+
+            let window = frames
+                .by_ref()
+                .take(20)
+                .collect::<Result<Vec<_>, _>>()
+                .unwrap();
+            let end = (target + 20).min(all.len());
+            assert_eq!(&window[..], &all[target.min(all.len())..end], "{}", file);
+        }
+    }
+}
+
+#[test]
+fn test_seek_time() {
+    let data = include_bytes!("../assets/replays/good/rumble.replay");
+    let (_, frames) = ParserBuilder::new(&data[..]).parse_streaming().unwrap();
+    let all = frames.collect::<Result<Vec<_>, _>>().unwrap();
+
+    let (_, mut frames) = ParserBuilder::new(&data[..]).parse_streaming().unwrap();
+    for &time in &[100.0, 16.29, 50.0, -1.0, 1000.0] {
+        frames.seek_time(time).unwrap();
+        let start = all.iter().position(|x| x.time >= time).unwrap_or(all.len());
+        assert_eq!(frames.current_frame(), start);
+
+        let window = frames
+            .by_ref()
+            .take(5)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        let end = (start + 5).min(all.len());
+        assert_eq!(&window[..], &all[start..end]);
+    }
+}
+
+#[test]
+fn test_seek_bad_keyframes() {
+    let mut data = include_bytes!("../assets/replays/good/rumble.replay").to_vec();
+
+    // Decoding from a nudged keyframe is garbage, so seeking should fall back to decoding from the
+    // start of the network data
+    let position = 0x12ca + 4 + 12 + 8;
+    data[position] = data[position].wrapping_add(1);
+
+    let parse = || {
+        ParserBuilder::new(&data[..])
+            .never_check_crc()
+            .parse_streaming()
+            .unwrap()
+    };
+
+    let (replay, frames) = parse();
+    let all = frames.collect::<Result<Vec<_>, _>>().unwrap();
+    assert_eq!(replay.keyframes[1].position, 137274);
+
+    let (_, mut frames) = parse();
+    frames.seek_frame(250).unwrap();
+    let window = frames.take(20).collect::<Result<Vec<_>, _>>().unwrap();
+    assert_eq!(&window[..], &all[250..270]);
+}
+
+#[test]
+fn test_goal_events() {
+    let data = include_bytes!("../assets/replays/good/rumble.replay");
+    let replay = ParserBuilder::new(&data[..])
+        .must_parse_network_data()
+        .parse()
+        .unwrap();
+
+    let frames = &replay.network_frames.as_ref().unwrap().frames;
+    let header = replay.typed_header().unwrap();
+    let goals = boxcars::goal_events(&replay, &ClassTables::default()).unwrap();
+    assert_eq!(goals.len(), header.goals.len());
+    for (goal, expected) in goals.iter().zip(header.goals.iter()) {
+        assert_eq!(goal.frame, expected.frame as usize);
+        assert_eq!(goal.time, frames[goal.frame].time);
+        assert_eq!(goal.player_name, expected.player_name);
+        assert_eq!(goal.team, expected.player_team);
+        assert!(goal.player.is_some());
+        assert!(goal.remote_id.is_some());
+        assert!(!goal.overtime);
+
+        // The replay predates network version 5, yet the speed is in unreal units and under the
+        // ball's max speed of 6000
+        let speed = goal.ball_speed.unwrap();
+        assert!(speed > 1000.0 && speed < 6000.0, "{}", speed);
+
+        let touch = goal.touches.last().unwrap();
+        assert!(touch.frame <= goal.frame);
+        assert!(touch.player.is_some());
+    }
+
+    // Touches aren't shared between goals
+    for goals in goals.windows(2) {
+        assert!(goals[1].touches[0].frame > goals[0].frame);
+    }
+}
+
+#[test]
+fn test_goal_events_overtime() {
+    let data = include_bytes!("../assets/replays/good/21a81.replay");
+    let replay = ParserBuilder::new(&data[..])
+        .must_parse_network_data()
+        .parse()
+        .unwrap();
+
+    let goals = boxcars::goal_events(&replay, &ClassTables::default()).unwrap();
+    let overtime: Vec<_> = goals.iter().map(|x| x.overtime).collect();
+    assert_eq!(overtime, vec![false, false, false, false, true]);
+    assert_eq!(goals[4].player_name, "Thaddeus");
+}
+
+#[test]
+fn test_goal_events_requires_network_data() {
+    let data = include_bytes!("../assets/replays/good/rumble.replay");
+    let replay = ParserBuilder::new(&data[..])
+        .never_parse_network_data()
+        .parse()
+        .unwrap();
+
+    assert_eq!(
+        boxcars::goal_events(&replay, &ClassTables::default()),
+        Err(boxcars::AnalysisError::MissingNetworkFrames)
+    );
+}
+
+#[test]
+fn test_boost_stats() {
+    let data = include_bytes!("../assets/replays/good/rlcs.replay");
+    let replay = ParserBuilder::new(&data[..])
+        .must_parse_network_data()
+        .parse()
+        .unwrap();
+
+    let stats = boxcars::boost_stats(&replay, &ClassTables::default()).unwrap();
+    let header = replay.typed_header().unwrap();
+    for player in &header.player_stats {
+        let boost = stats
+            .iter()
+            .find(|x| x.name.as_ref() == Some(&player.name))
+            .unwrap();
+        assert_eq!(boost.team, Some(player.team));
+        assert!(boost.consumed > 0.0);
+        assert!(boost.time_empty > 0.0);
+        assert!(boost.time_full > 0.0);
+        assert!(boost
+            .timeline
+            .iter()
+            .all(|x| x.amount >= 0.0 && x.amount <= 100.0));
+        assert_eq!(
+            boost.big_pads + boost.small_pads,
+            boost.pickups.iter().filter(|x| x.size.is_some()).count()
+        );
+        assert!(boost.stolen_big_pads <= boost.big_pads);
+        assert!(boost.stolen_small_pads <= boost.small_pads);
+    }
+
+    // Older replays replicate car locations in hundredths of an unreal unit, which are scaled so
+    // that the pickups are spread across the field
+    assert!(replay.net_version.is_none());
+    let locations: Vec<_> = stats
+        .iter()
+        .flat_map(|x| x.pickups.iter())
+        .filter_map(|x| x.location)
+        .collect();
+    assert!(locations.iter().all(|x| x.y.abs() < 5200.0));
+    assert!(locations.iter().any(|x| x.y.abs() > 4000.0));
+
+    // A standard field has 6 big pads and 28 small pads
+    let mut big_pads = std::collections::HashSet::new();
+    let mut small_pads = std::collections::HashSet::new();
+    for pickup in stats.iter().flat_map(|x| x.pickups.iter()) {
+        match pickup.size {
+            Some(boxcars::PadSize::Big) => big_pads.insert(pickup.pad.as_str()),
+            Some(boxcars::PadSize::Small) => small_pads.insert(pickup.pad.as_str()),
+            None => false,
+        };
+    }
+    assert_eq!(big_pads.len(), 6);
+    assert_eq!(small_pads.len(), 28);
+}
+
+#[test]
+fn test_demolitions() {
+    let data = include_bytes!("../assets/replays/good/rlcs.replay");
+    let replay = ParserBuilder::new(&data[..])
+        .must_parse_network_data()
+        .parse()
+        .unwrap();
+
+    // Keyframes replicate the demolition again, which should only be counted once
+    let demos = boxcars::demolitions(&replay, &ClassTables::default()).unwrap();
+    assert_eq!(demos.len(), 2);
+    for demo in &demos {
+        assert_eq!(demo.attacker_name.as_deref(), Some("Paschy90"));
+        assert_eq!(demo.victim_name.as_deref(), Some("Maestro."));
+        assert!(demo.attacker.is_some());
+        assert!(demo.location.is_some());
+        assert!(demo.attacker_velocity.is_some());
+    }
+
+    let data = include_bytes!("../assets/replays/good/rumble.replay");
+    let replay = ParserBuilder::new(&data[..])
+        .must_parse_network_data()
+        .parse()
+        .unwrap();
+
+    // Cars can be destroyed without an attacker
+    let demos = boxcars::demolitions(&replay, &ClassTables::default()).unwrap();
+    assert_eq!(demos.len(), 10);
+    assert!(demos
+        .iter()
+        .any(|x| x.attacker_car.is_none() && x.attacker_velocity.is_none()));
+    assert!(demos.iter().all(|x| x.victim.is_some()));
+}
+
+#[test]
+fn test_possession() {
+    let data = include_bytes!("../assets/replays/good/21a81.replay");
+    let replay = ParserBuilder::new(&data[..])
+        .must_parse_network_data()
+        .parse()
+        .unwrap();
+
+    let frames = &replay.network_frames.as_ref().unwrap().frames;
+    let possession = boxcars::possession(&replay, &ClassTables::default()).unwrap();
+    assert_eq!(possession.frames.len(), frames.len());
+
+    let touches = &possession.touches;
+    assert!(touches.windows(2).all(|x| x[0].frame <= x[1].frame));
+    assert!(touches.iter().all(|x| x.player.is_some()));
+
+    // Proximity catches consecutive touches by the same team
+    assert!(touches.windows(2).any(|x| x[0].team == x[1].team));
+
+    let first = touches[0].frame;
+    assert!(possession.frames[..first]
+        .iter()
+        .all(|x| x.team.is_none() && x.player.is_none()));
+    assert_eq!(possession.frames[first].team, Some(touches[0].team));
+    assert_eq!(possession.frames[first].player, touches[0].player);
+
+    let teams: Vec<_> = possession.teams.iter().map(|x| x.team).collect();
+    assert_eq!(teams, vec![0, 1]);
+    let total: f32 = possession.teams.iter().map(|x| x.percentage).sum();
+    assert!((total - 100.0).abs() < 0.01);
+    let total: f32 = possession.players.iter().map(|x| x.percentage).sum();
+    assert!((total - 100.0).abs() < 0.01);
+    assert!(possession.players.iter().all(|x| x.time > 0.0));
+}
+
+#[test]
+fn test_anonymize() {
+    let data = include_bytes!("../assets/replays/good/rumble.replay");
+    let mut replay = ParserBuilder::new(&data[..])
+        .must_parse_network_data()
+        .parse()
+        .unwrap();
+
+    let header = replay.typed_header().unwrap();
+    let names: Vec<_> = header.player_stats.iter().map(|x| x.name.clone()).collect();
+    assert!(names.contains(&String::from("comagoosie")));
+
+    let mut anonymizer = Anonymizer::new();
+    anonymizer.anonymize(&mut replay);
+
+    let written = ReplayWriter::new(&replay).write().unwrap();
+    let reparsed = ParserBuilder::new(&written[..])
+        .always_check_crc()
+        .must_parse_network_data()
+        .parse()
+        .unwrap();
+    assert_eq!(reparsed.network_frames, replay.network_frames);
+    assert_eq!(reparsed.properties, replay.properties);
+
+    let json = serde_json::to_string(&reparsed).unwrap();
+    assert!(names.iter().all(|x| !json.contains(&format!("\"{}\"", x))));
+    assert!(!json.contains("76561198101748375"));
+    assert!(!json.contains("76561198025655598"));
+
+    // The header and network data agree on the pseudonyms
+    let header = reparsed.typed_header().unwrap();
+    for stats in &header.player_stats {
+        assert!(stats.name.starts_with("Player "));
+        assert!(json.contains(&format!("{{\"String\":\"{}\"}}", stats.name)));
+    }
+
+    // The unknown bytes of PsyNet, PlayStation, and Switch ids are zeroed
+    for data in [
+        &include_bytes!("../assets/replays/good/d52eb.replay")[..],
+        &include_bytes!("../assets/replays/good/d5d6c.replay")[..],
+        &include_bytes!("../assets/replays/good/7083.replay")[..],
+    ] {
+        let mut replay = ParserBuilder::new(data)
+            .must_parse_network_data()
+            .parse()
+            .unwrap();
+        let before = unknown_id_bytes(&replay);
+        assert!(before.iter().any(|&x| x != 0));
+
+        anonymizer.anonymize(&mut replay);
+        let after = unknown_id_bytes(&replay);
+        assert_eq!(after.len(), before.len());
+        assert!(after.iter().all(|&x| x == 0));
+
+        let written = ReplayWriter::new(&replay).write().unwrap();
+        let reparsed = ParserBuilder::new(&written[..])
+            .must_parse_network_data()
+            .parse()
+            .unwrap();
+        assert_eq!(reparsed.network_frames, replay.network_frames);
+    }
+}
+
+/// The unknown bytes of every PsyNet, PlayStation, and Switch id in the network data
+fn unknown_id_bytes(replay: &boxcars::Replay) -> Vec<u8> {
+    let frames = &replay.network_frames.as_ref().unwrap().frames;
+    let mut result = Vec::new();
+    for update in frames.iter().flat_map(|x| x.updated_actors.iter()) {
+        let id = match update.attribute {
+            boxcars::Attribute::UniqueId(ref id)
+            | boxcars::Attribute::PartyLeader(Some(ref id)) => id,
+            boxcars::Attribute::Reservation(ref reservation) => &reservation.unique_id,
+            _ => continue,
+        };
+
+        match id.remote_id {
+            boxcars::attributes::RemoteId::PlayStation(ref x) => result.extend(&x.unknown1),
+            boxcars::attributes::RemoteId::PsyNet(ref x) => result.extend(&x.unknown1),
+            boxcars::attributes::RemoteId::Switch(ref x) => result.extend(&x.unknown1),
+            _ => {}
+        }
+    }
+    result
+}
+
+#[test]
+fn test_trim_before_goal() {
+    let data = include_bytes!("../assets/replays/good/rumble.replay");
+    let replay = ParserBuilder::new(&data[..])
+        .must_parse_network_data()
+        .parse()
+        .unwrap();
+
+    let header = replay.typed_header().unwrap();
+    let goal = header.goals[1].frame as usize;
+    let frames = &replay.network_frames.as_ref().unwrap().frames;
+    let range = TrimRange::Time(frames[goal].time - 10.0..frames[goal].time + 1.0);
+    let clip = boxcars::trim(&replay, range).unwrap();
+
+    let written = ReplayWriter::new(&clip).write().unwrap();
+    let reparsed = ParserBuilder::new(&written[..])
+        .always_check_crc()
+        .must_parse_network_data()
+        .parse()
+        .unwrap();
+    assert_eq!(reparsed.network_frames, clip.network_frames);
+
+    // The clip starts at the keyframe before the range
+    let clip_frames = &reparsed.network_frames.as_ref().unwrap().frames;
+    let cut = replay
+        .keyframes
+        .iter()
+        .map(|x| x.frame as usize)
+        .filter(|&x| frames[x].time <= frames[goal].time - 10.0)
+        .max()
+        .unwrap();
+    assert_eq!(clip_frames[0].time, frames[cut].time);
+    assert_eq!(reparsed.keyframes[0].frame, 0);
+
+    let clip_header = reparsed.typed_header().unwrap();
+    assert_eq!(clip_header.num_frames, Some(clip_frames.len() as i32));
+    assert_eq!(clip_header.goals.len(), 1);
+    assert_eq!(clip_header.goals[0].frame as usize, goal - cut);
+
+    // Every actor that is updated has been spawned within the clip
+    let tables = ClassTables::default();
+    let mut tracker = boxcars::ActorStateTracker::new(&reparsed.objects, &tables);
+    for frame in clip_frames {
+        tracker.process_frame(frame).unwrap();
+    }
+}
+
+#[test]
+fn test_error_report() {
+    let data = include_bytes!("../assets/replays/good/rumble.replay");
+    let tables = ClassTables::new()
+        .with_attribute("TAGame.Ball_TA:HitTeamNum", AttributeTag::NotImplemented);
+    let error = ParserBuilder::new(&data[..])
+        .with_tables(&tables)
+        .must_parse_network_data()
+        .parse()
+        .unwrap_err();
+
+    let report = error.report(5);
+    assert_eq!(report.sections, vec!["network data"]);
+    assert_eq!(
+        report.attribute_name.as_deref(),
+        Some("TAGame.Ball_TA:HitTeamNum")
+    );
+    assert_eq!(report.attribute_known, Some(true));
+    assert_eq!(
+        report.object_name.as_deref(),
+        Some("Archetypes.Ball.Ball_Default")
+    );
+    assert_eq!(report.recent_updates.len(), 5);
+    assert!(report.bit_position.is_some());
+
+    // The partially parsed frames end where the report says decoding failed
+    let replay = ParserBuilder::new(&data[..])
+        .with_tables(&tables)
+        .partial_network_data_on_error()
+        .parse()
+        .unwrap();
+    assert_eq!(report.major_version, Some(replay.major_version));
+    assert_eq!(report.minor_version, Some(replay.minor_version));
+    let frames = replay.network_frames.unwrap().frames;
+    assert_eq!(report.frame, Some(frames.len()));
+
+    let partial = replay.network_error.unwrap().report(5);
+    assert_eq!(partial.frame, report.frame);
+    assert_eq!(partial.bit_position, report.bit_position);
+    assert_eq!(partial.attribute_name, report.attribute_name);
+
+    let json = serde_json::to_string(&report).unwrap();
+    assert!(json.contains("\"attribute_name\":\"TAGame.Ball_TA:HitTeamNum\""));
+}
+
+#[test]
+fn test_error_report_bad_replays() {
+    let data = include_bytes!("../assets/replays/bad/fuzz-large-object-id.replay");
+    let error = ParserBuilder::new(&data[..])
+        .never_check_crc()
+        .must_parse_network_data()
+        .parse()
+        .unwrap_err();
+    let report = error.report(5);
+    assert_eq!(report.message, error.to_string());
+    assert!(!report.sections.is_empty());
+
+    let data = include_bytes!("../assets/replays/bad/fuzz-string-too-long.replay");
+    let error = ParserBuilder::new(&data[..])
+        .on_error_check_crc()
+        .parse()
+        .unwrap_err();
+    let report = error.report(5);
+    assert!(report.corrupt);
+    assert!(report.byte_offset.is_some());
+    assert_eq!(report.frame, None);
+}
+
+#[test]
+fn test_probe_unknown_attribute() {
+    let data = include_bytes!("../assets/replays/good/rumble.replay");
+    let report = boxcars::AttributeProber::new(&data[..]).probe().unwrap();
+    assert!(report.failure.is_none());
+    assert!(report
+        .unknown_attributes
+        .iter()
+        .all(|x| x.name != "TAGame.Ball_TA:HitTeamNum"));
+
+    let tables = ClassTables::new()
+        .with_attribute("TAGame.Ball_TA:HitTeamNum", AttributeTag::NotImplemented);
+    let report = boxcars::AttributeProber::new(&data[..])
+        .with_tables(&tables)
+        .with_lookahead(20)
+        .with_dump_bits(8)
+        .probe()
+        .unwrap();
+    assert!(report
+        .unknown_attributes
+        .iter()
+        .any(|x| x.name == "TAGame.Ball_TA:HitTeamNum"));
+
+    let failure = report.failure.unwrap();
+    assert_eq!(failure.bits.len(), 8);
+    assert!(failure.bits.chars().all(|x| x == '0' || x == '1'));
+
+    let best = &failure.candidates[0];
+    assert!(best.continues);
+    assert_eq!(best.frames, 20);
+
+    // Decoding the attribute as the best candidate gets past the failure
+    let tables = ClassTables::new().with_attribute("TAGame.Ball_TA:HitTeamNum", best.tag);
+    let replay = ParserBuilder::new(&data[..])
+        .with_tables(&tables)
+        .partial_network_data_on_error()
+        .parse()
+        .unwrap();
+    let frames = replay.network_frames.unwrap().frames;
+    assert!(frames.len() > failure.report.frame.unwrap() + 20);
+}
+
+#[test]
+fn test_bit_spans() {
+    let data = include_bytes!("../assets/replays/good/rumble.replay");
+    let parse = |threads| {
+        ParserBuilder::new(&data[..])
+            .must_parse_network_data()
+            .with_network_threads(threads)
+            .parse_with_bit_spans()
+            .unwrap()
+    };
+
+    let (replay, spans) = parse(1);
+    assert_eq!(parse(4), (replay.clone(), spans.clone()));
+
+    let network = replay.network_frames.as_ref().unwrap();
+    let spans = spans.unwrap();
+    assert_eq!(spans.len(), network.frames.len());
+    assert_eq!(spans[0].frame.start, 0);
+
+    // Keyframes record where their frame starts
+    for keyframe in &replay.keyframes {
+        assert_eq!(
+            spans[keyframe.frame as usize].frame.start,
+            keyframe.position as usize
+        );
+    }
+
+    for (frame, span) in network.frames.iter().zip(spans.iter()) {
+        assert_eq!(span.new_actors.len(), frame.new_actors.len());
+        assert_eq!(span.updated_actors.len(), frame.updated_actors.len());
+        for attr in &span.updated_actors {
+            assert!(span.frame.start < attr.start && attr.end <= span.frame.end);
+        }
+    }
+
+    for pair in spans.windows(2) {
+        assert_eq!(pair[0].frame.end, pair[1].frame.start);
+    }
+
+    // The team that last hit the ball is a byte
+    let hit_team = replay
+        .objects
+        .iter()
+        .position(|x| x == "TAGame.Ball_TA:HitTeamNum")
+        .unwrap() as i32;
+    let (frame, ind) = network
+        .frames
+        .iter()
+        .enumerate()
+        .find_map(|(i, frame)| {
+            let ind = frame
+                .updated_actors
+                .iter()
+                .position(|x| x.object_id == ObjectId(hit_team))?;
+            Some((i, ind))
+        })
+        .unwrap();
+    let span = spans[frame].updated_actors[ind];
+    assert_eq!(span.end - span.start, 8);
+
+    // The streaming iterator exposes the spans of the frame it last yielded
+    let (_, frames) = ParserBuilder::new(&data[..]).parse_streaming().unwrap();
+    let mut frames = frames.with_bit_spans();
+    frames.seek_frame(100).unwrap();
+    frames.next().unwrap().unwrap();
+    assert_eq!(frames.spans(), Some(&spans[100]));
+
+    // Spans are not recorded by default
+    let (_, mut frames) = ParserBuilder::new(&data[..]).parse_streaming().unwrap();
+    frames.next().unwrap().unwrap();
+    assert!(frames.spans().is_none());
+}
+
+#[test]
+fn test_header_scan_all_replays() {
+    for entry in std::fs::read_dir("assets/replays/good").unwrap() {
+        let path = entry.unwrap().path();
+        let data = std::fs::read(&path).unwrap();
+        let replay = ParserBuilder::new(&data[..])
+            .never_parse_network_data()
+            .parse()
+            .unwrap();
+
+        let header = ParserBuilder::new(&data[..])
+            .always_check_crc()
+            .parse_header()
+            .unwrap();
+        assert_eq!(header.header_size, replay.header_size);
+        assert_eq!(header.header_crc, replay.header_crc);
+        assert_eq!(header.major_version, replay.major_version);
+        assert_eq!(header.minor_version, replay.minor_version);
+        assert_eq!(header.net_version, replay.net_version);
+        assert_eq!(header.game_type, replay.game_type);
+        assert_eq!(header.properties().unwrap(), replay.properties);
+
+        // Looking up every key matches the first property with that key
+        let keys: Vec<&str> = replay.properties.iter().map(|(k, _)| k.as_str()).collect();
+        let found = header.properties_for(&keys).unwrap();
+        for (key, prop) in keys.iter().zip(found) {
+            let expected = replay
+                .properties
+                .iter()
+                .find(|(k, _)| k == key)
+                .map(|(_, v)| v);
+            assert_eq!(prop.as_ref(), expected, "{} in {}", key, path.display());
+        }
+
+        assert_eq!(header.property("NotAProperty").unwrap(), None);
+    }
+}
+
+#[test]
+fn test_header_scan_crc() {
+    let data = include_bytes!("../assets/replays/good/rumble.replay");
+    let header = ParserBuilder::new(&data[..]).parse_header().unwrap();
+
+    // Corrupt the length of the first property's key, which follows the header size and crc,
+    // the versions, and the game type
+    let net_version = if header.net_version.is_some() { 4 } else { 0 };
+    let offset = 8 + 8 + net_version + 4 + header.game_type.len() + 1;
+    let mut data = data.to_vec();
+    data[offset..offset + 4].copy_from_slice(&[0xff, 0xff, 0x00, 0x00]);
+
+    // The crc is only checked on demand, when a lookup fails
+    let header = ParserBuilder::new(&data[..]).parse_header().unwrap();
+    assert!(matches!(
+        header.property("MapName"),
+        Err(ParseError::CorruptReplay(_, _))
+    ));
+
+    let header = ParserBuilder::new(&data[..])
+        .never_check_crc()
+        .parse_header()
+        .unwrap();
+    assert!(matches!(
+        header.property("MapName"),
+        Err(ParseError::ParseError("header properties", _, _))
+    ));
+
+    let error = ParserBuilder::new(&data[..])
+        .always_check_crc()
+        .parse_header()
+        .unwrap_err();
+    assert!(matches!(error, ParseError::CrcMismatch(_, _)));
+}